@@ -0,0 +1,169 @@
+use std::convert::TryFrom;
+
+use crate::msg::{AllocPoint, AllocPointsResponse, Role};
+use crate::rewards::{accrue_pool_reward, checkpoint_reward_index};
+use crate::roles::assert_role;
+use crate::state::{
+    read_config, read_pool_info, store_pool_info, PoolAllocation, RewardAllocation,
+    POOL_ALLOCATIONS, REWARD_ALLOCATION,
+};
+use cosmwasm_std::{
+    CanonicalAddr, Decimal256, Deps, DepsMut, Env, MessageInfo, Order, Response, StdError,
+    StdResult, Storage, Uint128, Uint256,
+};
+
+// the reward is only credited to reward_per_point here, each pool takes its share when it is settled
+pub fn deposit_allocated_reward(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+) -> StdResult<Response> {
+    let config = read_config(deps.storage)?;
+    if config.rewarder != deps.api.addr_canonicalize(info.sender.as_str())? {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    let mut allocation = REWARD_ALLOCATION
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    if allocation.total_alloc_point == 0 {
+        return Err(StdError::generic_err("No pool has an alloc point"));
+    }
+    let total_reward = amount + allocation.reward_dust;
+    let reward_per_point =
+        Decimal256::checked_from_ratio(total_reward, allocation.total_alloc_point)
+            .map_err(|_| StdError::generic_err("Allocated reward is too large"))?;
+    allocation.reward_per_point += reward_per_point;
+
+    // the truncated part is not in reward_per_point, keep it for the next deposit
+    let distributed = Uint256::from(allocation.total_alloc_point) * reward_per_point;
+    allocation.reward_dust = Uint128::try_from(Uint256::from(total_reward) - distributed)?;
+
+    let now = env.block.time.seconds();
+    if allocation.last_deposit_time != 0 && now > allocation.last_deposit_time {
+        allocation.reward_per_sec =
+            amount.multiply_ratio(1u128, now - allocation.last_deposit_time);
+    }
+    allocation.last_deposit_time = now;
+    REWARD_ALLOCATION.save(deps.storage, &allocation)?;
+
+    Ok(Response::new().add_attributes([
+        ("action", "deposit_allocated_reward"),
+        ("amount", &amount.to_string()),
+    ]))
+}
+
+pub fn update_alloc_points(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    alloc_points: Vec<AllocPoint>,
+) -> StdResult<Response> {
+    assert_role(deps.as_ref(), &info.sender, Role::RewardOperator)?;
+
+    // the deposits so far are shared with the old points
+    settle_all_pool_allocations(deps.storage, env.block.height)?;
+
+    let mut allocation = REWARD_ALLOCATION
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    for AllocPoint {
+        staking_token,
+        alloc_point,
+    } in alloc_points
+    {
+        let asset_key = deps.api.addr_canonicalize(staking_token.as_str())?;
+        read_pool_info(deps.storage, &asset_key)?;
+        set_alloc_point(deps.storage, &mut allocation, &asset_key, alloc_point)?;
+    }
+    REWARD_ALLOCATION.save(deps.storage, &allocation)?;
+
+    Ok(Response::new().add_attributes([
+        ("action", "update_alloc_points"),
+        (
+            "total_alloc_point",
+            &allocation.total_alloc_point.to_string(),
+        ),
+    ]))
+}
+
+pub fn settle_all_pool_allocations(storage: &mut dyn Storage, height: u64) -> StdResult<()> {
+    let asset_keys = POOL_ALLOCATIONS
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<Vec<u8>>>>()?;
+    for asset_key in asset_keys {
+        settle_pool_allocation(storage, &asset_key)?;
+        checkpoint_reward_index(storage, &asset_key, height)?;
+    }
+    Ok(())
+}
+
+// the pool must be settled already, the caller stores the allocation
+pub fn set_alloc_point(
+    storage: &mut dyn Storage,
+    allocation: &mut RewardAllocation,
+    asset_key: &[u8],
+    alloc_point: u64,
+) -> StdResult<()> {
+    if let Some(pool_allocation) = POOL_ALLOCATIONS.may_load(storage, asset_key)? {
+        allocation.total_alloc_point -= pool_allocation.alloc_point;
+    }
+    if alloc_point == 0 {
+        POOL_ALLOCATIONS.remove(storage, asset_key);
+        return Ok(());
+    }
+    allocation.total_alloc_point += alloc_point;
+    POOL_ALLOCATIONS.save(
+        storage,
+        asset_key,
+        &PoolAllocation {
+            alloc_point,
+            reward_per_point: allocation.reward_per_point,
+        },
+    )
+}
+
+// move the share of the pool from the deposits since the last settlement into its reward_index
+pub fn settle_pool_allocation(storage: &mut dyn Storage, asset_key: &[u8]) -> StdResult<()> {
+    let mut pool_allocation = match POOL_ALLOCATIONS.may_load(storage, asset_key)? {
+        Some(pool_allocation) => pool_allocation,
+        None => return Ok(()),
+    };
+    let allocation = REWARD_ALLOCATION.load(storage)?;
+    if pool_allocation.reward_per_point == allocation.reward_per_point {
+        return Ok(());
+    }
+
+    let amount = Uint128::try_from(
+        Uint256::from(pool_allocation.alloc_point)
+            * (allocation.reward_per_point - pool_allocation.reward_per_point),
+    )?;
+    let mut pool_info = read_pool_info(storage, asset_key)?;
+    accrue_pool_reward(storage, asset_key, &mut pool_info, amount)?;
+    store_pool_info(storage, asset_key, &pool_info)?;
+
+    pool_allocation.reward_per_point = allocation.reward_per_point;
+    POOL_ALLOCATIONS.save(storage, asset_key, &pool_allocation)
+}
+
+pub fn query_alloc_points(deps: Deps) -> StdResult<AllocPointsResponse> {
+    let allocation = REWARD_ALLOCATION
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    let alloc_points = POOL_ALLOCATIONS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (asset_key, pool_allocation) = item?;
+            Ok(AllocPoint {
+                staking_token: deps.api.addr_humanize(&CanonicalAddr::from(asset_key))?,
+                alloc_point: pool_allocation.alloc_point,
+            })
+        })
+        .collect::<StdResult<Vec<AllocPoint>>>()?;
+
+    Ok(AllocPointsResponse {
+        total_alloc_point: allocation.total_alloc_point,
+        alloc_points,
+    })
+}
@@ -1,30 +1,71 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 
+use crate::allocation::{deposit_allocated_reward, query_alloc_points, update_alloc_points};
+use crate::fee::{
+    query_performance_fee_totals, query_unbond_fee_split, query_unbond_fee_totals,
+    update_performance_fee, update_unbond_fee_split,
+};
+use crate::gauge::{
+    advance_gauge_epoch, query_gauge_config, query_gauge_epoch, query_gauge_epochs,
+    query_gauge_vote, update_gauge_config, vote_gauge,
+};
+use crate::operator::{approve_operator, query_operators, resolve_staker, revoke_operator};
+use crate::positions::{
+    index_staker_positions, query_pool_stakers, query_staker_pools, query_top_stakers,
+};
 use crate::rewards::{
-    deposit_reward, process_reward_assets, query_all_reward_infos, query_reward_info,
-    withdraw_reward, withdraw_reward_others,
+    checkpoint_reward_index, close_reward_epoch, deposit_reward, query_all_reward_infos,
+    query_pool_apr, query_reward_info, query_stranded_rewards, settle_reward_info,
+    simulate_pool_sync, split_pending_reward, swap_reward_reply, sync_pool_rewards,
+    withdraw_reward, withdraw_reward_others, MAX_SWAP_REWARDS, SWAP_REWARD_REPLY_ID,
+};
+use crate::roles::{
+    assert_role, grant_role, query_paused, query_role_members, revoke_role, set_paused,
+};
+use crate::schedule::{add_reward_schedule, query_reward_schedules};
+use crate::staking::{
+    bond, claim_unbonded, restake, simulate_unbond, unbond, validate_max_unbond_fee,
+    validate_unbond_option, validate_unbonding_period,
 };
-use crate::staking::{bond, restake, unbond};
 use crate::state::{
-    read_all_pool_infos, read_config, read_pool_info, read_rewards_per_sec, read_unbonding_period,
-    read_user_lock_info, stakers_read, store_config, store_pool_info, store_rewards_per_sec,
-    store_unbonding_period, Config, PoolInfo, STAKED_BALANCES, STAKED_TOTAL, UNBOND_OPTIONS,
+    count_unlocked_lock_infos, pool_infos_read, read_all_pool_infos, read_all_user_lock_info,
+    read_config, read_pool_info, read_rewards_per_sec, read_total_rewards_per_sec,
+    read_unbonding_period, read_user_lock_info, rewards_read, store_config, store_pool_info,
+    store_rewards_per_sec, store_unbonding_period, Config, PoolInfo, RewardInfo, DEFAULT_LIMIT,
+    DEFAULT_TIMELOCK_DELAY, LOCK_ZAP_OUT, MAX_LIMIT, MAX_LOCKS_PER_TX, MAX_UNBOND_FEE,
+    PERFORMANCE_FEES, POOL_STATS, REWARD_INDEX_HISTORY, STAKED_BALANCES, STAKED_TOTAL,
+    TIMELOCK_DELAY, UNBOND_OPTIONS, UNIQUE_STAKERS_BACKFILL,
 };
+use crate::stats::{count_unique_stakers, query_claimed_rewards, query_pool_stats};
+use crate::timelock::{
+    cancel_admin_change, execute_admin_change, query_pending_admin_changes, query_timelock_delay,
+    submit_admin_change, update_timelock_delay,
+};
+use crate::zap::{zap_bond, zap_bond_reply, zap_out_reply, ZAP_BOND_REPLY_ID, ZAP_OUT_REPLY_ID};
 
 use crate::msg::{
-    ConfigResponse, Cw20HookMsg, ExecuteMsg, InstantiateMsg, LockInfoResponse, LockInfosResponse,
-    MigrateMsg, PoolInfoResponse, QueryMsg, QueryPoolInfoResponse, RewardsPerSecResponse,
-    StakedBalanceAtHeightResponse, TotalStakedAtHeightResponse, UnbondOptionResponse,
+    AdminChange, ConfigResponse, Cw20HookMsg, EmptyPoolPolicy, ExecuteMsg, InstantiateMsg,
+    LockInfo, LockInfoResponse, LockInfosResponse, LockStatus, MaturedLocksResponse, MigrateMsg,
+    OperatorScope, PoolInfoResponse, PoolStatus, PoolsResponse, PoolsResponseItem, PortfolioItem,
+    QueryMsg, QueryPoolInfoResponse, RewardIndexAtHeightResponse, RewardsPerSecResponse, Role,
+    StakedBalanceAtHeightResponse, StakerPortfolioResponse, TotalStakedAtHeightResponse,
+    UnbondFeeSplit, UnbondOptionResponse,
 };
 use cosmwasm_std::{
-    from_binary, to_binary, Addr, Api, Binary, CanonicalAddr, Decimal, Deps, DepsMut, Env,
-    MessageInfo, Order, Response, StdError, StdResult, Storage, Uint128,
+    from_binary, to_binary, Addr, Api, Binary, CanonicalAddr, Decimal, Decimal256, Deps, DepsMut,
+    Env, MessageInfo, Order, Reply, Response, StdError, StdResult, Storage, Uint128,
 };
-use oraiswap::asset::{Asset, AssetRaw};
+use oraiswap::asset::{Asset, AssetInfo, AssetRaw};
+use oraiswap::querier::calc_range_start;
 
+use cw2::set_contract_version;
 use cw20::Cw20ReceiveMsg;
 
+// version info for migration info
+const CONTRACT_NAME: &str = "crates.io:cw20-staking";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
@@ -42,8 +83,17 @@ pub fn instantiate(
             withdraw_fee_receiver: deps
                 .api
                 .addr_canonicalize(msg.withdraw_fee_receiver.as_str())?,
+            router: None,
+            empty_pool_policy: EmptyPoolPolicy::default(),
+            fee_collector: None,
+            factory: None,
         },
     )?;
+    TIMELOCK_DELAY.save(
+        deps.storage,
+        &msg.timelock_delay.unwrap_or(DEFAULT_TIMELOCK_DELAY),
+    )?;
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
     Ok(Response::default())
 }
 
@@ -55,22 +105,122 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
             rewarder,
             owner,
             withdraw_fee_receiver,
-        } => update_config(deps, info, owner, rewarder, withdraw_fee_receiver),
+            router,
+            empty_pool_policy,
+            fee_collector,
+            factory,
+        } => update_config(
+            deps,
+            env,
+            info,
+            owner,
+            rewarder,
+            withdraw_fee_receiver,
+            router,
+            empty_pool_policy,
+            fee_collector,
+            factory,
+        ),
         ExecuteMsg::UpdateRewardsPerSec {
             staking_token,
             assets,
-        } => update_rewards_per_sec(deps, info, staking_token, assets),
-        ExecuteMsg::DepositReward { rewards } => deposit_reward(deps, info, rewards),
+        } => update_rewards_per_sec(deps, env, info, staking_token, assets),
+        ExecuteMsg::DepositReward { rewards } => deposit_reward(deps, env, info, rewards),
+        ExecuteMsg::DepositAllocatedReward { amount } => {
+            deposit_allocated_reward(deps, env, info, amount)
+        }
+        ExecuteMsg::UpdateAllocPoints { alloc_points } => {
+            update_alloc_points(deps, env, info, alloc_points)
+        }
+        ExecuteMsg::UpdateGaugeConfig {
+            governance_token,
+            epoch_duration,
+        } => update_gauge_config(deps, env, info, governance_token, epoch_duration),
+        ExecuteMsg::VoteGauge { weights } => vote_gauge(deps, info, weights),
+        ExecuteMsg::AdvanceGaugeEpoch {} => advance_gauge_epoch(deps, env),
+        ExecuteMsg::GrantRole { role, addr } => grant_role(deps, info, role, addr),
+        ExecuteMsg::RevokeRole { role, addr } => revoke_role(deps, info, role, addr),
+        ExecuteMsg::SetPaused { paused } => set_paused(deps, info, paused),
+        ExecuteMsg::UpdateTimelockDelay { delay } => update_timelock_delay(deps, env, info, delay),
+        ExecuteMsg::ExecuteAdminChange { id } => execute_admin_change(deps, env, id),
+        ExecuteMsg::CancelAdminChange { id } => cancel_admin_change(deps, info, id),
+        ExecuteMsg::IndexStakerPositions {
+            staking_token,
+            start_after,
+            limit,
+        } => index_staker_positions(deps, info, staking_token, start_after, limit),
+        ExecuteMsg::CountUniqueStakers {
+            staking_token,
+            limit,
+        } => count_unique_stakers(deps, info, staking_token, limit),
         ExecuteMsg::RegisterAsset {
             staking_token,
             unbonding_period,
-        } => register_asset(deps, info, staking_token, unbonding_period),
+        } => register_asset(deps, env, info, staking_token, unbonding_period),
         ExecuteMsg::Unbond {
             staking_token,
             amount,
             unbond_period,
-        } => unbond(deps, env, info.sender, staking_token, amount, unbond_period),
-        ExecuteMsg::Withdraw { staking_token } => withdraw_reward(deps, env, info, staking_token),
+            zap_out,
+            staker_addr,
+        } => {
+            let staker_addr = resolve_staker(
+                deps.as_ref(),
+                &env,
+                &info.sender,
+                staker_addr,
+                OperatorScope::Unbond,
+            )?;
+            // the pair to zap out through is chosen by the staker only
+            if zap_out.is_some() && staker_addr.ne(&info.sender) {
+                return Err(StdError::generic_err("Operator is not allowed to zap out"));
+            }
+            // so is the unbond period, a shorter one costs the staker a fee
+            if unbond_period.is_some() && staker_addr.ne(&info.sender) {
+                return Err(StdError::generic_err(
+                    "Operator is not allowed to choose the unbond period",
+                ));
+            }
+            unbond(
+                deps,
+                env,
+                staker_addr,
+                staking_token,
+                amount,
+                unbond_period,
+                zap_out,
+            )
+        }
+        ExecuteMsg::Withdraw {
+            staking_token,
+            swap_to,
+            max_spread,
+            belief_prices,
+            staker_addr,
+        } => {
+            let staker_addr = resolve_staker(
+                deps.as_ref(),
+                &env,
+                &info.sender,
+                staker_addr,
+                OperatorScope::Claim,
+            )?;
+            // so is the asset to swap the rewards into
+            if swap_to.is_some() && staker_addr.ne(&info.sender) {
+                return Err(StdError::generic_err(
+                    "Operator is not allowed to swap rewards",
+                ));
+            }
+            withdraw_reward(
+                deps,
+                env,
+                staker_addr,
+                staking_token,
+                swap_to,
+                max_spread,
+                belief_prices,
+            )
+        }
         ExecuteMsg::WithdrawOthers {
             staking_token,
             staker_addrs,
@@ -78,17 +228,131 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
         ExecuteMsg::UpdateUnbondingPeriod {
             staking_token,
             unbonding_period,
-        } => execute_update_unbonding_period(deps, info, staking_token, unbonding_period),
-        ExecuteMsg::Restake { staking_token } => restake(deps, env, info.sender, staking_token),
+        } => execute_update_unbonding_period(deps, env, info, staking_token, unbonding_period),
+        ExecuteMsg::Restake {
+            staking_token,
+            staker_addr,
+            limit,
+        } => {
+            let staker_addr = resolve_staker(
+                deps.as_ref(),
+                &env,
+                &info.sender,
+                staker_addr,
+                OperatorScope::Restake,
+            )?;
+            restake(deps, env, staker_addr, staking_token, limit)
+        }
+        ExecuteMsg::ClaimUnbonded {
+            staking_token,
+            staker_addr,
+            limit,
+        } => {
+            let staker_addr = resolve_staker(
+                deps.as_ref(),
+                &env,
+                &info.sender,
+                staker_addr,
+                OperatorScope::Unbond,
+            )?;
+            claim_unbonded(deps, env, staker_addr, staking_token, limit)
+        }
         ExecuteMsg::UpdateUnbondOption {
             staking_token,
             period,
             fee,
-        } => execute_update_unbond_option(deps, info, staking_token, period, fee),
+        } => execute_update_unbond_option(deps, env, info, staking_token, period, fee),
         ExecuteMsg::RemoveUnbondOption {
             staking_token,
             period,
-        } => execute_remove_unbond_option(deps, info, staking_token, period),
+        } => execute_remove_unbond_option(deps, env, info, staking_token, period),
+        ExecuteMsg::UpdateUnbondFeeSplit {
+            treasury,
+            stakers,
+            burn,
+        } => update_unbond_fee_split(
+            deps,
+            env,
+            info,
+            UnbondFeeSplit {
+                treasury,
+                stakers,
+                burn,
+            },
+        ),
+        ExecuteMsg::UpdatePerformanceFee { staking_token, fee } => {
+            update_performance_fee(deps, env, info, staking_token, fee)
+        }
+        ExecuteMsg::UpdateMaxUnbondFee { max_fee } => {
+            execute_update_max_unbond_fee(deps, env, info, max_fee)
+        }
+        ExecuteMsg::ZapBond {
+            pair_addr,
+            assets,
+            slippage_tolerance,
+            minimum_bond_amount,
+        } => zap_bond(
+            deps,
+            env,
+            info,
+            pair_addr,
+            assets,
+            slippage_tolerance,
+            minimum_bond_amount,
+        ),
+        ExecuteMsg::ApproveOperator {
+            operator,
+            expires,
+            scopes,
+        } => approve_operator(deps, env, info, operator, expires, scopes),
+        ExecuteMsg::RevokeOperator { operator } => revoke_operator(deps, info, operator),
+        ExecuteMsg::AddRewardSchedule {
+            staking_token,
+            asset,
+            start_time,
+            end_time,
+        } => {
+            let denom = match &asset.info {
+                AssetInfo::NativeToken { denom } => denom,
+                AssetInfo::Token { .. } => {
+                    return Err(StdError::generic_err(
+                        "Cw20 rewards must be sent through the AddRewardSchedule hook",
+                    ))
+                }
+            };
+            let sent = info
+                .funds
+                .iter()
+                .find(|c| c.denom.eq(denom))
+                .map(|c| c.amount)
+                .unwrap_or_default();
+            if sent != asset.amount {
+                return Err(StdError::generic_err(
+                    "Native token balance mismatch between the argument and the transferred",
+                ));
+            }
+            add_reward_schedule(
+                deps,
+                env,
+                info.sender,
+                staking_token,
+                asset,
+                start_time,
+                end_time,
+            )
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> StdResult<Response> {
+    match msg.id {
+        ZAP_BOND_REPLY_ID => zap_bond_reply(deps, env, msg),
+        ZAP_OUT_REPLY_ID => zap_out_reply(deps, env),
+        id if (SWAP_REWARD_REPLY_ID..SWAP_REWARD_REPLY_ID + MAX_SWAP_REWARDS).contains(&id) => {
+            swap_reward_reply(deps, msg)
+        }
+        _ => Err(StdError::generic_err("invalid reply id")),
     }
 }
 
@@ -106,16 +370,40 @@ pub fn receive_cw20(
             info.sender,
             cw20_msg.amount,
         ),
+        Ok(Cw20HookMsg::AddRewardSchedule {
+            staking_token,
+            start_time,
+            end_time,
+        }) => add_reward_schedule(
+            deps,
+            env,
+            Addr::unchecked(cw20_msg.sender),
+            staking_token,
+            Asset {
+                info: AssetInfo::Token {
+                    contract_addr: info.sender,
+                },
+                amount: cw20_msg.amount,
+            },
+            start_time,
+            end_time,
+        ),
         Err(_) => Err(StdError::generic_err("invalid cw20 hook message")),
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn update_config(
-    deps: DepsMut,
+    mut deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     owner: Option<Addr>,
     rewarder: Option<Addr>,
     withdraw_fee_receiver: Option<Addr>,
+    router: Option<Addr>,
+    empty_pool_policy: Option<EmptyPoolPolicy>,
+    fee_collector: Option<Addr>,
+    factory: Option<Addr>,
 ) -> StdResult<Response> {
     let mut config: Config = read_config(deps.storage)?;
 
@@ -123,58 +411,65 @@ pub fn update_config(
         return Err(StdError::generic_err("unauthorized"));
     }
 
-    if let Some(owner) = owner {
-        config.owner = deps.api.addr_canonicalize(owner.as_str())?;
+    if let Some(router) = router {
+        config.router = Some(deps.api.addr_canonicalize(router.as_str())?);
     }
 
-    if let Some(rewarder) = rewarder {
-        config.rewarder = deps.api.addr_canonicalize(rewarder.as_str())?;
+    if let Some(empty_pool_policy) = empty_pool_policy {
+        config.empty_pool_policy = empty_pool_policy;
     }
 
-    if let Some(withdraw_fee_receiver) = withdraw_fee_receiver {
-        config.withdraw_fee_receiver =
-            deps.api.addr_canonicalize(withdraw_fee_receiver.as_str())?;
+    if let Some(factory) = factory {
+        config.factory = Some(deps.api.addr_canonicalize(factory.as_str())?);
     }
 
     store_config(deps.storage, &config)?;
-    Ok(Response::new().add_attribute("action", "update_config"))
+    let mut response = Response::new().add_attribute("action", "update_config");
+
+    // the owner, the rewarder and the fee receivers go through the timelock
+    let mut changes = vec![];
+    if let Some(owner) = owner {
+        deps.api.addr_validate(owner.as_str())?;
+        changes.push(AdminChange::Owner { owner });
+    }
+    if let Some(rewarder) = rewarder {
+        deps.api.addr_validate(rewarder.as_str())?;
+        changes.push(AdminChange::Rewarder { rewarder });
+    }
+    if let Some(withdraw_fee_receiver) = withdraw_fee_receiver {
+        changes.push(AdminChange::WithdrawFeeReceiver {
+            withdraw_fee_receiver,
+        });
+    }
+    if let Some(fee_collector) = fee_collector {
+        changes.push(AdminChange::FeeCollector { fee_collector });
+    }
+    for change in changes {
+        response =
+            response.add_attributes(submit_admin_change(deps.branch(), &env, change)?.attributes);
+    }
+
+    Ok(response)
 }
 
-// need to withdraw all rewards of the stakers belong to the pool
-// may need to call withdraw from backend side by querying all stakers with pagination in case out of gas
+// starts a new reward epoch, the rewards of the finished epoch are split with the old rates when each staker is touched
 fn update_rewards_per_sec(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     staking_token: Addr,
     assets: Vec<Asset>,
 ) -> StdResult<Response> {
-    let config: Config = read_config(deps.storage)?;
-
-    if deps.api.addr_canonicalize(info.sender.as_str())? != config.owner {
-        return Err(StdError::generic_err("unauthorized"));
-    }
+    assert_role(deps.as_ref(), &info.sender, Role::RewardOperator)?;
 
     let asset_key = deps.api.addr_canonicalize(staking_token.as_str())?.to_vec();
 
-    // withdraw all rewards for all stakers from this pool
-    let staker_addrs = stakers_read(deps.storage, &asset_key)
-        .range(None, None, Order::Ascending)
-        .map(|item| {
-            let (k, _) = item?;
-            Ok(CanonicalAddr::from(k))
-        })
-        .collect::<StdResult<Vec<CanonicalAddr>>>()?;
-
-    // let mut messages: Vec<CosmosMsg> = vec![];
-
-    // withdraw reward for each staker
-    for staker_addr_raw in staker_addrs {
-        process_reward_assets(
-            deps.storage,
-            &staker_addr_raw,
-            &Some(asset_key.clone()),
-            false,
-        )?;
+    // emit the allocated deposits and running schedules up to now, then close the current epoch
+    // with the old rates, each staker settles it on the next interaction
+    sync_pool_rewards(deps.storage, &asset_key, &env.block)?;
+    if let Ok(mut pool_info) = read_pool_info(deps.storage, &asset_key) {
+        close_reward_epoch(deps.storage, &asset_key, &mut pool_info)?;
+        store_pool_info(deps.storage, &asset_key, &pool_info)?;
     }
 
     // convert assets to raw_assets
@@ -190,15 +485,12 @@ fn update_rewards_per_sec(
 
 fn register_asset(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     staking_token: Addr,
     unbonding_period: Option<u64>,
 ) -> StdResult<Response> {
-    let config: Config = read_config(deps.storage)?;
-
-    if config.owner != deps.api.addr_canonicalize(info.sender.as_str())? {
-        return Err(StdError::generic_err("unauthorized"));
-    }
+    assert_role(deps.as_ref(), &info.sender, Role::PoolManager)?;
 
     // query asset_key from AssetInfo
     let asset_key = deps.api.addr_canonicalize(staking_token.as_str())?;
@@ -212,10 +504,15 @@ fn register_asset(
         &PoolInfo {
             staking_token: asset_key.clone(),
             total_bond_amount: Uint128::zero(),
-            reward_index: Decimal::zero(),
+            reward_index: Decimal256::zero(),
+            reward_dust: Uint128::zero(),
             pending_reward: Uint128::zero(),
+            epoch: 0,
+            fee_index: Decimal256::zero(),
         },
     )?;
+    // the reward index history starts with the pool
+    checkpoint_reward_index(deps.storage, &asset_key, env.block.height)?;
 
     if let Some(unbonding_period) = unbonding_period {
         if unbonding_period > 0 {
@@ -233,72 +530,91 @@ fn register_asset(
     ]))
 }
 
+// existing locks keep the unlock time they were created with
 fn execute_update_unbonding_period(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     staking_token: Addr,
     unbonding_period: u64,
 ) -> StdResult<Response> {
-    let config: Config = read_config(deps.storage)?;
+    assert_role(deps.as_ref(), &info.sender, Role::PoolManager)?;
 
-    if config.owner != deps.api.addr_canonicalize(info.sender.as_str())? {
-        return Err(StdError::generic_err("unauthorized"));
-    }
-
-    let asset_key = deps.api.addr_canonicalize(staking_token.as_str())?;
-    store_unbonding_period(deps.storage, &asset_key, unbonding_period)?;
+    // checked again when a queued change is applied
+    validate_unbonding_period(deps.storage, &staking_token, unbonding_period)?;
 
-    Ok(Response::new()
-        .add_attribute("action", "update_unbonding_period")
-        .add_attribute("unbonding_period", unbonding_period.to_string()))
+    submit_admin_change(
+        deps,
+        &env,
+        AdminChange::UnbondingPeriod {
+            staking_token,
+            unbonding_period,
+        },
+    )
 }
 
 fn execute_update_unbond_option(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     staking_token: Addr,
     unbonding_period: u64,
     fee: Decimal,
 ) -> StdResult<Response> {
-    let config: Config = read_config(deps.storage)?;
-
-    if config.owner != deps.api.addr_canonicalize(info.sender.as_str())? {
-        return Err(StdError::generic_err("unauthorized"));
-    }
+    assert_role(deps.as_ref(), &info.sender, Role::FeeManager)?;
 
-    // validate fee
-    if fee.gt(&Decimal::one()) {
-        return Err(StdError::generic_err(
-            "Unbond fee must be less than or equal 1",
-        ));
-    }
-    UNBOND_OPTIONS.save(deps.storage, (&staking_token, unbonding_period), &fee)?;
+    // checked again when a queued change is applied
+    validate_unbond_option(
+        deps.storage,
+        deps.api,
+        &staking_token,
+        unbonding_period,
+        fee,
+    )?;
 
-    Ok(Response::new()
-        .add_attribute("action", "update_instant_withdraw_option")
-        .add_attribute("staking_token", staking_token.to_string())
-        .add_attribute("unbonding_period", unbonding_period.to_string())
-        .add_attribute("fee", fee.to_string()))
+    submit_admin_change(
+        deps,
+        &env,
+        AdminChange::UnbondOption {
+            staking_token,
+            period: unbonding_period,
+            fee,
+        },
+    )
 }
 
 fn execute_remove_unbond_option(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     staking_token: Addr,
     unbonding_period: u64,
 ) -> StdResult<Response> {
-    let config: Config = read_config(deps.storage)?;
+    assert_role(deps.as_ref(), &info.sender, Role::FeeManager)?;
+
+    submit_admin_change(
+        deps,
+        &env,
+        AdminChange::RemoveUnbondOption {
+            staking_token,
+            period: unbonding_period,
+        },
+    )
+}
 
+fn execute_update_max_unbond_fee(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    max_fee: Decimal,
+) -> StdResult<Response> {
+    let config = read_config(deps.storage)?;
     if config.owner != deps.api.addr_canonicalize(info.sender.as_str())? {
         return Err(StdError::generic_err("unauthorized"));
     }
 
-    UNBOND_OPTIONS.remove(deps.storage, (&staking_token, unbonding_period));
-
-    Ok(Response::new()
-        .add_attribute("action", "remove_instant_withdraw_option")
-        .add_attribute("staking_token", staking_token.to_string())
-        .add_attribute("unbonding_period", unbonding_period.to_string()))
+    validate_max_unbond_fee(max_fee)?;
+    submit_admin_change(deps, &env, AdminChange::MaxUnbondFee { max_fee })
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -312,7 +628,11 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
         QueryMsg::RewardInfo {
             staker_addr,
             staking_token,
-        } => to_binary(&query_reward_info(deps, staker_addr, staking_token)?),
+        } => to_binary(&query_reward_info(deps, env, staker_addr, staking_token)?),
+        QueryMsg::ClaimedRewards {
+            staker_addr,
+            staking_token,
+        } => to_binary(&query_claimed_rewards(deps, staker_addr, staking_token)?),
         QueryMsg::RewardInfos {
             staking_token,
             start_after,
@@ -320,12 +640,46 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
             order,
         } => to_binary(&query_all_reward_infos(
             deps,
+            env,
             staking_token,
             start_after,
             limit,
             order,
         )?),
         QueryMsg::GetPoolsInformation {} => to_binary(&query_get_pools_infomation(deps)?),
+        QueryMsg::PoolStakers {
+            staking_token,
+            start_after,
+            limit,
+        } => to_binary(&query_pool_stakers(
+            deps,
+            staking_token,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::StakerPools {
+            staker_addr,
+            start_after,
+            limit,
+        } => to_binary(&query_staker_pools(deps, staker_addr, start_after, limit)?),
+        QueryMsg::TopStakers {
+            staking_token,
+            limit,
+        } => to_binary(&query_top_stakers(deps, staking_token, limit)?),
+        QueryMsg::Pools {
+            start_after,
+            limit,
+            order,
+            status,
+            has_rewards,
+        } => to_binary(&query_pools(
+            deps,
+            start_after,
+            limit,
+            order,
+            status,
+            has_rewards,
+        )?),
         QueryMsg::LockInfos {
             staker_addr,
             staking_token,
@@ -351,6 +705,15 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
         QueryMsg::TotalStakedAtHeight { asset_key, height } => {
             to_binary(&query_total_staked_at_height(deps, env, asset_key, height)?)
         }
+        QueryMsg::RewardIndexAtHeight {
+            staking_token,
+            height,
+        } => to_binary(&query_reward_index_at_height(
+            deps,
+            env,
+            staking_token,
+            height,
+        )?),
         QueryMsg::UnbondFee {
             staking_token,
             period,
@@ -358,12 +721,90 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
         QueryMsg::UnbondOptions { staking_token } => {
             to_binary(&query_unbond_options(deps, staking_token)?)
         }
+        QueryMsg::UnbondFeeSplit {} => to_binary(&query_unbond_fee_split(deps)?),
+        QueryMsg::UnbondFeeTotals { staking_token } => {
+            to_binary(&query_unbond_fee_totals(deps, staking_token)?)
+        }
+        QueryMsg::PoolStats { staking_token } => to_binary(&query_pool_stats(deps, staking_token)?),
+        QueryMsg::PerformanceFeeTotals { staking_token } => {
+            to_binary(&query_performance_fee_totals(deps, staking_token)?)
+        }
+        QueryMsg::MaxUnbondFee {} => to_binary(
+            &MAX_UNBOND_FEE
+                .may_load(deps.storage)?
+                .unwrap_or(Decimal::one()),
+        ),
+        QueryMsg::MaturedLocks {
+            staker_addr,
+            staking_token,
+        } => to_binary(&query_matured_locks(deps, env, staker_addr, staking_token)?),
+        QueryMsg::StakerPortfolio { staker_addr } => {
+            to_binary(&query_staker_portfolio(deps, env, staker_addr)?)
+        }
+        QueryMsg::SimulateUnbond {
+            staker_addr,
+            staking_token,
+            amount,
+            unbond_period,
+        } => to_binary(&simulate_unbond(
+            deps,
+            env,
+            staker_addr,
+            staking_token,
+            amount,
+            unbond_period,
+        )?),
+        QueryMsg::PoolApr {
+            staking_token,
+            pair_addr,
+            usd_denom,
+        } => to_binary(&query_pool_apr(
+            deps,
+            env,
+            staking_token,
+            pair_addr,
+            usd_denom,
+        )?),
+        QueryMsg::Operators {
+            staker_addr,
+            start_after,
+            limit,
+        } => to_binary(&query_operators(deps, staker_addr, start_after, limit)?),
+        QueryMsg::RewardSchedules {
+            staking_token,
+            start_after,
+            limit,
+        } => to_binary(&query_reward_schedules(
+            deps,
+            env,
+            staking_token,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::AllocPoints {} => to_binary(&query_alloc_points(deps)?),
+        QueryMsg::RoleMembers {
+            role,
+            start_after,
+            limit,
+        } => to_binary(&query_role_members(deps, role, start_after, limit)?),
+        QueryMsg::Paused {} => to_binary(&query_paused(deps)?),
+        QueryMsg::StrandedRewards {} => to_binary(&query_stranded_rewards(deps)?),
+        QueryMsg::GaugeConfig {} => to_binary(&query_gauge_config(deps)?),
+        QueryMsg::GaugeEpoch { epoch } => to_binary(&query_gauge_epoch(deps, epoch)?),
+        QueryMsg::GaugeEpochs { start_after, limit } => {
+            to_binary(&query_gauge_epochs(deps, start_after, limit)?)
+        }
+        QueryMsg::GaugeVote { voter, epoch } => to_binary(&query_gauge_vote(deps, voter, epoch)?),
+        QueryMsg::TimelockDelay {} => to_binary(&query_timelock_delay(deps)?),
+        QueryMsg::PendingAdminChanges { start_after, limit } => {
+            to_binary(&query_pending_admin_changes(deps, start_after, limit)?)
+        }
     }
 }
 
 pub fn query_lock_infos(
     deps: Deps,
-    _env: Env,
+    env: Env,
     staker_addr: Addr,
     staking_token: Addr,
     start_after: Option<u64>,
@@ -378,25 +819,174 @@ pub fn query_lock_infos(
         limit,
         order,
     )?;
+    let lock_infos = lock_infos
+        .into_iter()
+        .map(|lock| _lock_info_response(deps.storage, &env, &staking_token, &staker_addr, lock))
+        .collect::<StdResult<Vec<LockInfoResponse>>>()?;
+
+    let mut claimable_amount = Uint128::zero();
+    let mut locked_amount = Uint128::zero();
+    let mut next_unlock_time = None;
+    for lock in read_all_user_lock_info(
+        deps.storage,
+        staking_token.as_bytes(),
+        staker_addr.as_bytes(),
+    )? {
+        if lock.unlock_time <= env.block.time {
+            claimable_amount += lock.amount;
+        } else {
+            locked_amount += lock.amount;
+            // locks are in ascending order
+            next_unlock_time = next_unlock_time.or(Some(lock.unlock_time.seconds()));
+        }
+    }
+
     Ok(LockInfosResponse {
         staker_addr,
         staking_token,
-        lock_infos: lock_infos
-            .into_iter()
-            .map(|lock| LockInfoResponse {
-                amount: lock.amount,
-                unlock_time: lock.unlock_time.seconds(),
-            })
-            .collect(),
+        lock_infos,
+        claimable_amount,
+        locked_amount,
+        next_unlock_time,
+    })
+}
+
+pub fn query_matured_locks(
+    deps: Deps,
+    env: Env,
+    staker_addr: Addr,
+    staking_token: Addr,
+) -> StdResult<MaturedLocksResponse> {
+    let (count, amount) = count_unlocked_lock_infos(
+        deps.storage,
+        staking_token.as_bytes(),
+        staker_addr.as_bytes(),
+        env.block.time,
+    )?;
+    Ok(MaturedLocksResponse {
+        count,
+        amount,
+        max_per_tx: MAX_LOCKS_PER_TX,
+    })
+}
+
+fn _lock_info_response(
+    storage: &dyn Storage,
+    env: &Env,
+    staking_token: &Addr,
+    staker_addr: &Addr,
+    lock: LockInfo,
+) -> StdResult<LockInfoResponse> {
+    let zap_out = LOCK_ZAP_OUT.may_load(
+        storage,
+        (
+            staking_token.as_bytes(),
+            staker_addr.as_bytes(),
+            lock.unlock_time.seconds(),
+        ),
+    )?;
+    let status = if lock.unlock_time <= env.block.time {
+        LockStatus::Claimable
+    } else {
+        LockStatus::Locked
+    };
+    Ok(LockInfoResponse {
+        amount: lock.amount,
+        unlock_time: lock.unlock_time.seconds(),
+        zap_out,
+        status,
     })
 }
 
+pub fn query_staker_portfolio(
+    deps: Deps,
+    env: Env,
+    staker_addr: Addr,
+) -> StdResult<StakerPortfolioResponse> {
+    let staker_addr_raw = deps.api.addr_canonicalize(staker_addr.as_str())?;
+    let rewards_bucket = rewards_read(deps.storage, &staker_addr_raw);
+
+    let mut pools = vec![];
+    for (asset_key, pool_info) in read_all_pool_infos(deps.storage)? {
+        let staking_token = deps.api.addr_humanize(&pool_info.staking_token)?;
+        let reward_info = rewards_bucket.may_load(&asset_key)?;
+        let synced = simulate_pool_sync(deps.storage, &asset_key, &env.block)?;
+        let pool_info = read_pool_info(&synced, &asset_key)?;
+        let lock_infos = read_all_user_lock_info(
+            deps.storage,
+            staking_token.as_bytes(),
+            staker_addr.as_bytes(),
+        )?;
+
+        // skip the pools without any position
+        let mut reward_info = match reward_info {
+            None if lock_infos.is_empty() => continue,
+            reward_info => reward_info.unwrap_or_else(|| RewardInfo {
+                native_token: false,
+                index: pool_info.reward_index,
+                bond_amount: Uint128::zero(),
+                pending_reward: Uint128::zero(),
+                pending_withdraw: vec![],
+                epoch: pool_info.epoch,
+                fee_index: pool_info.fee_index,
+                pending_fee_share: Uint128::zero(),
+            }),
+        };
+
+        settle_reward_info(&synced, &asset_key, &pool_info, &mut reward_info)?;
+        split_pending_reward(&synced, &asset_key, &mut reward_info)?;
+        let pending_rewards = reward_info
+            .pending_withdraw
+            .into_iter()
+            .map(|pw| pw.to_normal(deps.api))
+            .collect::<StdResult<Vec<Asset>>>()?;
+
+        let mut claimable_locks = vec![];
+        let mut locked = vec![];
+        for lock in lock_infos {
+            let lock = _lock_info_response(deps.storage, &env, &staking_token, &staker_addr, lock)?;
+            match lock.status {
+                LockStatus::Claimable => claimable_locks.push(lock),
+                LockStatus::Locked => locked.push(lock),
+            }
+        }
+
+        let staked_balance = STAKED_BALANCES
+            .may_load_at_height(deps.storage, (&asset_key, &staker_addr), env.block.height)?
+            .unwrap_or_default();
+
+        pools.push(PortfolioItem {
+            staking_token,
+            bond_amount: reward_info.bond_amount,
+            pending_rewards,
+            claimable_locks,
+            locked,
+            staked_balance,
+        });
+    }
+
+    Ok(StakerPortfolioResponse { staker_addr, pools })
+}
+
 pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
     let state = read_config(deps.storage)?;
     let resp = ConfigResponse {
         owner: deps.api.addr_humanize(&state.owner)?,
         rewarder: deps.api.addr_humanize(&state.rewarder)?,
         withdraw_fee_receiver: deps.api.addr_humanize(&state.withdraw_fee_receiver)?,
+        router: state
+            .router
+            .map(|router| deps.api.addr_humanize(&router))
+            .transpose()?,
+        empty_pool_policy: state.empty_pool_policy,
+        fee_collector: state
+            .fee_collector
+            .map(|fee_collector| deps.api.addr_humanize(&fee_collector))
+            .transpose()?,
+        factory: state
+            .factory
+            .map(|factory| deps.api.addr_humanize(&factory))
+            .transpose()?,
     };
 
     Ok(resp)
@@ -412,6 +1002,9 @@ pub fn query_pool_info(deps: Deps, staking_token: Addr) -> StdResult<PoolInfoRes
         reward_index: pool_info.reward_index,
         pending_reward: pool_info.pending_reward,
         unbonding_period,
+        performance_fee: PERFORMANCE_FEES
+            .may_load(deps.storage, &asset_key)?
+            .unwrap_or_default(),
     })
 }
 
@@ -447,6 +1040,9 @@ pub fn parse_read_all_pool_infos(
                     reward_index: pool_info.reward_index,
                     pending_reward: pool_info.pending_reward,
                     unbonding_period,
+                    performance_fee: PERFORMANCE_FEES
+                        .may_load(storage, &asset_key)?
+                        .unwrap_or_default(),
                 },
             })
         })
@@ -458,6 +1054,76 @@ pub fn query_get_pools_infomation(deps: Deps) -> StdResult<Vec<QueryPoolInfoResp
     parse_read_all_pool_infos(deps.storage, deps.api, pool_infos)
 }
 
+pub fn query_pools(
+    deps: Deps,
+    start_after: Option<Addr>,
+    limit: Option<u32>,
+    order: Option<i32>,
+    status: Option<PoolStatus>,
+    has_rewards: Option<bool>,
+) -> StdResult<PoolsResponse> {
+    // default is Ascending
+    let order_by = Order::try_from(order.unwrap_or(1))?;
+    let start_after = start_after
+        .and_then(|a| deps.api.addr_canonicalize(a.as_str()).ok())
+        .map(|c| c.to_vec());
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    let (start, end) = match order_by {
+        Order::Ascending => (calc_range_start(start_after), None),
+        Order::Descending => (None, start_after),
+    };
+
+    let mut pools = vec![];
+    let mut scanned = 0;
+    let mut last_scanned = None;
+    for item in pool_infos_read(deps.storage)
+        .range(start.as_deref(), end.as_deref(), order_by)
+        .take(limit)
+    {
+        let (asset_key, pool_info) = item?;
+        scanned += 1;
+        last_scanned = Some(pool_info.staking_token.clone());
+        let pool_status = if pool_info.total_bond_amount.is_zero() {
+            PoolStatus::Empty
+        } else {
+            PoolStatus::Active
+        };
+        if status
+            .as_ref()
+            .map_or(false, |status| status != &pool_status)
+        {
+            continue;
+        }
+        let rewards_per_sec = read_total_rewards_per_sec(deps.storage, &asset_key)?;
+        let pool_has_rewards = rewards_per_sec.iter().any(|asset| !asset.amount.is_zero());
+        if has_rewards.map_or(false, |has_rewards| has_rewards != pool_has_rewards) {
+            continue;
+        }
+
+        let staking_token = deps.api.addr_humanize(&pool_info.staking_token)?;
+        pools.push(PoolsResponseItem {
+            pool_info: query_pool_info(deps, staking_token.clone())?,
+            status: pool_status,
+            rewards_per_sec: rewards_per_sec
+                .into_iter()
+                .map(|asset| asset.to_normal(deps.api))
+                .collect::<StdResult<Vec<Asset>>>()?,
+            unbond_options: query_unbond_options(deps, staking_token)?,
+        });
+    }
+
+    // a short page means the pools are exhausted
+    let next_start_after = match last_scanned {
+        Some(last_scanned) if scanned == limit => Some(deps.api.addr_humanize(&last_scanned)?),
+        _ => None,
+    };
+    Ok(PoolsResponse {
+        pools,
+        next_start_after,
+    })
+}
+
 pub fn query_staked_balance_at_height(
     deps: Deps,
     env: Env,
@@ -488,6 +1154,27 @@ pub fn query_total_staked_at_height(
     Ok(TotalStakedAtHeightResponse { total, height })
 }
 
+// like the staked balances, the index at a height is the one before the changes of that block
+pub fn query_reward_index_at_height(
+    deps: Deps,
+    env: Env,
+    staking_token: Addr,
+    height: Option<u64>,
+) -> StdResult<RewardIndexAtHeightResponse> {
+    let asset_key = deps.api.addr_canonicalize(staking_token.as_str())?.to_vec();
+    let height = height.unwrap_or(env.block.height);
+    // the history starts when the pool is registered, or with the migration for older pools
+    let reward_index = REWARD_INDEX_HISTORY
+        .may_load_at_height(deps.storage, &asset_key, height)?
+        .ok_or_else(|| {
+            StdError::generic_err(format!("Reward index is not recorded at height {}", height))
+        })?;
+    Ok(RewardIndexAtHeightResponse {
+        reward_index,
+        height,
+    })
+}
+
 pub fn query_unbond_options(
     deps: Deps,
     staking_token: Addr,
@@ -505,7 +1192,19 @@ pub fn query_unbond_options(
 
 // migrate contract
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(deps: DepsMut, _env: Env, msg: MigrateMsg) -> StdResult<Response> {
+pub fn migrate(deps: DepsMut, env: Env, msg: MigrateMsg) -> StdResult<Response> {
+    // keep the optional settings of the current config
+    let (router, empty_pool_policy, fee_collector, factory) = read_config(deps.storage).map_or(
+        (None, EmptyPoolPolicy::default(), None, None),
+        |config| {
+            (
+                config.router,
+                config.empty_pool_policy,
+                config.fee_collector,
+                config.factory,
+            )
+        },
+    );
     store_config(
         deps.storage,
         &Config {
@@ -514,7 +1213,29 @@ pub fn migrate(deps: DepsMut, _env: Env, msg: MigrateMsg) -> StdResult<Response>
             withdraw_fee_receiver: deps
                 .api
                 .addr_canonicalize(msg.withdraw_fee_receiver.as_str())?,
+            router,
+            empty_pool_policy,
+            fee_collector,
+            factory,
         },
     )?;
+
+    // older versions applied admin changes right away
+    if TIMELOCK_DELAY.may_load(deps.storage)?.is_none() {
+        TIMELOCK_DELAY.save(deps.storage, &DEFAULT_TIMELOCK_DELAY)?;
+    }
+
+    // the reward index history starts from the current index of the pools. The stakers of the
+    // pools without stats are counted by CountUniqueStakers, a page at a time
+    for (asset_key, _) in read_all_pool_infos(deps.storage)? {
+        checkpoint_reward_index(deps.storage, &asset_key, env.block.height)?;
+        if !POOL_STATS.has(deps.storage, &asset_key) {
+            UNIQUE_STAKERS_BACKFILL.save(deps.storage, &asset_key, &vec![])?;
+        }
+    }
+
+    // reward indexes are stored as decimal strings, so the Decimal indexes of older versions
+    // load as Decimal256 as they are and reward_dust starts from zero
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
     Ok(Response::default())
 }
@@ -0,0 +1,183 @@
+use std::convert::TryFrom;
+
+use crate::msg::{AdminChange, Role, UnbondFeeSplit, UnbondFeeTotalsResponse};
+use crate::roles::assert_role;
+use crate::state::{
+    read_config, read_pool_info, store_pool_info, MAX_PERFORMANCE_FEE, PERFORMANCE_FEE_TOTALS,
+    UNBOND_FEE_SPLIT, UNBOND_FEE_TOTALS,
+};
+use crate::stats::update_pool_stats;
+use crate::timelock::submit_admin_change;
+use cosmwasm_std::{
+    to_binary, Addr, Api, CosmosMsg, Decimal, Decimal256, Deps, DepsMut, Env, MessageInfo,
+    Response, StdError, StdResult, Storage, Uint128, Uint256, WasmMsg,
+};
+use cw20::Cw20ExecuteMsg;
+use oraiswap::asset::Asset;
+
+pub fn update_unbond_fee_split(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    split: UnbondFeeSplit,
+) -> StdResult<Response> {
+    assert_role(deps.as_ref(), &info.sender, Role::FeeManager)?;
+
+    validate_unbond_fee_split(&split)?;
+    submit_admin_change(
+        deps,
+        &env,
+        AdminChange::UnbondFeeSplit {
+            treasury: split.treasury,
+            stakers: split.stakers,
+            burn: split.burn,
+        },
+    )
+}
+
+pub fn validate_unbond_fee_split(split: &UnbondFeeSplit) -> StdResult<()> {
+    if split.treasury + split.stakers + split.burn != Decimal::one() {
+        return Err(StdError::generic_err("Unbond fee split must sum up to 1"));
+    }
+    Ok(())
+}
+
+// share the fee withheld from an unbond, the unbonded amount must have left the pool already.
+// The stakers share goes into fee_index, the part truncated from it and the whole share of an
+// emptied pool go to the treasury
+pub fn distribute_unbond_fee(
+    storage: &mut dyn Storage,
+    api: &dyn Api,
+    staking_token: &Addr,
+    asset_key: &[u8],
+    fee_amount: Uint128,
+) -> StdResult<Vec<CosmosMsg>> {
+    if fee_amount.is_zero() {
+        return Ok(vec![]);
+    }
+
+    let split = UNBOND_FEE_SPLIT.may_load(storage)?.unwrap_or_default();
+    let burn_amount = fee_amount * split.burn;
+    let mut stakers_amount = fee_amount * split.stakers;
+
+    let mut pool_info = read_pool_info(storage, asset_key)?;
+    if pool_info.total_bond_amount.is_zero() {
+        stakers_amount = Uint128::zero();
+    } else if !stakers_amount.is_zero() {
+        let fee_per_bond = Decimal256::from_ratio(stakers_amount, pool_info.total_bond_amount);
+        pool_info.fee_index += fee_per_bond;
+        store_pool_info(storage, asset_key, &pool_info)?;
+        stakers_amount =
+            Uint128::try_from(Uint256::from(pool_info.total_bond_amount) * fee_per_bond)?;
+    }
+    let treasury_amount = fee_amount - stakers_amount - burn_amount;
+
+    let mut totals = UNBOND_FEE_TOTALS
+        .may_load(storage, asset_key)?
+        .unwrap_or_default();
+    totals.treasury += treasury_amount;
+    totals.stakers += stakers_amount;
+    totals.burned += burn_amount;
+    UNBOND_FEE_TOTALS.save(storage, asset_key, &totals)?;
+    update_pool_stats(storage, asset_key, |stats| stats.unbond_fees += fee_amount)?;
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+    if !treasury_amount.is_zero() {
+        let config = read_config(storage)?;
+        messages.push(
+            WasmMsg::Execute {
+                contract_addr: staking_token.to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: api
+                        .addr_humanize(&config.withdraw_fee_receiver)?
+                        .to_string(),
+                    amount: treasury_amount,
+                })?,
+                funds: vec![],
+            }
+            .into(),
+        );
+    }
+    if !burn_amount.is_zero() {
+        messages.push(
+            WasmMsg::Execute {
+                contract_addr: staking_token.to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Burn {
+                    amount: burn_amount,
+                })?,
+                funds: vec![],
+            }
+            .into(),
+        );
+    }
+    Ok(messages)
+}
+
+pub fn update_performance_fee(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    staking_token: Addr,
+    fee: Decimal,
+) -> StdResult<Response> {
+    assert_role(deps.as_ref(), &info.sender, Role::FeeManager)?;
+
+    // checked again when a queued change is applied
+    validate_performance_fee(deps.storage, deps.api, &staking_token, fee)?;
+
+    submit_admin_change(
+        deps,
+        &env,
+        AdminChange::PerformanceFee { staking_token, fee },
+    )
+}
+
+pub fn validate_performance_fee(
+    storage: &dyn Storage,
+    api: &dyn Api,
+    staking_token: &Addr,
+    fee: Decimal,
+) -> StdResult<()> {
+    if fee > MAX_PERFORMANCE_FEE {
+        return Err(StdError::generic_err(format!(
+            "Performance fee must be less than or equal {}",
+            MAX_PERFORMANCE_FEE
+        )));
+    }
+    let asset_key = api.addr_canonicalize(staking_token.as_str())?;
+    if read_pool_info(storage, &asset_key).is_err() {
+        return Err(StdError::generic_err("Staking token is not registered"));
+    }
+    Ok(())
+}
+
+pub fn query_unbond_fee_split(deps: Deps) -> StdResult<UnbondFeeSplit> {
+    Ok(UNBOND_FEE_SPLIT.may_load(deps.storage)?.unwrap_or_default())
+}
+
+pub fn query_performance_fee_totals(deps: Deps, staking_token: Addr) -> StdResult<Vec<Asset>> {
+    let asset_key = deps.api.addr_canonicalize(staking_token.as_str())?;
+    PERFORMANCE_FEE_TOTALS
+        .may_load(deps.storage, &asset_key)?
+        .unwrap_or_default()
+        .into_iter()
+        .map(|fee| fee.to_normal(deps.api))
+        .collect()
+}
+
+pub fn query_unbond_fee_totals(
+    deps: Deps,
+    staking_token: Addr,
+) -> StdResult<UnbondFeeTotalsResponse> {
+    let asset_key = deps.api.addr_canonicalize(staking_token.as_str())?;
+    let totals = UNBOND_FEE_TOTALS
+        .may_load(deps.storage, &asset_key)?
+        .unwrap_or_default();
+
+    Ok(UnbondFeeTotalsResponse {
+        staking_token,
+        treasury: totals.treasury,
+        stakers: totals.stakers,
+        burned: totals.burned,
+    })
+}
@@ -0,0 +1,300 @@
+use crate::allocation::{set_alloc_point, settle_all_pool_allocations};
+use crate::msg::{
+    GaugeConfigResponse, GaugeEpochResponse, GaugeTally, GaugeVoteResponse, GaugeWeight, Role,
+};
+use crate::roles::assert_role;
+use crate::state::{
+    read_pool_info, GaugeConfig, GaugeEpoch, GaugeVoteInfo, DEFAULT_LIMIT, GAUGE_CONFIG,
+    GAUGE_EPOCHS, GAUGE_EPOCH_ID, GAUGE_TALLIES, GAUGE_VOTES, MAX_LIMIT, POOL_ALLOCATIONS,
+    REWARD_ALLOCATION, STAKED_BALANCES,
+};
+use cosmwasm_std::{
+    Addr, Decimal, Deps, DepsMut, Env, MessageInfo, Order, Response, StdError, StdResult, Storage,
+    Uint128,
+};
+use cw_storage_plus::Bound;
+
+// alloc points given to the pools at rollover, in basis points of the tally
+const GAUGE_ALLOC_POINTS: u128 = 10_000;
+
+pub fn update_gauge_config(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    governance_token: Addr,
+    epoch_duration: u64,
+) -> StdResult<Response> {
+    assert_role(deps.as_ref(), &info.sender, Role::PoolManager)?;
+
+    read_pool_info(
+        deps.storage,
+        &deps.api.addr_canonicalize(governance_token.as_str())?,
+    )?;
+    if epoch_duration == 0 {
+        return Err(StdError::generic_err("Epoch duration must not be zero"));
+    }
+
+    GAUGE_CONFIG.save(
+        deps.storage,
+        &GaugeConfig {
+            governance_token: governance_token.clone(),
+            epoch_duration,
+        },
+    )?;
+
+    // the running epoch keeps its end time
+    if !GAUGE_EPOCH_ID.exists(deps.storage) {
+        start_gauge_epoch(deps.storage, &env, 1, epoch_duration)?;
+    }
+
+    Ok(Response::new().add_attributes([
+        ("action", "update_gauge_config"),
+        ("governance_token", governance_token.as_str()),
+        ("epoch_duration", &epoch_duration.to_string()),
+    ]))
+}
+
+pub fn vote_gauge(
+    deps: DepsMut,
+    info: MessageInfo,
+    weights: Vec<GaugeWeight>,
+) -> StdResult<Response> {
+    let gauge_config = GAUGE_CONFIG
+        .may_load(deps.storage)?
+        .ok_or_else(|| StdError::generic_err("Gauge voting is not enabled"))?;
+    let epoch_id = GAUGE_EPOCH_ID.load(deps.storage)?;
+    let epoch = GAUGE_EPOCHS.load(deps.storage, epoch_id)?;
+
+    let mut total_weight = Decimal::zero();
+    for (i, gauge_weight) in weights.iter().enumerate() {
+        if gauge_weight
+            .staking_token
+            .eq(&gauge_config.governance_token)
+        {
+            return Err(StdError::generic_err("Cannot vote for the governance pool"));
+        }
+        if weights[..i]
+            .iter()
+            .any(|w| w.staking_token.eq(&gauge_weight.staking_token))
+        {
+            return Err(StdError::generic_err("Duplicate pool in the vote"));
+        }
+        read_pool_info(
+            deps.storage,
+            &deps
+                .api
+                .addr_canonicalize(gauge_weight.staking_token.as_str())?,
+        )?;
+        total_weight += gauge_weight.weight;
+    }
+    if total_weight > Decimal::one() {
+        return Err(StdError::generic_err("Vote weights must not exceed 1"));
+    }
+
+    let governance_key = deps
+        .api
+        .addr_canonicalize(gauge_config.governance_token.as_str())?
+        .to_vec();
+    let power = STAKED_BALANCES
+        .may_load_at_height(
+            deps.storage,
+            (&governance_key, &info.sender),
+            epoch.start_height,
+        )?
+        .unwrap_or_default();
+    if power.is_zero() {
+        return Err(StdError::generic_err(
+            "No governance bond at the start of the epoch",
+        ));
+    }
+
+    // replace the previous vote of this epoch
+    if let Some(prev_vote) = GAUGE_VOTES.may_load(deps.storage, (epoch_id, &info.sender))? {
+        for gauge_weight in prev_vote.weights {
+            GAUGE_TALLIES.update(
+                deps.storage,
+                (epoch_id, &gauge_weight.staking_token),
+                |votes| -> StdResult<Uint128> {
+                    Ok(votes
+                        .unwrap_or_default()
+                        .checked_sub(prev_vote.power * gauge_weight.weight)?)
+                },
+            )?;
+        }
+    }
+    for gauge_weight in weights.iter() {
+        GAUGE_TALLIES.update(
+            deps.storage,
+            (epoch_id, &gauge_weight.staking_token),
+            |votes| -> StdResult<Uint128> {
+                Ok(votes.unwrap_or_default() + power * gauge_weight.weight)
+            },
+        )?;
+    }
+    GAUGE_VOTES.save(
+        deps.storage,
+        (epoch_id, &info.sender),
+        &GaugeVoteInfo { power, weights },
+    )?;
+
+    Ok(Response::new().add_attributes([
+        ("action", "vote_gauge"),
+        ("voter", info.sender.as_str()),
+        ("epoch", &epoch_id.to_string()),
+        ("power", &power.to_string()),
+    ]))
+}
+
+// anyone can roll over a finished epoch
+pub fn advance_gauge_epoch(deps: DepsMut, env: Env) -> StdResult<Response> {
+    let gauge_config = GAUGE_CONFIG
+        .may_load(deps.storage)?
+        .ok_or_else(|| StdError::generic_err("Gauge voting is not enabled"))?;
+    let epoch_id = GAUGE_EPOCH_ID.load(deps.storage)?;
+    let epoch = GAUGE_EPOCHS.load(deps.storage, epoch_id)?;
+    if env.block.time.seconds() < epoch.end_time {
+        return Err(StdError::generic_err("Gauge epoch is not finished yet"));
+    }
+
+    let tallies = read_gauge_tallies(deps.storage, epoch_id)?;
+    let total_votes = tallies
+        .iter()
+        .fold(Uint128::zero(), |total, tally| total + tally.votes);
+
+    // without votes, the alloc points of the previous epoch are kept
+    if !total_votes.is_zero() {
+        // the deposits so far are shared with the old points
+        settle_all_pool_allocations(deps.storage, env.block.height)?;
+        let mut allocation = REWARD_ALLOCATION
+            .may_load(deps.storage)?
+            .unwrap_or_default();
+        let asset_keys = POOL_ALLOCATIONS
+            .keys(deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<Vec<u8>>>>()?;
+        for asset_key in asset_keys {
+            set_alloc_point(deps.storage, &mut allocation, &asset_key, 0)?;
+        }
+        for tally in tallies {
+            let asset_key = deps.api.addr_canonicalize(tally.staking_token.as_str())?;
+            let alloc_point = tally
+                .votes
+                .multiply_ratio(GAUGE_ALLOC_POINTS, total_votes)
+                .u128() as u64;
+            set_alloc_point(deps.storage, &mut allocation, &asset_key, alloc_point)?;
+        }
+        REWARD_ALLOCATION.save(deps.storage, &allocation)?;
+    }
+
+    start_gauge_epoch(
+        deps.storage,
+        &env,
+        epoch_id + 1,
+        gauge_config.epoch_duration,
+    )?;
+
+    Ok(Response::new().add_attributes([
+        ("action", "advance_gauge_epoch"),
+        ("epoch", &(epoch_id + 1).to_string()),
+        ("total_votes", &total_votes.to_string()),
+    ]))
+}
+
+fn start_gauge_epoch(
+    storage: &mut dyn Storage,
+    env: &Env,
+    epoch_id: u64,
+    epoch_duration: u64,
+) -> StdResult<()> {
+    GAUGE_EPOCH_ID.save(storage, &epoch_id)?;
+    GAUGE_EPOCHS.save(
+        storage,
+        epoch_id,
+        &GaugeEpoch {
+            start_time: env.block.time.seconds(),
+            start_height: env.block.height,
+            end_time: env.block.time.seconds() + epoch_duration,
+        },
+    )
+}
+
+fn read_gauge_tallies(storage: &dyn Storage, epoch_id: u64) -> StdResult<Vec<GaugeTally>> {
+    GAUGE_TALLIES
+        .prefix(epoch_id)
+        .range(storage, None, None, Order::Ascending)
+        .filter(|item| item.as_ref().map_or(true, |(_, votes)| !votes.is_zero()))
+        .map(|item| {
+            let (staking_token, votes) = item?;
+            Ok(GaugeTally {
+                staking_token,
+                votes,
+            })
+        })
+        .collect()
+}
+
+fn gauge_epoch_response(
+    storage: &dyn Storage,
+    epoch_id: u64,
+    epoch: GaugeEpoch,
+) -> StdResult<GaugeEpochResponse> {
+    Ok(GaugeEpochResponse {
+        epoch: epoch_id,
+        start_time: epoch.start_time,
+        start_height: epoch.start_height,
+        end_time: epoch.end_time,
+        tallies: read_gauge_tallies(storage, epoch_id)?,
+    })
+}
+
+pub fn query_gauge_config(deps: Deps) -> StdResult<GaugeConfigResponse> {
+    let gauge_config = GAUGE_CONFIG.load(deps.storage)?;
+    Ok(GaugeConfigResponse {
+        governance_token: gauge_config.governance_token,
+        epoch_duration: gauge_config.epoch_duration,
+        current_epoch: GAUGE_EPOCH_ID.load(deps.storage)?,
+    })
+}
+
+pub fn query_gauge_epoch(deps: Deps, epoch: Option<u64>) -> StdResult<GaugeEpochResponse> {
+    let epoch_id = match epoch {
+        Some(epoch_id) => epoch_id,
+        None => GAUGE_EPOCH_ID.load(deps.storage)?,
+    };
+    let epoch = GAUGE_EPOCHS.load(deps.storage, epoch_id)?;
+    gauge_epoch_response(deps.storage, epoch_id, epoch)
+}
+
+pub fn query_gauge_epochs(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<GaugeEpochResponse>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    GAUGE_EPOCHS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (epoch_id, epoch) = item?;
+            gauge_epoch_response(deps.storage, epoch_id, epoch)
+        })
+        .collect()
+}
+
+pub fn query_gauge_vote(
+    deps: Deps,
+    voter: Addr,
+    epoch: Option<u64>,
+) -> StdResult<Option<GaugeVoteResponse>> {
+    let epoch_id = match epoch {
+        Some(epoch_id) => epoch_id,
+        None => GAUGE_EPOCH_ID.load(deps.storage)?,
+    };
+    Ok(GAUGE_VOTES
+        .may_load(deps.storage, (epoch_id, &voter))?
+        .map(|vote| GaugeVoteResponse {
+            power: vote.power,
+            weights: vote.weights,
+        }))
+}
@@ -1,8 +1,19 @@
+mod allocation;
 pub mod contract;
+mod fee;
+mod gauge;
 pub mod msg;
+mod operator;
+mod overlay;
+mod positions;
 mod rewards;
+mod roles;
+mod schedule;
 mod staking;
 mod state;
+mod stats;
+mod timelock;
+mod zap;
 
 #[cfg(test)]
 mod testing;
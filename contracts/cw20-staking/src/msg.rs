@@ -1,7 +1,7 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
 
-use cosmwasm_std::{Addr, Decimal, Timestamp, Uint128};
-use cw20::Cw20ReceiveMsg;
+use cosmwasm_std::{Addr, Decimal, Decimal256, Timestamp, Uint128};
+use cw20::{Cw20ReceiveMsg, Expiration};
 use oraiswap::asset::{Asset, AssetInfo};
 
 #[cw_serde]
@@ -10,6 +10,8 @@ pub struct InstantiateMsg {
     pub owner: Option<Addr>,
     pub rewarder: Addr,
     pub withdraw_fee_receiver: Addr,
+    // seconds sensitive admin changes wait, default is one day
+    pub timelock_delay: Option<u64>,
 }
 
 #[cw_serde]
@@ -22,6 +24,13 @@ pub enum ExecuteMsg {
         rewarder: Option<Addr>,
         owner: Option<Addr>,
         withdraw_fee_receiver: Option<Addr>,
+        // oraiswap router used to swap claimed rewards
+        router: Option<Addr>,
+        empty_pool_policy: Option<EmptyPoolPolicy>,
+        // receives the performance fees, the withdraw_fee_receiver by default. Timelocked
+        fee_collector: Option<Addr>,
+        // oraiswap factory listing the pairs accepted for zapping
+        factory: Option<Addr>,
     },
     UpdateUnbondingPeriod {
         staking_token: Addr,
@@ -41,6 +50,51 @@ pub enum ExecuteMsg {
     DepositReward {
         rewards: Vec<RewardMsg>,
     },
+    /// Deposit a single reward amount, split among the pools by their alloc points
+    DepositAllocatedReward {
+        amount: Uint128,
+    },
+    /// Set the alloc points of the given pools, every pool is settled with the old points first
+    UpdateAllocPoints {
+        alloc_points: Vec<AllocPoint>,
+    },
+    /// Let the stakers of the governance pool vote on the alloc points, starts the first epoch
+    UpdateGaugeConfig {
+        governance_token: Addr,
+        epoch_duration: u64,
+    },
+    /// Split the sender's governance bond at the epoch start among the other pools,
+    /// replacing its previous vote of this epoch
+    VoteGauge {
+        weights: Vec<GaugeWeight>,
+    },
+    /// Apply the tally of the finished epoch as alloc points and start a new epoch
+    AdvanceGaugeEpoch {},
+    GrantRole {
+        role: Role,
+        addr: Addr,
+    },
+    RevokeRole {
+        role: Role,
+        addr: Addr,
+    },
+    /// Stop or resume bonding, zap bonding and restaking
+    SetPaused {
+        paused: bool,
+    },
+    /// Delay in seconds before unbonding periods, unbond options and the fee receiver change.
+    /// A longer delay applies right away, a shorter one is queued with the current delay
+    UpdateTimelockDelay {
+        delay: u64,
+    },
+    /// Apply a queued admin change once its eta has passed, anyone can execute it
+    ExecuteAdminChange {
+        id: u64,
+    },
+    /// Drop a queued admin change, allowed for the owner and the pausers
+    CancelAdminChange {
+        id: u64,
+    },
 
     ////////////////////////
     /// User operations ///
@@ -49,11 +103,24 @@ pub enum ExecuteMsg {
         staking_token: Addr,
         amount: Uint128,
         unbond_period: Option<u64>,
+        // withdraw the lp from the oraiswap pair when the lock is released
+        zap_out: Option<ZapOutMsg>,
+        // unbond for this staker as an approved operator, the lp always goes to the staker as is
+        staker_addr: Option<Addr>,
     },
     /// Withdraw pending rewards
     Withdraw {
         // If the asset token is not given, then all rewards are withdrawn
         staking_token: Option<Addr>,
+        // swap the reward assets with a belief price into this asset through the router, the others
+        // and the failed swaps are paid as is
+        swap_to: Option<AssetInfo>,
+        // required along with swap_to, bounds the price of each swap against its belief price
+        max_spread: Option<Decimal>,
+        #[serde(default)]
+        belief_prices: Vec<BeliefPrice>,
+        // withdraw for this staker as an approved operator, the rewards always go to the staker unswapped
+        staker_addr: Option<Addr>,
     },
     // Withdraw for others in this pool, such as when rewards per second are changed for the pool
     WithdrawOthers {
@@ -62,6 +129,18 @@ pub enum ExecuteMsg {
     },
     Restake {
         staking_token: Addr,
+        // restake for this staker as an approved operator
+        staker_addr: Option<Addr>,
+        // max number of locks to restake, capped by the contract
+        limit: Option<u32>,
+    },
+    /// Return the unlocked lp without unbonding more
+    ClaimUnbonded {
+        staking_token: Addr,
+        // claim for this staker as an approved operator, the lp always goes to the staker
+        staker_addr: Option<Addr>,
+        // max number of locks to claim, capped by the contract
+        limit: Option<u32>,
     },
     UpdateUnbondOption {
         staking_token: Addr,
@@ -72,12 +151,201 @@ pub enum ExecuteMsg {
         staking_token: Addr,
         period: u64,
     },
+    /// Share the unbond option fees between the withdraw_fee_receiver, the remaining stakers
+    /// of the pool and a burn, the parts must sum up to 1. Timelocked
+    UpdateUnbondFeeSplit {
+        treasury: Decimal,
+        stakers: Decimal,
+        burn: Decimal,
+    },
+    /// Keep this share of each reward asset withdrawn from the pool for the fee collector,
+    /// at most 20%. Timelocked
+    UpdatePerformanceFee {
+        staking_token: Addr,
+        fee: Decimal,
+    },
+    /// Cap the fee of unbond options, the existing options are kept as is. Timelocked
+    UpdateMaxUnbondFee {
+        max_fee: Decimal,
+    },
+    /// Provide liquidity to an oraiswap pair and bond the minted lp token in one transaction.
+    /// Native assets must be sent as funds, cw20 assets need an allowance for this contract
+    ZapBond {
+        pair_addr: Addr,
+        assets: [Asset; 2],
+        slippage_tolerance: Option<Decimal>,
+        // revert if the minted lp amount is less than this
+        minimum_bond_amount: Option<Uint128>,
+    },
+    /// Allow an operator to manage the sender's positions within the given scopes
+    ApproveOperator {
+        operator: Addr,
+        // default is never
+        expires: Option<Expiration>,
+        scopes: Vec<OperatorScope>,
+    },
+    RevokeOperator {
+        operator: Addr,
+    },
+    /// Queue a reward program emitted linearly from start_time to end_time, the native asset
+    /// must be sent as funds. Cw20 rewards are funded through the AddRewardSchedule hook
+    AddRewardSchedule {
+        staking_token: Addr,
+        asset: Asset,
+        start_time: u64,
+        end_time: u64,
+    },
+    /// Index the positions bonded before the staker indexes were introduced, a page of the
+    /// pool stakers at a time. The last_staker attribute is the start of the next page
+    IndexStakerPositions {
+        staking_token: Addr,
+        start_after: Option<Addr>,
+        limit: Option<u32>,
+    },
+    /// Count the stakers of a pool migrated before the pool stats into unique_stakers, a page at
+    /// a time from where the last call stopped. The done attribute is true on the last page
+    CountUniqueStakers {
+        staking_token: Addr,
+        limit: Option<u32>,
+    },
+}
+
+// the owner holds every role
+#[cw_serde]
+pub enum Role {
+    // RegisterAsset, UpdateUnbondingPeriod, UpdateGaugeConfig
+    PoolManager,
+    // UpdateUnbondOption, RemoveUnbondOption, UpdateUnbondFeeSplit, UpdatePerformanceFee
+    FeeManager,
+    // SetPaused, CancelAdminChange
+    Pauser,
+    // UpdateRewardsPerSec, AddRewardSchedule, UpdateAllocPoints
+    RewardOperator,
+    // WithdrawOthers, IndexStakerPositions, CountUniqueStakers
+    Keeper,
+}
+
+// the admin changes held back by the timelock delay
+#[cw_serde]
+pub enum AdminChange {
+    UnbondingPeriod {
+        staking_token: Addr,
+        unbonding_period: u64,
+    },
+    UnbondOption {
+        staking_token: Addr,
+        period: u64,
+        fee: Decimal,
+    },
+    RemoveUnbondOption {
+        staking_token: Addr,
+        period: u64,
+    },
+    WithdrawFeeReceiver {
+        withdraw_fee_receiver: Addr,
+    },
+    PerformanceFee {
+        staking_token: Addr,
+        fee: Decimal,
+    },
+    FeeCollector {
+        fee_collector: Addr,
+    },
+    TimelockDelay {
+        delay: u64,
+    },
+    Owner {
+        owner: Addr,
+    },
+    Rewarder {
+        rewarder: Addr,
+    },
+    UnbondFeeSplit {
+        treasury: Decimal,
+        stakers: Decimal,
+        burn: Decimal,
+    },
+    MaxUnbondFee {
+        max_fee: Decimal,
+    },
+}
+
+#[cw_serde]
+pub struct UnbondFeeSplit {
+    pub treasury: Decimal,
+    // paid in the staking token along with the rewards
+    pub stakers: Decimal,
+    pub burn: Decimal,
+}
+
+impl Default for UnbondFeeSplit {
+    fn default() -> Self {
+        UnbondFeeSplit {
+            treasury: Decimal::one(),
+            stakers: Decimal::zero(),
+            burn: Decimal::zero(),
+        }
+    }
+}
+
+// what happens to a DepositReward for a pool without any bond
+#[cw_serde]
+#[derive(Default)]
+pub enum EmptyPoolPolicy {
+    // keep it in pending_reward, released along with the next reward once the pool is bonded, in the
+    // assets it was deposited with
+    #[default]
+    Accumulate,
+    // send it back to the rewarder, split by rewards_per_sec, what the split truncates is kept
+    Refund,
+}
+
+#[cw_serde]
+pub struct AllocPoint {
+    pub staking_token: Addr,
+    pub alloc_point: u64,
+}
+
+#[cw_serde]
+pub struct GaugeWeight {
+    pub staking_token: Addr,
+    // share of the voting power, the weights of a vote sum up to at most 1
+    pub weight: Decimal,
+}
+
+#[cw_serde]
+pub enum OperatorScope {
+    // withdraw rewards, including swapping them
+    Claim,
+    Restake,
+    // unbond, the lp is always sent to the staker
+    Unbond,
+}
+
+// swap_to the staker expects for a unit of the reward asset, priced off chain
+#[cw_serde]
+pub struct BeliefPrice {
+    pub info: AssetInfo,
+    pub price: Decimal,
+}
+
+#[cw_serde]
+pub struct ZapOutMsg {
+    pub pair_addr: Addr,
+    // minimum amounts of the underlying assets to receive, otherwise the unbond fails
+    pub minimum_receive: Vec<Asset>,
 }
 
 #[cw_serde]
 pub enum Cw20HookMsg {
     // this call from LP token contract
     Bond {},
+    // this call from the reward token contract, the sender of the tokens must be the owner
+    AddRewardSchedule {
+        staking_token: Addr,
+        start_time: u64,
+        end_time: u64,
+    },
 }
 
 /// We currently take no arguments for migrations
@@ -110,6 +378,12 @@ pub enum QueryMsg {
         staker_addr: Addr,
         staking_token: Option<Addr>,
     },
+    // lifetime rewards paid to the staker, per pool and asset
+    #[returns(ClaimedRewardsResponse)]
+    ClaimedRewards {
+        staker_addr: Addr,
+        staking_token: Option<Addr>,
+    },
     #[returns(Vec<RewardInfoResponse>)]
     // Query all staker belong to the pool
     RewardInfos {
@@ -121,6 +395,38 @@ pub enum QueryMsg {
     },
     #[returns(Vec<QueryPoolInfoResponse>)]
     GetPoolsInformation {},
+    // stakers with a non zero bond, by address
+    #[returns(PoolStakersResponse)]
+    PoolStakers {
+        staking_token: Addr,
+        start_after: Option<Addr>,
+        limit: Option<u32>,
+    },
+    // pools the staker has a non zero bond in, by staking token
+    #[returns(Vec<StakerPositionResponse>)]
+    StakerPools {
+        staker_addr: Addr,
+        start_after: Option<Addr>,
+        limit: Option<u32>,
+    },
+    // stakers of the pool with the largest bonds first
+    #[returns(Vec<StakerPositionResponse>)]
+    TopStakers {
+        staking_token: Addr,
+        limit: Option<u32>,
+    },
+    // paginated by staking token, the limit bounds the scanned pools so a page can hold fewer
+    // matches, keep paging while next_start_after is set
+    #[returns(PoolsResponse)]
+    Pools {
+        start_after: Option<Addr>,
+        limit: Option<u32>,
+        // so can convert or throw error
+        order: Option<i32>,
+        status: Option<PoolStatus>,
+        // only the pools with a non zero rewards_per_sec
+        has_rewards: Option<bool>,
+    },
     #[returns(LockInfosResponse)]
     LockInfos {
         staker_addr: Addr,
@@ -142,10 +448,221 @@ pub enum QueryMsg {
         asset_key: Addr,
         height: Option<u64>,
     },
+    #[returns(RewardIndexAtHeightResponse)]
+    RewardIndexAtHeight {
+        staking_token: Addr,
+        height: Option<u64>,
+    },
     #[returns(Decimal)]
     UnbondFee { staking_token: Addr, period: u64 },
     #[returns(Vec<UnbondOptionResponse>)]
     UnbondOptions { staking_token: Addr },
+    #[returns(Decimal)]
+    MaxUnbondFee {},
+    #[returns(UnbondFeeSplit)]
+    UnbondFeeSplit {},
+    // the unbond fees collected by the pool so far
+    #[returns(UnbondFeeTotalsResponse)]
+    UnbondFeeTotals { staking_token: Addr },
+    // the performance fees kept back from the rewards of the pool so far
+    #[returns(Vec<Asset>)]
+    PerformanceFeeTotals { staking_token: Addr },
+    // lifetime totals of the pool, counted since they were introduced
+    #[returns(PoolStatsResponse)]
+    PoolStats { staking_token: Addr },
+    /// Unlocked entries that are not claimed yet, claims process a limited number of them a time
+    #[returns(MaturedLocksResponse)]
+    MaturedLocks {
+        staker_addr: Addr,
+        staking_token: Addr,
+    },
+    #[returns(StakerPortfolioResponse)]
+    StakerPortfolio { staker_addr: Addr },
+    #[returns(SimulateUnbondResponse)]
+    SimulateUnbond {
+        staker_addr: Addr,
+        staking_token: Addr,
+        amount: Uint128,
+        unbond_period: Option<u64>,
+    },
+    #[returns(PoolAprResponse)]
+    PoolApr {
+        staking_token: Addr,
+        // the oraiswap pair of the staking token, its oracle prices the rewards and the lp
+        pair_addr: Option<Addr>,
+        // the usd stable denom of the oracle the values are quoted in
+        usd_denom: Option<String>,
+    },
+    #[returns(Vec<OperatorResponse>)]
+    Operators {
+        staker_addr: Addr,
+        start_after: Option<Addr>,
+        limit: Option<u32>,
+    },
+    #[returns(Vec<RewardScheduleResponse>)]
+    RewardSchedules {
+        staking_token: Addr,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    #[returns(AllocPointsResponse)]
+    AllocPoints {},
+    // members granted the role, the owner is not listed
+    #[returns(Vec<Addr>)]
+    RoleMembers {
+        role: Role,
+        start_after: Option<Addr>,
+        limit: Option<u32>,
+    },
+    #[returns(bool)]
+    Paused {},
+    // pools holding rewards that were deposited while nothing was bonded
+    #[returns(Vec<StrandedRewardResponse>)]
+    StrandedRewards {},
+    #[returns(GaugeConfigResponse)]
+    GaugeConfig {},
+    // the current epoch by default
+    #[returns(GaugeEpochResponse)]
+    GaugeEpoch { epoch: Option<u64> },
+    #[returns(Vec<GaugeEpochResponse>)]
+    GaugeEpochs {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    #[returns(Option<GaugeVoteResponse>)]
+    GaugeVote { voter: Addr, epoch: Option<u64> },
+    #[returns(u64)]
+    TimelockDelay {},
+    #[returns(Vec<PendingAdminChangeResponse>)]
+    PendingAdminChanges {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+}
+
+#[cw_serde]
+pub struct PoolStatsResponse {
+    pub staking_token: Addr,
+    pub rewards_deposited: Vec<Asset>,
+    pub rewards_claimed: Vec<Asset>,
+    pub total_bonded: Uint128,
+    pub total_unbonded: Uint128,
+    pub unbond_fees: Uint128,
+    pub unique_stakers: u64,
+}
+
+#[cw_serde]
+pub struct UnbondFeeTotalsResponse {
+    pub staking_token: Addr,
+    pub treasury: Uint128,
+    pub stakers: Uint128,
+    pub burned: Uint128,
+}
+
+#[cw_serde]
+pub struct PendingAdminChangeResponse {
+    pub id: u64,
+    pub change: AdminChange,
+    // the change can be executed from this time
+    pub eta: u64,
+}
+
+#[cw_serde]
+pub struct GaugeConfigResponse {
+    pub governance_token: Addr,
+    pub epoch_duration: u64,
+    pub current_epoch: u64,
+}
+
+#[cw_serde]
+pub struct GaugeTally {
+    pub staking_token: Addr,
+    pub votes: Uint128,
+}
+
+#[cw_serde]
+pub struct GaugeEpochResponse {
+    pub epoch: u64,
+    pub start_time: u64,
+    pub start_height: u64,
+    pub end_time: u64,
+    pub tallies: Vec<GaugeTally>,
+}
+
+#[cw_serde]
+pub struct GaugeVoteResponse {
+    pub power: Uint128,
+    pub weights: Vec<GaugeWeight>,
+}
+
+#[cw_serde]
+pub struct StrandedRewardResponse {
+    pub staking_token: Addr,
+    pub pending_reward: Uint128,
+    // pending_reward split by the rewards_per_sec it was deposited with, what the split truncates
+    // is paid with the next reward
+    pub assets: Vec<Asset>,
+}
+
+#[cw_serde]
+pub struct AllocPointsResponse {
+    pub total_alloc_point: u64,
+    pub alloc_points: Vec<AllocPoint>,
+}
+
+#[cw_serde]
+pub enum RewardScheduleStatus {
+    Upcoming,
+    Active,
+    Finished,
+}
+
+#[cw_serde]
+pub struct RewardScheduleResponse {
+    pub id: u64,
+    pub asset: Asset,
+    pub start_time: u64,
+    pub end_time: u64,
+    pub status: RewardScheduleStatus,
+}
+
+#[cw_serde]
+pub struct SimulateUnbondResponse {
+    pub fee_amount: Uint128,
+    pub amount_after_fee: Uint128,
+    // none when the lp is returned right away
+    pub unlock_time: Option<Timestamp>,
+    // pending_withdraw assets and the unbond fee share paid out when the position is closed
+    pub reward_assets: Vec<Asset>,
+    // the unbond would fail with this amount
+    pub exceed_bond_amount: bool,
+}
+
+#[cw_serde]
+pub struct RewardEmission {
+    pub info: AssetInfo,
+    pub per_second: Uint128,
+    pub per_year: Uint128,
+    // reward amount of a single bonded lp unit per day
+    pub per_bond_unit_per_day: Decimal,
+}
+
+#[cw_serde]
+pub struct PoolAprResponse {
+    pub staking_token: Addr,
+    pub total_bond_amount: Uint128,
+    // the rewards_per_sec, the running reward schedules and the share of the allocated reward
+    pub emissions: Vec<RewardEmission>,
+    // yearly reward value over bonded value, both priced in usd_denom by the pair's oracle,
+    // none when the pair or the denom is not given or a value can not be computed
+    pub usd_apr: Option<Decimal>,
+}
+
+#[cw_serde]
+pub struct OperatorResponse {
+    pub operator: Addr,
+    pub expires: Expiration,
+    pub scopes: Vec<OperatorScope>,
 }
 
 #[cw_serde]
@@ -159,6 +676,10 @@ pub struct ConfigResponse {
     pub owner: Addr,
     pub rewarder: Addr,
     pub withdraw_fee_receiver: Addr,
+    pub router: Option<Addr>,
+    pub empty_pool_policy: EmptyPoolPolicy,
+    pub fee_collector: Option<Addr>,
+    pub factory: Option<Addr>,
 }
 
 #[cw_serde]
@@ -171,9 +692,11 @@ pub struct RewardsPerSecResponse {
 pub struct PoolInfoResponse {
     pub staking_token: Addr,
     pub total_bond_amount: Uint128,
-    pub reward_index: Decimal,
+    pub reward_index: Decimal256,
     pub pending_reward: Uint128,
     pub unbonding_period: Option<u64>,
+    // share of the withdrawn rewards kept by the protocol
+    pub performance_fee: Decimal,
 }
 
 // We define a custom struct for each query response
@@ -189,6 +712,34 @@ pub struct RewardInfoResponseItem {
     pub bond_amount: Uint128,
     pub pending_reward: Uint128,
     pub pending_withdraw: Vec<Asset>,
+    // staking token from the unbond fees of the others, paid on withdraw
+    pub pending_fee_share: Uint128,
+}
+
+#[cw_serde]
+pub struct StakerPositionResponse {
+    pub staking_token: Addr,
+    pub staker_addr: Addr,
+    pub bond_amount: Uint128,
+}
+
+#[cw_serde]
+pub struct PoolStakersResponse {
+    // counted since the positions were indexed
+    pub staker_count: u64,
+    pub positions: Vec<StakerPositionResponse>,
+}
+
+#[cw_serde]
+pub struct ClaimedRewardsResponse {
+    pub staker_addr: Addr,
+    pub claimed_rewards: Vec<ClaimedRewardsItem>,
+}
+
+#[cw_serde]
+pub struct ClaimedRewardsItem {
+    pub staking_token: Addr,
+    pub claimed: Vec<Asset>,
 }
 
 #[cw_serde]
@@ -203,6 +754,28 @@ pub struct QueryPoolInfoResponse {
     pub pool_info: PoolInfoResponse,
 }
 
+// a pool is active while some lp is bonded
+#[cw_serde]
+pub enum PoolStatus {
+    Active,
+    Empty,
+}
+
+#[cw_serde]
+pub struct PoolsResponse {
+    pub pools: Vec<PoolsResponseItem>,
+    // the last scanned pool, none once the pools are exhausted
+    pub next_start_after: Option<Addr>,
+}
+
+#[cw_serde]
+pub struct PoolsResponseItem {
+    pub pool_info: PoolInfoResponse,
+    pub status: PoolStatus,
+    pub rewards_per_sec: Vec<Asset>,
+    pub unbond_options: Vec<UnbondOptionResponse>,
+}
+
 #[cw_serde]
 pub struct LockInfo {
     pub amount: Uint128,
@@ -213,6 +786,14 @@ pub struct LockInfo {
 pub struct LockInfoResponse {
     pub amount: Uint128,
     pub unlock_time: u64,
+    pub zap_out: Option<ZapOutMsg>,
+    pub status: LockStatus,
+}
+
+#[cw_serde]
+pub enum LockStatus {
+    Claimable,
+    Locked,
 }
 
 #[cw_serde]
@@ -220,6 +801,36 @@ pub struct LockInfosResponse {
     pub staker_addr: Addr,
     pub staking_token: Addr,
     pub lock_infos: Vec<LockInfoResponse>,
+    // totals of all the locks, not only this page
+    pub claimable_amount: Uint128,
+    pub locked_amount: Uint128,
+    pub next_unlock_time: Option<u64>,
+}
+
+#[cw_serde]
+pub struct MaturedLocksResponse {
+    pub count: u64,
+    pub amount: Uint128,
+    // max number of locks processed per transaction
+    pub max_per_tx: u32,
+}
+
+#[cw_serde]
+pub struct PortfolioItem {
+    pub staking_token: Addr,
+    pub bond_amount: Uint128,
+    // pending_reward split by the current rewards_per_sec, together with pending_withdraw
+    pub pending_rewards: Vec<Asset>,
+    // unlocked, returned on the next unbond
+    pub claimable_locks: Vec<LockInfoResponse>,
+    pub locked: Vec<LockInfoResponse>,
+    pub staked_balance: Uint128,
+}
+
+#[cw_serde]
+pub struct StakerPortfolioResponse {
+    pub staker_addr: Addr,
+    pub pools: Vec<PortfolioItem>,
 }
 
 #[cw_serde]
@@ -233,3 +844,9 @@ pub struct TotalStakedAtHeightResponse {
     pub total: Uint128,
     pub height: u64,
 }
+
+#[cw_serde]
+pub struct RewardIndexAtHeightResponse {
+    pub reward_index: Decimal256,
+    pub height: u64,
+}
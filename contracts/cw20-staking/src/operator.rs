@@ -0,0 +1,108 @@
+use crate::msg::{OperatorResponse, OperatorScope};
+use crate::state::{OperatorApproval, DEFAULT_LIMIT, MAX_LIMIT, OPERATORS};
+use cosmwasm_std::{Addr, Deps, DepsMut, Env, MessageInfo, Order, Response, StdError, StdResult};
+use cw20::Expiration;
+use cw_storage_plus::Bound;
+
+pub fn approve_operator(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    operator: Addr,
+    expires: Option<Expiration>,
+    scopes: Vec<OperatorScope>,
+) -> StdResult<Response> {
+    deps.api.addr_validate(operator.as_str())?;
+    if operator.eq(&info.sender) {
+        return Err(StdError::generic_err("Cannot approve yourself as operator"));
+    }
+
+    let expires = expires.unwrap_or_default();
+    if expires.is_expired(&env.block) {
+        return Err(StdError::generic_err(
+            "Operator approval is already expired",
+        ));
+    }
+
+    if scopes.is_empty() {
+        return Err(StdError::generic_err("Operator scopes must not be empty"));
+    }
+
+    OPERATORS.save(
+        deps.storage,
+        (&info.sender, &operator),
+        &OperatorApproval { expires, scopes },
+    )?;
+
+    Ok(Response::new().add_attributes([
+        ("action", "approve_operator"),
+        ("staker_addr", info.sender.as_str()),
+        ("operator", operator.as_str()),
+        ("expires", &expires.to_string()),
+    ]))
+}
+
+pub fn revoke_operator(deps: DepsMut, info: MessageInfo, operator: Addr) -> StdResult<Response> {
+    OPERATORS.remove(deps.storage, (&info.sender, &operator));
+
+    Ok(Response::new().add_attributes([
+        ("action", "revoke_operator"),
+        ("staker_addr", info.sender.as_str()),
+        ("operator", operator.as_str()),
+    ]))
+}
+
+// returns the staker the sender is acting for, the sender itself when no staker is given
+pub fn resolve_staker(
+    deps: Deps,
+    env: &Env,
+    sender: &Addr,
+    staker_addr: Option<Addr>,
+    scope: OperatorScope,
+) -> StdResult<Addr> {
+    let staker_addr = match staker_addr {
+        Some(staker_addr) if staker_addr.ne(sender) => staker_addr,
+        _ => return Ok(sender.clone()),
+    };
+
+    let approval = OPERATORS
+        .may_load(deps.storage, (&staker_addr, sender))?
+        .ok_or_else(|| StdError::generic_err("unauthorized"))?;
+
+    if approval.expires.is_expired(&env.block) {
+        return Err(StdError::generic_err("Operator approval is expired"));
+    }
+
+    if !approval.scopes.contains(&scope) {
+        return Err(StdError::generic_err(format!(
+            "Operator is not allowed to {:?}",
+            scope
+        )));
+    }
+
+    Ok(staker_addr)
+}
+
+pub fn query_operators(
+    deps: Deps,
+    staker_addr: Addr,
+    start_after: Option<Addr>,
+    limit: Option<u32>,
+) -> StdResult<Vec<OperatorResponse>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.as_ref().map(Bound::exclusive);
+
+    OPERATORS
+        .prefix(&staker_addr)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (operator, approval) = item?;
+            Ok(OperatorResponse {
+                operator,
+                expires: approval.expires,
+                scopes: approval.scopes,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,68 @@
+use cosmwasm_std::{Order, Record, Storage};
+use std::collections::BTreeMap;
+
+// a read only storage keeping the writes in memory, so queries can sync a pool the same way executes do
+pub struct StorageOverlay<'a> {
+    base: &'a dyn Storage,
+    // none marks a removed key
+    writes: BTreeMap<Vec<u8>, Option<Vec<u8>>>,
+}
+
+impl<'a> StorageOverlay<'a> {
+    pub fn new(base: &'a dyn Storage) -> Self {
+        StorageOverlay {
+            base,
+            writes: BTreeMap::new(),
+        }
+    }
+}
+
+impl Storage for StorageOverlay<'_> {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        match self.writes.get(key) {
+            Some(value) => value.clone(),
+            None => self.base.get(key),
+        }
+    }
+
+    fn range<'b>(
+        &'b self,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = Record> + 'b> {
+        let writes: Vec<(&Vec<u8>, &Option<Vec<u8>>)> = self
+            .writes
+            .iter()
+            .filter(|(key, _)| {
+                start.map_or(true, |start| key.as_slice() >= start)
+                    && end.map_or(true, |end| key.as_slice() < end)
+            })
+            .collect();
+        if writes.is_empty() {
+            return self.base.range(start, end, order);
+        }
+
+        // only the ranges touched by a write are merged in memory
+        let mut records: BTreeMap<Vec<u8>, Vec<u8>> =
+            self.base.range(start, end, Order::Ascending).collect();
+        for (key, value) in writes {
+            match value {
+                Some(value) => records.insert(key.clone(), value.clone()),
+                None => records.remove(key),
+            };
+        }
+        match order {
+            Order::Ascending => Box::new(records.into_iter()),
+            Order::Descending => Box::new(records.into_iter().rev()),
+        }
+    }
+
+    fn set(&mut self, key: &[u8], value: &[u8]) {
+        self.writes.insert(key.to_vec(), Some(value.to_vec()));
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        self.writes.insert(key.to_vec(), None);
+    }
+}
@@ -0,0 +1,196 @@
+use crate::msg::{PoolStakersResponse, Role, StakerPositionResponse};
+use crate::roles::assert_role;
+use crate::state::{
+    read_pool_info, rewards_read, staker_positions, stakers_read, StakerPosition, DEFAULT_LIMIT,
+    MAX_LIMIT, POOL_STAKER_COUNTS,
+};
+use cosmwasm_std::{
+    Addr, Api, CanonicalAddr, Deps, DepsMut, MessageInfo, Order, Response, StdError, StdResult,
+    Storage, Uint128,
+};
+use cw_storage_plus::Bound;
+use oraiswap::querier::calc_range_start;
+
+// keep the indexed position in sync with the bond amount, closed positions are removed
+pub fn update_staker_position(
+    storage: &mut dyn Storage,
+    asset_key: &[u8],
+    staker_addr: &CanonicalAddr,
+    bond_amount: Uint128,
+) -> StdResult<()> {
+    let positions = staker_positions();
+    let key = (asset_key, staker_addr.as_slice());
+    let indexed = positions.may_load(storage, key)?.is_some();
+
+    if bond_amount.is_zero() {
+        if indexed {
+            positions.remove(storage, key)?;
+            POOL_STAKER_COUNTS.update(storage, asset_key, |count| -> StdResult<u64> {
+                Ok(count.unwrap_or_default().saturating_sub(1))
+            })?;
+        }
+        return Ok(());
+    }
+
+    positions.save(
+        storage,
+        key,
+        &StakerPosition {
+            staking_token: CanonicalAddr::from(asset_key),
+            staker_addr: staker_addr.clone(),
+            bond_amount,
+        },
+    )?;
+    if !indexed {
+        POOL_STAKER_COUNTS.update(storage, asset_key, |count| -> StdResult<u64> {
+            Ok(count.unwrap_or_default() + 1)
+        })?;
+    }
+    Ok(())
+}
+
+// the positions bonded before the indexes were introduced are added page by page,
+// indexing a staker again only refreshes its position
+pub fn index_staker_positions(
+    deps: DepsMut,
+    info: MessageInfo,
+    staking_token: Addr,
+    start_after: Option<Addr>,
+    limit: Option<u32>,
+) -> StdResult<Response> {
+    assert_role(deps.as_ref(), &info.sender, Role::Keeper)?;
+
+    let asset_key = deps.api.addr_canonicalize(staking_token.as_str())?;
+    if read_pool_info(deps.storage, &asset_key).is_err() {
+        return Err(StdError::generic_err("Staking token is not registered"));
+    }
+    let start_after = start_after
+        .map(|staker_addr| deps.api.addr_canonicalize(staker_addr.as_str()))
+        .transpose()?
+        .map(|staker_addr| staker_addr.to_vec());
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    let staker_keys = stakers_read(deps.storage, &asset_key)
+        .range(
+            calc_range_start(start_after).as_deref(),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| item.map(|(staker_key, _)| staker_key))
+        .collect::<StdResult<Vec<Vec<u8>>>>()?;
+
+    let mut last_staker = None;
+    for staker_key in staker_keys.iter() {
+        let staker_addr = CanonicalAddr::from(staker_key.as_slice());
+        let bond_amount = rewards_read(deps.storage, &staker_addr)
+            .may_load(&asset_key)?
+            .map_or(Uint128::zero(), |reward_info| reward_info.bond_amount);
+        update_staker_position(deps.storage, &asset_key, &staker_addr, bond_amount)?;
+        last_staker = Some(staker_addr);
+    }
+
+    let mut response = Response::new().add_attributes([
+        ("action", "index_staker_positions"),
+        ("staking_token", staking_token.as_str()),
+        ("indexed", &staker_keys.len().to_string()),
+    ]);
+    // the next page starts after it
+    if let Some(last_staker) = last_staker {
+        response = response.add_attribute(
+            "last_staker",
+            deps.api.addr_humanize(&last_staker)?.to_string(),
+        );
+    }
+    Ok(response)
+}
+
+fn to_position_response(
+    api: &dyn Api,
+    position: StakerPosition,
+) -> StdResult<StakerPositionResponse> {
+    Ok(StakerPositionResponse {
+        staking_token: api.addr_humanize(&position.staking_token)?,
+        staker_addr: api.addr_humanize(&position.staker_addr)?,
+        bond_amount: position.bond_amount,
+    })
+}
+
+pub fn query_pool_stakers(
+    deps: Deps,
+    staking_token: Addr,
+    start_after: Option<Addr>,
+    limit: Option<u32>,
+) -> StdResult<PoolStakersResponse> {
+    let asset_key = deps.api.addr_canonicalize(staking_token.as_str())?;
+    let start_after = start_after
+        .map(|staker_addr| deps.api.addr_canonicalize(staker_addr.as_str()))
+        .transpose()?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    let positions = staker_positions()
+        .prefix(&asset_key)
+        .range(
+            deps.storage,
+            start_after.as_deref().map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| to_position_response(deps.api, item?.1))
+        .collect::<StdResult<Vec<StakerPositionResponse>>>()?;
+
+    Ok(PoolStakersResponse {
+        staker_count: POOL_STAKER_COUNTS
+            .may_load(deps.storage, &asset_key)?
+            .unwrap_or_default(),
+        positions,
+    })
+}
+
+pub fn query_staker_pools(
+    deps: Deps,
+    staker_addr: Addr,
+    start_after: Option<Addr>,
+    limit: Option<u32>,
+) -> StdResult<Vec<StakerPositionResponse>> {
+    let staker_addr_raw = deps.api.addr_canonicalize(staker_addr.as_str())?;
+    let start = start_after
+        .map(|staking_token| -> StdResult<_> {
+            let asset_key = deps.api.addr_canonicalize(staking_token.as_str())?;
+            Ok(Bound::exclusive((
+                asset_key.to_vec(),
+                staker_addr_raw.to_vec(),
+            )))
+        })
+        .transpose()?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    staker_positions()
+        .idx
+        .staker
+        .prefix(staker_addr_raw.to_vec())
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| to_position_response(deps.api, item?.1))
+        .collect()
+}
+
+// largest bonds first
+pub fn query_top_stakers(
+    deps: Deps,
+    staking_token: Addr,
+    limit: Option<u32>,
+) -> StdResult<Vec<StakerPositionResponse>> {
+    let asset_key = deps.api.addr_canonicalize(staking_token.as_str())?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    staker_positions()
+        .idx
+        .bond_amount
+        .sub_prefix(asset_key.to_vec())
+        .range(deps.storage, None, None, Order::Descending)
+        .take(limit)
+        .map(|item| to_position_response(deps.api, item?.1))
+        .collect()
+}
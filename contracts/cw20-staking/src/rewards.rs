@@ -1,20 +1,46 @@
 use std::convert::TryFrom;
 
-use crate::msg::{RewardInfoResponse, RewardInfoResponseItem, RewardMsg};
+use crate::allocation::settle_pool_allocation;
+use crate::msg::{
+    BeliefPrice, EmptyPoolPolicy, PoolAprResponse, RewardEmission, RewardInfoResponse,
+    RewardInfoResponseItem, RewardMsg, Role, StrandedRewardResponse,
+};
+use crate::overlay::StorageOverlay;
+use crate::roles::assert_role;
+use crate::schedule::sync_reward_schedules;
 use crate::state::{
-    read_config, read_pool_info, read_rewards_per_sec, rewards_read, rewards_store, stakers_read,
-    store_pool_info, PoolInfo, RewardInfo, DEFAULT_LIMIT, MAX_LIMIT,
+    read_all_pool_infos, read_config, read_pool_info, read_rewards_per_sec,
+    read_total_rewards_per_sec, rewards_read, rewards_store, stakers_read, store_pool_info,
+    PoolInfo, RewardEpoch, RewardInfo, SwapRewardRefund, ACTIVE_REWARD_SCHEDULES, DEFAULT_LIMIT,
+    MAX_LIMIT, PERFORMANCE_FEES, PERFORMANCE_FEE_TOTALS, POOL_ALLOCATIONS, REWARD_ALLOCATION,
+    REWARD_EPOCHS, REWARD_INDEX_HISTORY, STRANDED_REWARDS, SWAP_REWARD_REFUNDS,
 };
+use crate::stats::{record_rewards_claimed, record_rewards_deposited};
 use cosmwasm_std::{
-    Addr, Api, CanonicalAddr, CosmosMsg, Decimal, Deps, DepsMut, Env, MessageInfo, Order, Response,
-    StdError, StdResult, Storage, Uint128,
+    coin, to_binary, Addr, Api, BlockInfo, CanonicalAddr, CosmosMsg, Decimal, Decimal256, Deps,
+    DepsMut, Env, Fraction, MessageInfo, Order, QuerierWrapper, Reply, Response, StdError,
+    StdResult, Storage, SubMsg, SubMsgResult, Uint128, Uint256, WasmMsg,
 };
-use oraiswap::asset::{Asset, AssetRaw};
+use cw20::Cw20ExecuteMsg;
+use oraiswap::asset::{Asset, AssetInfo, AssetInfoRaw, AssetRaw, PairInfo};
+use oraiswap::oracle::OracleContract;
+use oraiswap::pair::{PoolResponse, QueryMsg as PairQueryMsg};
 use oraiswap::querier::calc_range_start;
+use oraiswap::router::{
+    Cw20HookMsg as RouterCw20HookMsg, ExecuteMsg as RouterExecuteMsg, SwapOperation,
+};
+
+// each swapped reward asset gets its own reply id starting from this one
+pub const SWAP_REWARD_REPLY_ID: u64 = 100;
+pub const MAX_SWAP_REWARDS: u64 = 100;
+
+const SECONDS_PER_DAY: u64 = 86_400;
+const SECONDS_PER_YEAR: u64 = 365 * SECONDS_PER_DAY;
 
 // deposit_reward must be from reward token contract
 pub fn deposit_reward(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     rewards: Vec<RewardMsg>,
 ) -> StdResult<Response> {
@@ -26,30 +52,56 @@ pub fn deposit_reward(
     }
 
     let mut rewards_amount = Uint128::zero();
+    let mut messages: Vec<CosmosMsg> = vec![];
 
     for reward_msg in rewards.iter() {
         let asset_key = deps
             .api
             .addr_canonicalize(reward_msg.staking_token.as_str())?;
+        sync_pool_rewards(deps.storage, &asset_key, &env.block)?;
         let mut pool_info: PoolInfo = read_pool_info(deps.storage, &asset_key)?;
 
-        let mut normal_reward = reward_msg.total_accumulation_amount;
+        let normal_reward = reward_msg.total_accumulation_amount;
 
-        // normal rewards are array of Assets
-        if !pool_info.total_bond_amount.is_zero() {
-            normal_reward += pool_info.pending_reward;
-            let normal_reward_per_bond =
-                Decimal::from_ratio(normal_reward, pool_info.total_bond_amount);
-            pool_info.reward_index += normal_reward_per_bond;
-            pool_info.pending_reward = Uint128::zero();
+        // nobody can earn it yet, it is stranded for the next reward unless the policy refunds it
+        if pool_info.total_bond_amount.is_zero()
+            && config.empty_pool_policy == EmptyPoolPolicy::Refund
+        {
+            let refund_assets = split_reward_amount(
+                normal_reward,
+                read_rewards_per_sec(deps.storage, &asset_key).unwrap_or_default(),
+            );
+            // without any weight the refunded assets are unknown, so it is kept as well,
+            // along with what the split truncates
+            let refunded: Uint128 = refund_assets.iter().map(|ra| ra.amount).sum();
+            strand_pool_reward(
+                deps.storage,
+                &asset_key,
+                &mut pool_info,
+                normal_reward - refunded,
+                vec![],
+            )?;
+            for refund_asset in refund_assets {
+                if refund_asset.amount.is_zero() {
+                    continue;
+                }
+                messages.push(refund_asset.to_normal(deps.api)?.into_msg(
+                    None,
+                    &deps.querier,
+                    info.sender.clone(),
+                )?);
+            }
+        } else {
+            accrue_pool_reward(deps.storage, &asset_key, &mut pool_info, normal_reward)?;
         }
 
         store_pool_info(deps.storage, &asset_key, &pool_info)?;
+        checkpoint_reward_index(deps.storage, &asset_key, env.block.height)?;
 
         rewards_amount += reward_msg.total_accumulation_amount;
     }
 
-    Ok(Response::new().add_attributes([
+    Ok(Response::new().add_messages(messages).add_attributes([
         ("action", "deposit_reward"),
         ("rewards_amount", &rewards_amount.to_string()),
     ]))
@@ -58,11 +110,46 @@ pub fn deposit_reward(
 // withdraw all rewards or single reward depending on asset_token
 pub fn withdraw_reward(
     deps: DepsMut,
-    _env: Env,
-    info: MessageInfo,
+    env: Env,
+    staker_addr: Addr,
     staking_token: Option<Addr>,
+    swap_to: Option<AssetInfo>,
+    max_spread: Option<Decimal>,
+    belief_prices: Vec<BeliefPrice>,
 ) -> StdResult<Response> {
-    let staker_addr = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if max_spread.map_or(false, |max_spread| max_spread > Decimal::one()) {
+        return Err(StdError::generic_err(
+            "Max spread must be less than or equal 1",
+        ));
+    }
+
+    let config = read_config(deps.storage)?;
+    // the swaps are checked before the rewards are processed
+    let router = match &swap_to {
+        None => None,
+        Some(_) => {
+            if max_spread.is_none() {
+                return Err(StdError::generic_err(
+                    "Max spread is required to swap rewards",
+                ));
+            }
+            if belief_prices.len() as u64 > MAX_SWAP_REWARDS {
+                return Err(StdError::generic_err(
+                    "Too many reward assets to swap, withdraw them per staking token",
+                ));
+            }
+            Some(
+                deps.api.addr_humanize(
+                    config
+                        .router
+                        .as_ref()
+                        .ok_or_else(|| StdError::generic_err("Router is not configured"))?,
+                )?,
+            )
+        }
+    };
+
+    let staker_addr_raw = deps.api.addr_canonicalize(staker_addr.as_str())?;
     let asset_key = staking_token.map_or(None, |a| {
         deps.api
             .addr_canonicalize(a.as_str())
@@ -70,34 +157,202 @@ pub fn withdraw_reward(
             .ok()
     });
 
-    let reward_assets = process_reward_assets(deps.storage, &staker_addr, &asset_key, true)?;
-
-    let messages = reward_assets
+    let (reward_assets, fee_assets, fee_shares) =
+        process_reward_assets(deps.storage, &env.block, &staker_addr_raw, &asset_key, true)?;
+    let reward_assets = reward_assets
         .into_iter()
-        .map(|ra| {
-            ra.to_normal(deps.api)?
-                .into_msg(None, &deps.querier, info.sender.clone())
-        })
-        .collect::<StdResult<Vec<CosmosMsg>>>()?;
+        .map(|ra| ra.to_normal(deps.api))
+        .collect::<StdResult<Vec<Asset>>>()?;
+
+    let mut response = add_performance_fees(
+        deps.api,
+        &deps.querier,
+        deps.storage,
+        Response::new().add_attribute("action", "withdraw_reward"),
+        fee_assets,
+    )?;
+
+    // the stakers shares of the unbond fees are paid as they are, never swapped
+    for fee_share in fee_shares {
+        response = response.add_message(fee_share.to_normal(deps.api)?.into_msg(
+            None,
+            &deps.querier,
+            staker_addr.clone(),
+        )?);
+    }
 
-    Ok(Response::new()
-        .add_messages(messages)
-        .add_attribute("action", "withdraw_reward"))
+    let (swap_to, router) = match (swap_to, router) {
+        (Some(swap_to), Some(router)) => (swap_to, router),
+        _ => {
+            let messages = reward_assets
+                .into_iter()
+                .map(|ra| ra.into_msg(None, &deps.querier, staker_addr.clone()))
+                .collect::<StdResult<Vec<CosmosMsg>>>()?;
+
+            return Ok(response.add_messages(messages));
+        }
+    };
+
+    let max_spread = max_spread.unwrap_or_default();
+    let mut reply_id = SWAP_REWARD_REPLY_ID;
+    for reward_asset in reward_assets.into_iter() {
+        let belief_price = belief_prices
+            .iter()
+            .find(|belief_price| belief_price.info.eq(&reward_asset.info));
+        let minimum_receive = match belief_price {
+            Some(belief_price)
+                if !reward_asset.info.eq(&swap_to) && !reward_asset.amount.is_zero() =>
+            {
+                _minimum_receive(reward_asset.amount, belief_price.price, max_spread)?
+            }
+            // nothing to swap, or no price the staker accepts
+            _ => {
+                response = response.add_message(reward_asset.into_msg(
+                    None,
+                    &deps.querier,
+                    staker_addr.clone(),
+                )?);
+                continue;
+            }
+        };
+
+        let swap_msg = _swap_reward_msg(
+            &router,
+            &reward_asset,
+            &swap_to,
+            minimum_receive,
+            &staker_addr,
+        )?;
+        SWAP_REWARD_REFUNDS.save(
+            deps.storage,
+            reply_id,
+            &SwapRewardRefund {
+                recipient: staker_addr.clone(),
+                asset: reward_asset,
+            },
+        )?;
+        response = response.add_submessage(SubMsg::reply_always(swap_msg, reply_id));
+        reply_id += 1;
+    }
+
+    Ok(response)
+}
+
+// the offered amount at the belief price, less the max spread
+fn _minimum_receive(
+    amount: Uint128,
+    belief_price: Decimal,
+    max_spread: Decimal,
+) -> StdResult<Uint128> {
+    let expected = amount
+        .checked_multiply_ratio(belief_price.numerator(), belief_price.denominator())
+        .map_err(|_| StdError::generic_err("Belief price is too large"))?;
+    Ok(expected.checked_sub(expected * max_spread)?)
+}
+
+// the performance fees go to the fee collector, the withdraw_fee_receiver by default
+pub fn add_performance_fees(
+    api: &dyn Api,
+    querier: &QuerierWrapper,
+    storage: &dyn Storage,
+    mut response: Response,
+    fee_assets: Vec<AssetRaw>,
+) -> StdResult<Response> {
+    if fee_assets.is_empty() {
+        return Ok(response);
+    }
+
+    let config = read_config(storage)?;
+    let fee_collector = api.addr_humanize(
+        config
+            .fee_collector
+            .as_ref()
+            .unwrap_or(&config.withdraw_fee_receiver),
+    )?;
+    for fee_asset in fee_assets {
+        let fee_asset = fee_asset.to_normal(api)?;
+        let fee_attr = match &fee_asset.info {
+            AssetInfo::NativeToken { denom } => format!("{}{}", fee_asset.amount, denom),
+            AssetInfo::Token { contract_addr } => format!("{}{}", fee_asset.amount, contract_addr),
+        };
+        response = response
+            .add_message(fee_asset.into_msg(None, querier, fee_collector.clone())?)
+            .add_attribute("performance_fee", fee_attr);
+    }
+    Ok(response)
+}
+
+fn _swap_reward_msg(
+    router: &Addr,
+    offer_asset: &Asset,
+    ask_asset_info: &AssetInfo,
+    minimum_receive: Uint128,
+    to: &Addr,
+) -> StdResult<CosmosMsg> {
+    let operations = vec![SwapOperation::OraiSwap {
+        offer_asset_info: offer_asset.info.clone(),
+        ask_asset_info: ask_asset_info.clone(),
+    }];
+    let minimum_receive = Some(minimum_receive);
+
+    let swap_msg = match &offer_asset.info {
+        AssetInfo::NativeToken { denom } => WasmMsg::Execute {
+            contract_addr: router.to_string(),
+            msg: to_binary(&RouterExecuteMsg::ExecuteSwapOperations {
+                operations,
+                minimum_receive,
+                to: Some(to.clone()),
+            })?,
+            funds: vec![coin(offer_asset.amount.u128(), denom)],
+        },
+        AssetInfo::Token { contract_addr } => WasmMsg::Execute {
+            contract_addr: contract_addr.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Send {
+                contract: router.to_string(),
+                amount: offer_asset.amount,
+                msg: to_binary(&RouterCw20HookMsg::ExecuteSwapOperations {
+                    operations,
+                    minimum_receive,
+                    to: Some(to.clone()),
+                })?,
+            })?,
+            funds: vec![],
+        },
+    };
+
+    Ok(swap_msg.into())
+}
+
+// the failed swap is reverted, so the reward asset is still in this contract and can be refunded
+pub fn swap_reward_reply(deps: DepsMut, msg: Reply) -> StdResult<Response> {
+    let refund = SWAP_REWARD_REFUNDS.load(deps.storage, msg.id)?;
+    SWAP_REWARD_REFUNDS.remove(deps.storage, msg.id);
+
+    match msg.result {
+        SubMsgResult::Ok(_) => Ok(Response::new()),
+        SubMsgResult::Err(err) => Ok(Response::new()
+            .add_message(
+                refund
+                    .asset
+                    .into_msg(None, &deps.querier, refund.recipient.clone())?,
+            )
+            .add_attributes([
+                ("action", "refund_reward"),
+                ("recipient", refund.recipient.as_str()),
+                ("amount", &refund.asset.amount.to_string()),
+                ("error", &err),
+            ])),
+    }
 }
 
 pub fn withdraw_reward_others(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     staker_addrs: Vec<Addr>,
     staker_addr: Option<Addr>,
 ) -> StdResult<Response> {
-    let config = read_config(deps.storage)?;
-
-    // only admin can execute this message
-    if config.owner != deps.api.addr_canonicalize(info.sender.as_str())? {
-        return Err(StdError::generic_err("unauthorized"));
-    }
+    assert_role(deps.as_ref(), &info.sender, Role::Keeper)?;
 
     let asset_key = staker_addr.map_or(None, |a| {
         deps.api
@@ -110,7 +365,13 @@ pub fn withdraw_reward_others(
     // withdraw reward for each staker
     for staker_addr in staker_addrs {
         let staker_addr_raw = deps.api.addr_canonicalize(staker_addr.as_str())?;
-        process_reward_assets(deps.storage, &staker_addr_raw, &asset_key, false)?;
+        process_reward_assets(
+            deps.storage,
+            &env.block,
+            &staker_addr_raw,
+            &asset_key,
+            false,
+        )?;
     }
 
     Ok(Response::new().add_attribute("action", "withdraw_reward_others"))
@@ -130,13 +391,15 @@ fn update_reward_assets_amount(reward_assets: &mut Vec<AssetRaw>, rw: AssetRaw,
     }
 }
 
-// this function will return total asset to reward, then later can be updated as pending_withdraw, or send to client
+// this function will return total asset to reward, then later can be updated as pending_withdraw, or send to client.
+// The performance fees and the stakers shares of the unbond fees are returned separately
 pub fn process_reward_assets(
     storage: &mut dyn Storage,
+    block: &BlockInfo,
     staker_addr: &CanonicalAddr,
     asset_key: &Option<Vec<u8>>,
     do_withdraw: bool,
-) -> StdResult<Vec<AssetRaw>> {
+) -> StdResult<(Vec<AssetRaw>, Vec<AssetRaw>, Vec<AssetRaw>)> {
     let rewards_bucket = rewards_read(storage, staker_addr);
 
     // single reward withdraw, using Vec to store reference variable in local function
@@ -155,45 +418,46 @@ pub fn process_reward_assets(
 
     // only has value when do_withdraw
     let mut reward_assets: Vec<AssetRaw> = vec![];
+    let mut fee_assets: Vec<AssetRaw> = vec![];
+    let mut fee_shares: Vec<AssetRaw> = vec![];
 
     for reward_pair in reward_pairs {
         let (asset_key, mut reward_info) = reward_pair;
+        sync_pool_rewards(storage, &asset_key, block)?;
         let pool_info: PoolInfo = read_pool_info(storage, &asset_key)?;
 
         // Withdraw reward to pending reward
-        // if the lp token was migrated, and the user did not close their position yet, cap the reward at the snapshot
-        let pool_index = pool_info.reward_index;
-
-        before_share_change(pool_index, &mut reward_info)?;
-
-        if !reward_info.pending_reward.is_zero() {
-            // calculate and accumulate the reward amount
-            let rewards_per_sec = read_rewards_per_sec(storage, &asset_key)?;
-            // now calculate weight
-            let total_amount: Uint128 = rewards_per_sec.iter().map(|rw| rw.amount).sum();
-
-            for rw in rewards_per_sec {
-                // ignore empty weight
-                if rw.amount.is_zero() {
-                    continue;
-                }
-                let amount =
-                    reward_info.pending_reward * Decimal::from_ratio(rw.amount, total_amount);
-
-                // update pending_withdraw, first time push it, later update the amount
-                update_reward_assets_amount(&mut reward_info.pending_withdraw, rw, amount);
-            }
-
-            // reset pending_reward
-            reward_info.pending_reward = Uint128::zero();
-        }
+        settle_reward_info(storage, &asset_key, &pool_info, &mut reward_info)?;
+        split_pending_reward(storage, &asset_key, &mut reward_info)?;
 
         // if withdraw, then update reward_assets to create MsgSend
         if do_withdraw {
-            for rw in reward_info.pending_withdraw {
-                update_reward_assets_amount(&mut reward_assets, rw.clone(), rw.amount);
+            let (claimed_assets, pool_fee_assets) =
+                split_performance_fee(storage, &asset_key, reward_info.pending_withdraw)?;
+            record_reward_withdraw(
+                storage,
+                staker_addr,
+                &asset_key,
+                &claimed_assets,
+                &pool_fee_assets,
+            )?;
+            for rw in claimed_assets {
+                let amount = rw.amount;
+                update_reward_assets_amount(&mut reward_assets, rw, amount);
+            }
+            for rw in pool_fee_assets {
+                let amount = rw.amount;
+                update_reward_assets_amount(&mut fee_assets, rw, amount);
             }
             reward_info.pending_withdraw = vec![];
+
+            if !reward_info.pending_fee_share.is_zero() {
+                fee_shares.push(fee_share_asset(
+                    &pool_info.staking_token,
+                    reward_info.pending_fee_share,
+                ));
+                reward_info.pending_fee_share = Uint128::zero();
+            }
         }
 
         // Update rewards info, if empty bond_amount and withdraw then remove
@@ -204,28 +468,334 @@ pub fn process_reward_assets(
         }
     }
 
-    Ok(reward_assets)
+    Ok((reward_assets, fee_assets, fee_shares))
+}
+
+// split the withdrawn assets into the part paid to the staker and the performance fee of the pool
+pub fn split_performance_fee(
+    storage: &dyn Storage,
+    asset_key: &[u8],
+    assets: Vec<AssetRaw>,
+) -> StdResult<(Vec<AssetRaw>, Vec<AssetRaw>)> {
+    let fee_rate = PERFORMANCE_FEES
+        .may_load(storage, asset_key)?
+        .unwrap_or_default();
+
+    let mut claimed_assets: Vec<AssetRaw> = vec![];
+    let mut fee_assets: Vec<AssetRaw> = vec![];
+    for rw in assets {
+        let fee_amount = rw.amount * fee_rate;
+        update_reward_assets_amount(&mut claimed_assets, rw.clone(), rw.amount - fee_amount);
+        if !fee_amount.is_zero() {
+            update_reward_assets_amount(&mut fee_assets, rw, fee_amount);
+        }
+    }
+    Ok((claimed_assets, fee_assets))
+}
+
+// count the rewards claimed by the staker and the performance fees kept from them
+pub fn record_reward_withdraw(
+    storage: &mut dyn Storage,
+    staker_addr: &CanonicalAddr,
+    asset_key: &[u8],
+    claimed_assets: &[AssetRaw],
+    fee_assets: &[AssetRaw],
+) -> StdResult<()> {
+    if !fee_assets.is_empty() {
+        let mut pool_fees = PERFORMANCE_FEE_TOTALS
+            .may_load(storage, asset_key)?
+            .unwrap_or_default();
+        for rw in fee_assets {
+            update_reward_assets_amount(&mut pool_fees, rw.clone(), rw.amount);
+        }
+        PERFORMANCE_FEE_TOTALS.save(storage, asset_key, &pool_fees)?;
+    }
+    record_rewards_claimed(storage, staker_addr, asset_key, claimed_assets)
+}
+
+// move pending_reward into pending_withdraw, split by the rewards_per_sec weights
+pub fn split_pending_reward(
+    storage: &dyn Storage,
+    asset_key: &[u8],
+    reward_info: &mut RewardInfo,
+) -> StdResult<()> {
+    if reward_info.pending_reward.is_zero() {
+        return Ok(());
+    }
+
+    // calculate and accumulate the reward amount
+    let rewards_per_sec = read_total_rewards_per_sec(storage, asset_key)?;
+    _split_pending_reward(reward_info, rewards_per_sec);
+    Ok(())
+}
+
+fn _split_pending_reward(reward_info: &mut RewardInfo, rewards_per_sec: Vec<AssetRaw>) {
+    let reward_assets = split_reward_amount(reward_info.pending_reward, rewards_per_sec);
+    // without any weight, keep it for the next epoch
+    if reward_assets.is_empty() {
+        return;
+    }
+
+    for ra in reward_assets {
+        // update pending_withdraw, first time push it, later update the amount
+        let amount = ra.amount;
+        update_reward_assets_amount(&mut reward_info.pending_withdraw, ra, amount);
+    }
+
+    // reset pending_reward
+    reward_info.pending_reward = Uint128::zero();
+}
+
+// split the amount by the rewards_per_sec weights, empty when there is no weight
+pub fn split_reward_amount(amount: Uint128, rewards_per_sec: Vec<AssetRaw>) -> Vec<AssetRaw> {
+    // now calculate weight
+    let total_amount: Uint128 = rewards_per_sec.iter().map(|rw| rw.amount).sum();
+    if total_amount.is_zero() {
+        return vec![];
+    }
+
+    rewards_per_sec
+        .into_iter()
+        // ignore empty weight
+        .filter(|rw| !rw.amount.is_zero())
+        .map(|rw| AssetRaw {
+            amount: amount * Decimal::from_ratio(rw.amount, total_amount),
+            info: rw.info,
+        })
+        .collect()
+}
+
+pub fn query_stranded_rewards(deps: Deps) -> StdResult<Vec<StrandedRewardResponse>> {
+    read_all_pool_infos(deps.storage)?
+        .into_iter()
+        .filter(|(_, pool_info)| !pool_info.pending_reward.is_zero())
+        .map(|(asset_key, pool_info)| {
+            let assets = STRANDED_REWARDS
+                .may_load(deps.storage, &asset_key)?
+                .unwrap_or_default()
+                .into_iter()
+                .map(|asset| asset.to_normal(deps.api))
+                .collect::<StdResult<Vec<Asset>>>()?;
+            Ok(StrandedRewardResponse {
+                staking_token: deps.api.addr_humanize(&CanonicalAddr::from(asset_key))?,
+                pending_reward: pool_info.pending_reward,
+                assets,
+            })
+        })
+        .collect()
+}
+
+// bring the pool up to date with the allocated deposits and the reward schedules
+pub fn sync_pool_rewards(
+    storage: &mut dyn Storage,
+    asset_key: &[u8],
+    block: &BlockInfo,
+) -> StdResult<()> {
+    settle_pool_allocation(storage, asset_key)?;
+    sync_reward_schedules(storage, asset_key, block.time)?;
+    checkpoint_reward_index(storage, asset_key, block.height)
+}
+
+// queries can not write, so the pool is synced on top of the storage and read from the overlay
+pub fn simulate_pool_sync<'a>(
+    storage: &'a dyn Storage,
+    asset_key: &[u8],
+    block: &BlockInfo,
+) -> StdResult<StorageOverlay<'a>> {
+    let mut synced = StorageOverlay::new(storage);
+    sync_pool_rewards(&mut synced, asset_key, block)?;
+    Ok(synced)
+}
+
+// save the stored reward_index of the pool at this height when it has changed
+pub fn checkpoint_reward_index(
+    storage: &mut dyn Storage,
+    asset_key: &[u8],
+    height: u64,
+) -> StdResult<()> {
+    let reward_index = match read_pool_info(storage, asset_key) {
+        Ok(pool_info) => pool_info.reward_index,
+        Err(_) => return Ok(()),
+    };
+    if REWARD_INDEX_HISTORY.may_load(storage, asset_key)? != Some(reward_index) {
+        REWARD_INDEX_HISTORY.save(storage, asset_key, &reward_index, height)?;
+    }
+    Ok(())
+}
+
+// finish the current epoch with the current rewards_per_sec, the caller stores the pool
+pub fn close_reward_epoch(
+    storage: &mut dyn Storage,
+    asset_key: &[u8],
+    pool_info: &mut PoolInfo,
+) -> StdResult<()> {
+    let rewards_per_sec = read_total_rewards_per_sec(storage, asset_key)?;
+    REWARD_EPOCHS.save(
+        storage,
+        (asset_key, pool_info.epoch),
+        &RewardEpoch {
+            rewards_per_sec,
+            end_index: pool_info.reward_index,
+        },
+    )?;
+    pool_info.epoch += 1;
+    Ok(())
+}
+
+// distribute the amount to the bonded lp along with the stranded rewards, or strand it split by the
+// current rewards_per_sec until the next reward finds the pool bonded. The stats count the reward
+// once it is distributed, split by the rewards_per_sec it is paid with
+pub fn accrue_pool_reward(
+    storage: &mut dyn Storage,
+    asset_key: &[u8],
+    pool_info: &mut PoolInfo,
+    amount: Uint128,
+) -> StdResult<()> {
+    let rewards_per_sec = read_total_rewards_per_sec(storage, asset_key)?;
+    if pool_info.total_bond_amount.is_zero() {
+        let assets = split_reward_amount(amount, rewards_per_sec);
+        return strand_pool_reward(storage, asset_key, pool_info, amount, assets);
+    }
+    release_stranded_rewards(storage, asset_key, pool_info)?;
+
+    // the part of pending_reward the split could not assign goes out with this reward
+    let deposited = amount + pool_info.pending_reward;
+    _add_to_reward_index(pool_info, deposited)?;
+    pool_info.pending_reward = Uint128::zero();
+
+    record_rewards_deposited(
+        storage,
+        asset_key,
+        &split_reward_amount(deposited, rewards_per_sec),
+    )
+}
+
+// keep a reward of an empty pool in pending_reward, the assets may sum up to less than the amount
+pub fn strand_pool_reward(
+    storage: &mut dyn Storage,
+    asset_key: &[u8],
+    pool_info: &mut PoolInfo,
+    amount: Uint128,
+    assets: Vec<AssetRaw>,
+) -> StdResult<()> {
+    pool_info.pending_reward += amount;
+    if assets.is_empty() {
+        return Ok(());
+    }
+    let mut stranded = STRANDED_REWARDS
+        .may_load(storage, asset_key)?
+        .unwrap_or_default();
+    for asset in assets {
+        let amount = asset.amount;
+        update_reward_assets_amount(&mut stranded, asset, amount);
+    }
+    STRANDED_REWARDS.save(storage, asset_key, &stranded)
+}
+
+// the stranded rewards get an epoch of their own, so the stakers are paid the assets that were
+// deposited instead of the current rewards_per_sec mix
+fn release_stranded_rewards(
+    storage: &mut dyn Storage,
+    asset_key: &[u8],
+    pool_info: &mut PoolInfo,
+) -> StdResult<()> {
+    let stranded = match STRANDED_REWARDS.may_load(storage, asset_key)? {
+        Some(stranded) => stranded,
+        None => return Ok(()),
+    };
+    STRANDED_REWARDS.remove(storage, asset_key);
+
+    let amount: Uint128 = stranded.iter().map(|asset| asset.amount).sum();
+    pool_info.pending_reward = pool_info.pending_reward.checked_sub(amount)?;
+    close_reward_epoch(storage, asset_key, pool_info)?;
+    _add_to_reward_index(pool_info, amount)?;
+    REWARD_EPOCHS.save(
+        storage,
+        (asset_key, pool_info.epoch),
+        &RewardEpoch {
+            rewards_per_sec: stranded.clone(),
+            end_index: pool_info.reward_index,
+        },
+    )?;
+    pool_info.epoch += 1;
+
+    record_rewards_deposited(storage, asset_key, &stranded)
+}
+
+fn _add_to_reward_index(pool_info: &mut PoolInfo, amount: Uint128) -> StdResult<()> {
+    let normal_reward = amount + pool_info.reward_dust;
+    let normal_reward_per_bond = Decimal256::from_ratio(normal_reward, pool_info.total_bond_amount);
+    pool_info.reward_index += normal_reward_per_bond;
+
+    // the truncated part is not in reward_index, keep it for the next deposit
+    let distributed = Uint256::from(pool_info.total_bond_amount) * normal_reward_per_bond;
+    pool_info.reward_dust = Uint128::try_from(Uint256::from(normal_reward) - distributed)?;
+    Ok(())
+}
+
+// settle the finished epochs with their own rewards_per_sec, then withdraw the current epoch reward to pending reward
+pub fn settle_reward_info(
+    storage: &dyn Storage,
+    asset_key: &[u8],
+    pool_info: &PoolInfo,
+    reward_info: &mut RewardInfo,
+) -> StdResult<()> {
+    while reward_info.epoch < pool_info.epoch {
+        let epoch = REWARD_EPOCHS.load(storage, (asset_key, reward_info.epoch))?;
+        before_share_change(epoch.end_index, reward_info)?;
+        _split_pending_reward(reward_info, epoch.rewards_per_sec);
+        reward_info.epoch += 1;
+    }
+    settle_fee_share(pool_info, reward_info)?;
+    before_share_change(pool_info.reward_index, reward_info)
+}
+
+// the stakers share of the unbond fees is kept out of the rewards, so no performance fee or
+// claimed rewards count it
+fn settle_fee_share(pool_info: &PoolInfo, reward_info: &mut RewardInfo) -> StdResult<()> {
+    let bond_amount = Uint256::from(reward_info.bond_amount);
+    reward_info.pending_fee_share += Uint128::try_from(
+        (bond_amount * pool_info.fee_index).checked_sub(bond_amount * reward_info.fee_index)?,
+    )?;
+    reward_info.fee_index = pool_info.fee_index;
+    Ok(())
+}
+
+// the stakers share of the unbond fees paid in the staking token
+pub fn fee_share_asset(staking_token: &CanonicalAddr, amount: Uint128) -> AssetRaw {
+    AssetRaw {
+        info: AssetInfoRaw::Token {
+            contract_addr: staking_token.clone(),
+        },
+        amount,
+    }
 }
 
 // withdraw reward to pending reward
-pub fn before_share_change(pool_index: Decimal, reward_info: &mut RewardInfo) -> StdResult<()> {
-    let pending_reward = (reward_info.bond_amount * pool_index)
-        .checked_sub(reward_info.bond_amount * reward_info.index)?;
+pub fn before_share_change(pool_index: Decimal256, reward_info: &mut RewardInfo) -> StdResult<()> {
+    let bond_amount = Uint256::from(reward_info.bond_amount);
+    let pending_reward = (bond_amount * pool_index).checked_sub(bond_amount * reward_info.index)?;
 
     reward_info.index = pool_index;
-    reward_info.pending_reward += pending_reward;
+    reward_info.pending_reward += Uint128::try_from(pending_reward)?;
     Ok(())
 }
 
 pub fn query_reward_info(
     deps: Deps,
+    env: Env,
     staker_addr: Addr,
     staking_token: Option<Addr>,
 ) -> StdResult<RewardInfoResponse> {
     let staker_addr_raw = deps.api.addr_canonicalize(staker_addr.as_str())?;
 
-    let reward_infos: Vec<RewardInfoResponseItem> =
-        _read_reward_infos_response(deps.api, deps.storage, &staker_addr_raw, &staking_token)?;
+    let reward_infos: Vec<RewardInfoResponseItem> = _read_reward_infos_response(
+        deps.api,
+        deps.storage,
+        &env.block,
+        &staker_addr_raw,
+        &staking_token,
+    )?;
 
     Ok(RewardInfoResponse {
         staker_addr,
@@ -235,6 +805,7 @@ pub fn query_reward_info(
 
 pub fn query_all_reward_infos(
     deps: Deps,
+    env: Env,
     staking_token: Addr,
     start_after: Option<Addr>,
     limit: Option<u32>,
@@ -264,6 +835,7 @@ pub fn query_all_reward_infos(
             let reward_infos: Vec<RewardInfoResponseItem> = _read_reward_infos_response(
                 deps.api,
                 deps.storage,
+                &env.block,
                 &staker_addr_raw,
                 &Some(staking_token.clone()),
             )?;
@@ -281,6 +853,7 @@ pub fn query_all_reward_infos(
 fn _read_reward_infos_response(
     api: &dyn Api,
     storage: &dyn Storage,
+    block: &BlockInfo,
     staker_addr: &CanonicalAddr,
     staking_token: &Option<Addr>,
 ) -> StdResult<Vec<RewardInfoResponseItem>> {
@@ -289,11 +862,10 @@ fn _read_reward_infos_response(
         .into_iter()
         .map(|(staking_token, mut reward_info)| {
             let asset_key = api.addr_canonicalize(staking_token.as_str())?.to_vec();
-            let pool_info = read_pool_info(storage, &asset_key)?;
+            let synced = simulate_pool_sync(storage, &asset_key, block)?;
+            let pool_info = read_pool_info(&synced, &asset_key)?;
 
-            let pool_index = pool_info.reward_index;
-
-            before_share_change(pool_index, &mut reward_info)?;
+            settle_reward_info(&synced, &asset_key, &pool_info, &mut reward_info)?;
 
             let pending_withdraw = reward_info
                 .pending_withdraw
@@ -306,6 +878,7 @@ fn _read_reward_infos_response(
                 bond_amount: reward_info.bond_amount,
                 pending_reward: reward_info.pending_reward,
                 pending_withdraw,
+                pending_fee_share: reward_info.pending_fee_share,
             })
         })
         .collect::<StdResult<Vec<RewardInfoResponseItem>>>()?;
@@ -343,3 +916,181 @@ fn _read_reward_infos(
 
     Ok(results)
 }
+
+pub fn query_pool_apr(
+    deps: Deps,
+    env: Env,
+    staking_token: Addr,
+    pair_addr: Option<Addr>,
+    usd_denom: Option<String>,
+) -> StdResult<PoolAprResponse> {
+    let asset_key = deps.api.addr_canonicalize(staking_token.as_str())?;
+    let pool_info = read_pool_info(deps.storage, &asset_key)?;
+    let total_bond_amount = pool_info.total_bond_amount;
+
+    let emissions = _read_emissions(deps.storage, &asset_key, env.block.time.seconds())?
+        .into_iter()
+        .map(|rw| {
+            let rw = rw.to_normal(deps.api)?;
+            let per_day = rw.amount.checked_mul(Uint128::from(SECONDS_PER_DAY))?;
+            Ok(RewardEmission {
+                per_second: rw.amount,
+                per_year: rw.amount.checked_mul(Uint128::from(SECONDS_PER_YEAR))?,
+                per_bond_unit_per_day: if total_bond_amount.is_zero() {
+                    Decimal::zero()
+                } else {
+                    Decimal::checked_from_ratio(per_day, total_bond_amount).map_err(|_| {
+                        StdError::generic_err("Reward per bond unit per day is too large")
+                    })?
+                },
+                info: rw.info,
+            })
+        })
+        .collect::<StdResult<Vec<RewardEmission>>>()?;
+
+    let usd_apr = match (pair_addr, usd_denom) {
+        (Some(pair_addr), Some(usd_denom)) if !total_bond_amount.is_zero() => _compute_apr(
+            &deps.querier,
+            &staking_token,
+            &pair_addr,
+            &usd_denom,
+            total_bond_amount,
+            &emissions,
+        )?,
+        _ => None,
+    };
+
+    Ok(PoolAprResponse {
+        staking_token,
+        total_bond_amount,
+        emissions,
+        usd_apr,
+    })
+}
+
+// what the pool is paid per second right now, by asset
+fn _read_emissions(storage: &dyn Storage, asset_key: &[u8], now: u64) -> StdResult<Vec<AssetRaw>> {
+    let mut emissions = read_rewards_per_sec(storage, asset_key).unwrap_or_default();
+
+    for item in
+        ACTIVE_REWARD_SCHEDULES
+            .prefix(asset_key)
+            .range(storage, None, None, Order::Ascending)
+    {
+        let (_, schedule) = item?;
+        if schedule.start_time <= now && now < schedule.end_time {
+            let rate = schedule
+                .asset
+                .amount
+                .multiply_ratio(1u128, schedule.end_time - schedule.start_time);
+            _add_emission(&mut emissions, schedule.asset.info, rate)?;
+        }
+    }
+
+    // the allocated reward is paid with the rewards_per_sec of the pool and its schedules
+    if let Some(pool_allocation) = POOL_ALLOCATIONS.may_load(storage, asset_key)? {
+        let allocation = REWARD_ALLOCATION.load(storage)?;
+        let amount = allocation
+            .reward_per_sec
+            .multiply_ratio(pool_allocation.alloc_point, allocation.total_alloc_point);
+        let total_rewards_per_sec = read_total_rewards_per_sec(storage, asset_key)?;
+        for rw in split_reward_amount(amount, total_rewards_per_sec) {
+            _add_emission(&mut emissions, rw.info, rw.amount)?;
+        }
+    }
+
+    Ok(emissions)
+}
+
+fn _add_emission(
+    emissions: &mut Vec<AssetRaw>,
+    info: AssetInfoRaw,
+    amount: Uint128,
+) -> StdResult<()> {
+    match emissions
+        .iter_mut()
+        .find(|emission| emission.info.eq(&info))
+    {
+        Some(emission) => emission.amount = emission.amount.checked_add(amount)?,
+        None => emissions.push(AssetRaw { info, amount }),
+    }
+    Ok(())
+}
+
+// every value is priced in usd_denom with the oracle of the pair, cw20 pair assets are priced against the other side of the pool
+fn _compute_apr(
+    querier: &QuerierWrapper,
+    staking_token: &Addr,
+    pair_addr: &Addr,
+    usd_denom: &str,
+    total_bond_amount: Uint128,
+    emissions: &[RewardEmission],
+) -> StdResult<Option<Decimal>> {
+    let pair_info: PairInfo =
+        querier.query_wasm_smart(pair_addr.clone(), &PairQueryMsg::Pair {})?;
+    if pair_info.liquidity_token.ne(staking_token) {
+        return Err(StdError::generic_err(
+            "The pair does not belong to the staking token",
+        ));
+    }
+    let pool: PoolResponse = querier.query_wasm_smart(pair_addr.clone(), &PairQueryMsg::Pool {})?;
+    let oracle = OracleContract(pair_info.oracle_addr);
+
+    let price = |info: &AssetInfo| -> Option<Decimal> {
+        if let Some(price) = _query_usd_price(querier, &oracle, usd_denom, info) {
+            return Some(price);
+        }
+        let index = pool.assets.iter().position(|a| a.info.eq(info))?;
+        let (asset, other) = (&pool.assets[index], &pool.assets[1 - index]);
+        let other_price = _query_usd_price(querier, &oracle, usd_denom, &other.info)?;
+        Decimal::checked_from_ratio(other.amount, asset.amount)
+            .ok()?
+            .checked_mul(other_price)
+            .ok()
+    };
+    let value = |amount: Uint128, info: &AssetInfo| -> Option<Uint128> {
+        let price = price(info)?;
+        amount
+            .checked_multiply_ratio(price.numerator(), price.denominator())
+            .ok()
+    };
+
+    let mut pool_value = Uint128::zero();
+    for asset in pool.assets.iter() {
+        match value(asset.amount, &asset.info) {
+            Some(value) => pool_value = pool_value.checked_add(value)?,
+            None => return Ok(None),
+        }
+    }
+    let bonded_value = match pool_value.checked_multiply_ratio(total_bond_amount, pool.total_share)
+    {
+        Ok(bonded_value) if !bonded_value.is_zero() => bonded_value,
+        _ => return Ok(None),
+    };
+
+    let mut reward_value = Uint128::zero();
+    for emission in emissions.iter() {
+        match value(emission.per_year, &emission.info) {
+            Some(value) => reward_value = reward_value.checked_add(value)?,
+            None => return Ok(None),
+        }
+    }
+
+    Ok(Decimal::checked_from_ratio(reward_value, bonded_value).ok())
+}
+
+fn _query_usd_price(
+    querier: &QuerierWrapper,
+    oracle: &OracleContract,
+    usd_denom: &str,
+    info: &AssetInfo,
+) -> Option<Decimal> {
+    match info {
+        AssetInfo::NativeToken { denom } if denom == usd_denom => Some(Decimal::one()),
+        AssetInfo::NativeToken { denom } => oracle
+            .query_exchange_rate(querier, denom.clone(), usd_denom.to_string())
+            .ok()
+            .map(|res| res.item.exchange_rate),
+        AssetInfo::Token { .. } => None,
+    }
+}
@@ -0,0 +1,98 @@
+use crate::msg::Role;
+use crate::state::{read_config, DEFAULT_LIMIT, MAX_LIMIT, PAUSED, ROLE_MEMBERS};
+use cosmwasm_std::{
+    Addr, Deps, DepsMut, MessageInfo, Order, Response, StdError, StdResult, Storage,
+};
+use cw_storage_plus::Bound;
+
+fn role_key(role: &Role) -> &'static str {
+    match role {
+        Role::PoolManager => "pool_manager",
+        Role::FeeManager => "fee_manager",
+        Role::Pauser => "pauser",
+        Role::RewardOperator => "reward_operator",
+        Role::Keeper => "keeper",
+    }
+}
+
+// the owner implicitly holds every role
+pub fn assert_role(deps: Deps, sender: &Addr, role: Role) -> StdResult<()> {
+    let config = read_config(deps.storage)?;
+    if config.owner == deps.api.addr_canonicalize(sender.as_str())?
+        || ROLE_MEMBERS.has(deps.storage, (role_key(&role), sender))
+    {
+        return Ok(());
+    }
+    Err(StdError::generic_err("unauthorized"))
+}
+
+pub fn assert_not_paused(storage: &dyn Storage) -> StdResult<()> {
+    if PAUSED.may_load(storage)?.unwrap_or_default() {
+        return Err(StdError::generic_err("Contract is paused"));
+    }
+    Ok(())
+}
+
+pub fn grant_role(deps: DepsMut, info: MessageInfo, role: Role, addr: Addr) -> StdResult<Response> {
+    let config = read_config(deps.storage)?;
+    if config.owner != deps.api.addr_canonicalize(info.sender.as_str())? {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    let addr = deps.api.addr_validate(addr.as_str())?;
+    ROLE_MEMBERS.save(deps.storage, (role_key(&role), &addr), &true)?;
+
+    Ok(Response::new().add_attributes([
+        ("action", "grant_role"),
+        ("role", role_key(&role)),
+        ("addr", addr.as_str()),
+    ]))
+}
+
+pub fn revoke_role(
+    deps: DepsMut,
+    info: MessageInfo,
+    role: Role,
+    addr: Addr,
+) -> StdResult<Response> {
+    let config = read_config(deps.storage)?;
+    if config.owner != deps.api.addr_canonicalize(info.sender.as_str())? {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    ROLE_MEMBERS.remove(deps.storage, (role_key(&role), &addr));
+
+    Ok(Response::new().add_attributes([
+        ("action", "revoke_role"),
+        ("role", role_key(&role)),
+        ("addr", addr.as_str()),
+    ]))
+}
+
+// bonding, zap bonding and restaking are stopped while paused, leaving the pools is always possible
+pub fn set_paused(deps: DepsMut, info: MessageInfo, paused: bool) -> StdResult<Response> {
+    assert_role(deps.as_ref(), &info.sender, Role::Pauser)?;
+    PAUSED.save(deps.storage, &paused)?;
+
+    Ok(Response::new().add_attributes([("action", "set_paused"), ("paused", &paused.to_string())]))
+}
+
+pub fn query_role_members(
+    deps: Deps,
+    role: Role,
+    start_after: Option<Addr>,
+    limit: Option<u32>,
+) -> StdResult<Vec<Addr>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.as_ref().map(Bound::exclusive);
+
+    ROLE_MEMBERS
+        .prefix(role_key(&role))
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect()
+}
+
+pub fn query_paused(deps: Deps) -> StdResult<bool> {
+    Ok(PAUSED.may_load(deps.storage)?.unwrap_or_default())
+}
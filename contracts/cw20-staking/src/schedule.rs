@@ -0,0 +1,223 @@
+use crate::msg::{RewardScheduleResponse, RewardScheduleStatus, Role};
+use crate::rewards::{
+    accrue_pool_reward, checkpoint_reward_index, close_reward_epoch, strand_pool_reward,
+};
+use crate::roles::assert_role;
+use crate::state::{
+    read_pool_info, store_pool_info, RewardSchedule, ACTIVE_REWARD_SCHEDULES, DEFAULT_LIMIT,
+    MAX_ACTIVE_REWARD_SCHEDULES, MAX_LIMIT, REWARD_SCHEDULES, REWARD_SCHEDULE_COUNT,
+    REWARD_SCHEDULE_SYNC, SCHEDULE_REWARDS_PER_SEC,
+};
+use cosmwasm_std::{
+    Addr, Deps, DepsMut, Env, Order, Response, StdError, StdResult, Storage, Timestamp, Uint128,
+};
+use cw_storage_plus::Bound;
+use oraiswap::asset::{Asset, AssetRaw};
+
+// the asset must be transferred to this contract already
+pub fn add_reward_schedule(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    staking_token: Addr,
+    asset: Asset,
+    start_time: u64,
+    end_time: u64,
+) -> StdResult<Response> {
+    assert_role(deps.as_ref(), &sender, Role::RewardOperator)?;
+
+    let asset_key = deps.api.addr_canonicalize(staking_token.as_str())?;
+    read_pool_info(deps.storage, &asset_key)?;
+
+    let now = env.block.time.seconds();
+    if start_time < now {
+        return Err(StdError::generic_err(
+            "Reward schedule must not start in the past",
+        ));
+    }
+    if end_time <= start_time {
+        return Err(StdError::generic_err(
+            "Reward schedule must end after it starts",
+        ));
+    }
+    if asset.amount < Uint128::from(end_time - start_time) {
+        return Err(StdError::generic_err(
+            "Reward amount must be at least 1 per second",
+        ));
+    }
+
+    // emit the running schedules until now, so the new one only counts from its start time
+    sync_reward_schedules(deps.storage, &asset_key, env.block.time)?;
+    checkpoint_reward_index(deps.storage, &asset_key, env.block.height)?;
+    if !REWARD_SCHEDULE_SYNC.has(deps.storage, &asset_key) {
+        REWARD_SCHEDULE_SYNC.save(deps.storage, &asset_key, &now)?;
+    }
+    // every sync goes through the running schedules
+    let active_schedules = ACTIVE_REWARD_SCHEDULES
+        .prefix(&asset_key)
+        .keys(deps.storage, None, None, Order::Ascending)
+        .count();
+    if active_schedules >= MAX_ACTIVE_REWARD_SCHEDULES {
+        return Err(StdError::generic_err(
+            "Too many reward schedules are running for this pool",
+        ));
+    }
+
+    let asset_raw = asset.to_raw(deps.api)?;
+
+    let id = REWARD_SCHEDULE_COUNT
+        .may_load(deps.storage)?
+        .unwrap_or_default()
+        + 1;
+    REWARD_SCHEDULE_COUNT.save(deps.storage, &id)?;
+    let schedule = RewardSchedule {
+        asset: asset_raw,
+        start_time,
+        end_time,
+    };
+    REWARD_SCHEDULES.save(deps.storage, (&asset_key, id), &schedule)?;
+    ACTIVE_REWARD_SCHEDULES.save(deps.storage, (&asset_key, id), &schedule)?;
+
+    Ok(Response::new().add_attributes([
+        ("action", "add_reward_schedule"),
+        ("staking_token", staking_token.as_str()),
+        ("schedule_id", &id.to_string()),
+        ("amount", &asset.amount.to_string()),
+        ("start_time", &start_time.to_string()),
+        ("end_time", &end_time.to_string()),
+    ]))
+}
+
+// emit the schedules of the pool from the last sync until now. At each schedule boundary, the rates of the
+// running schedules are stored apart from rewards_per_sec and paid on top of it, a change starts a new
+// reward epoch
+pub fn sync_reward_schedules(
+    storage: &mut dyn Storage,
+    asset_key: &[u8],
+    time: Timestamp,
+) -> StdResult<()> {
+    let last_sync = match REWARD_SCHEDULE_SYNC.may_load(storage, asset_key)? {
+        Some(last_sync) => last_sync,
+        None => return Ok(()),
+    };
+    let now = time.seconds();
+    if now <= last_sync {
+        return Ok(());
+    }
+
+    let (ids, schedules): (Vec<u64>, Vec<RewardSchedule>) = ACTIVE_REWARD_SCHEDULES
+        .prefix(asset_key)
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<(u64, RewardSchedule)>>>()?
+        .into_iter()
+        .unzip();
+
+    let mut boundaries: Vec<u64> = schedules
+        .iter()
+        .flat_map(|s| [s.start_time, s.end_time])
+        .filter(|time| *time > last_sync && *time < now)
+        .chain([now])
+        .collect();
+    boundaries.sort_unstable();
+    boundaries.dedup();
+
+    let mut pool_info = read_pool_info(storage, asset_key)?;
+    let mut from = last_sync;
+    for to in boundaries {
+        let mut rates: Vec<AssetRaw> = vec![];
+        let mut emitted: Vec<AssetRaw> = vec![];
+        let mut amount = Uint128::zero();
+        for s in schedules
+            .iter()
+            .filter(|s| s.start_time <= from && s.end_time >= to)
+        {
+            let duration = s.end_time - s.start_time;
+            let rate = s.asset.amount.multiply_ratio(1u128, duration);
+            match rates.iter_mut().find(|r| r.info.eq(&s.asset.info)) {
+                Some(r) => r.amount += rate,
+                None => rates.push(AssetRaw {
+                    info: s.asset.info.clone(),
+                    amount: rate,
+                }),
+            }
+            // emitted until `to` minus emitted until `from`, so the schedule sums up to its amount
+            let emitted_amount = s.asset.amount.multiply_ratio(to - s.start_time, duration)
+                - s.asset.amount.multiply_ratio(from - s.start_time, duration);
+            amount += emitted_amount;
+            match emitted.iter_mut().find(|e| e.info.eq(&s.asset.info)) {
+                Some(e) => e.amount += emitted_amount,
+                None => emitted.push(AssetRaw {
+                    info: s.asset.info.clone(),
+                    amount: emitted_amount,
+                }),
+            }
+        }
+
+        let last_rates = SCHEDULE_REWARDS_PER_SEC
+            .may_load(storage, asset_key)?
+            .unwrap_or_default();
+        if rates != last_rates {
+            close_reward_epoch(storage, asset_key, &mut pool_info)?;
+            if rates.is_empty() {
+                SCHEDULE_REWARDS_PER_SEC.remove(storage, asset_key);
+            } else {
+                SCHEDULE_REWARDS_PER_SEC.save(storage, asset_key, &rates)?;
+            }
+        }
+        if pool_info.total_bond_amount.is_zero() {
+            // the emitted assets are known, so they are stranded as is
+            if !amount.is_zero() {
+                strand_pool_reward(storage, asset_key, &mut pool_info, amount, emitted)?;
+            }
+        } else if !amount.is_zero() {
+            accrue_pool_reward(storage, asset_key, &mut pool_info, amount)?;
+        }
+        from = to;
+    }
+
+    // the finished schedules are fully emitted
+    for (id, schedule) in ids.into_iter().zip(schedules.iter()) {
+        if schedule.end_time <= now {
+            ACTIVE_REWARD_SCHEDULES.remove(storage, (asset_key, id));
+        }
+    }
+
+    store_pool_info(storage, asset_key, &pool_info)?;
+    REWARD_SCHEDULE_SYNC.save(storage, asset_key, &now)
+}
+
+pub fn query_reward_schedules(
+    deps: Deps,
+    env: Env,
+    staking_token: Addr,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<RewardScheduleResponse>> {
+    let asset_key = deps.api.addr_canonicalize(staking_token.as_str())?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+    let now = env.block.time.seconds();
+
+    REWARD_SCHEDULES
+        .prefix(&asset_key)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (id, schedule) = item?;
+            let status = if now < schedule.start_time {
+                RewardScheduleStatus::Upcoming
+            } else if now < schedule.end_time {
+                RewardScheduleStatus::Active
+            } else {
+                RewardScheduleStatus::Finished
+            };
+            Ok(RewardScheduleResponse {
+                id,
+                asset: schedule.asset.to_normal(deps.api)?,
+                start_time: schedule.start_time,
+                end_time: schedule.end_time,
+                status,
+            })
+        })
+        .collect()
+}
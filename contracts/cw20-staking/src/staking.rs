@@ -1,17 +1,25 @@
-use crate::msg::LockInfo;
-use crate::rewards::before_share_change;
+use crate::fee::distribute_unbond_fee;
+use crate::msg::{LockInfo, SimulateUnbondResponse, ZapOutMsg};
+use crate::positions::update_staker_position;
+use crate::rewards::{
+    add_performance_fees, checkpoint_reward_index, fee_share_asset, record_reward_withdraw,
+    settle_reward_info, simulate_pool_sync, split_performance_fee, sync_pool_rewards,
+};
+use crate::roles::assert_not_paused;
 use crate::state::{
-    insert_lock_info, read_config, read_pool_info, read_unbonding_period,
-    remove_and_accumulate_lock_info, remove_and_accumulate_lock_info_restake, rewards_read,
-    rewards_store, stakers_store, store_pool_info, PoolInfo, RewardInfo, STAKED_BALANCES,
-    STAKED_TOTAL, UNBOND_OPTIONS,
+    insert_lock_info, read_lock_amount, read_pool_info, read_unbonding_period,
+    remove_lock_infos_after, remove_lock_infos_until, rewards_read, rewards_store, stakers_store,
+    store_pool_info, PoolInfo, RewardInfo, LOCK_ZAP_OUT, MAX_LOCKS_PER_TX, MAX_UNBOND_FEE,
+    STAKED_BALANCES, STAKED_TOTAL, UNBOND_OPTIONS,
 };
+use crate::stats::{count_new_staker, update_pool_stats};
+use crate::zap::{merge_zap_out, validate_zap_out, zap_out_lp};
 use cosmwasm_std::{
-    attr, to_binary, Addr, Api, CanonicalAddr, CosmosMsg, Decimal, DepsMut, Env, Response,
-    StdError, StdResult, Storage, Uint128, WasmMsg,
+    attr, to_binary, Addr, Api, BlockInfo, CanonicalAddr, CosmosMsg, Decimal, Decimal256, Deps,
+    DepsMut, Env, Order, Response, StdError, StdResult, Storage, Uint128, WasmMsg,
 };
 use cw20::Cw20ExecuteMsg;
-use oraiswap::asset::Asset;
+use oraiswap::asset::{Asset, AssetRaw};
 
 pub fn bond(
     deps: DepsMut,
@@ -20,11 +28,12 @@ pub fn bond(
     staking_token: Addr,
     amount: Uint128,
 ) -> StdResult<Response> {
+    assert_not_paused(deps.storage)?;
     let staker_addr_raw: CanonicalAddr = deps.api.addr_canonicalize(staker_addr.as_str())?;
     _increase_bond_amount(
         deps.storage,
         deps.api,
-        env.block.height,
+        &env.block,
         &staker_addr_raw,
         &staking_token,
         amount,
@@ -45,54 +54,60 @@ pub fn unbond(
     staking_token: Addr,
     amount: Uint128,
     unbond_period: Option<u64>,
+    zap_out: Option<ZapOutMsg>,
 ) -> StdResult<Response> {
     let staker_addr_raw: CanonicalAddr = deps.api.addr_canonicalize(staker_addr.as_str())?;
 
     let asset_key = deps.api.addr_canonicalize(staking_token.as_str())?;
 
     // withdraw_avaiable_lock
-    let mut response = _withdraw_lock(deps.storage, &env, &staker_addr, &staking_token)?;
+    let (mut response, mut zap) = _withdraw_lock(
+        deps.storage,
+        &env,
+        &staker_addr,
+        &staking_token,
+        MAX_LOCKS_PER_TX,
+    )?;
 
     if !amount.is_zero() {
-        let period: u64;
-        let mut amount_after_fee = amount;
-
-        if let Some(unbond_period) = unbond_period {
-            let config = read_config(deps.storage)?;
-            period = unbond_period;
-            // charge fee
-            let fee_percent = UNBOND_OPTIONS
-                .load(deps.storage, (&staking_token, unbond_period))
-                .map_err(|_e| StdError::generic_err("This unbond options doesn't exist"))?;
-
-            let fee_amount = amount * fee_percent;
-            amount_after_fee -= fee_amount;
-
-            // transfer fee to fee_receiver
-            response = response.add_message(WasmMsg::Execute {
-                contract_addr: staking_token.to_string(),
-                msg: to_binary(&Cw20ExecuteMsg::Transfer {
-                    recipient: deps
-                        .api
-                        .addr_humanize(&config.withdraw_fee_receiver)?
-                        .to_string(),
-                    amount: fee_amount,
-                })?,
-                funds: vec![],
-            });
-        } else {
-            period = read_unbonding_period(deps.storage, &asset_key).unwrap_or_default();
+        if let Some(zap_out) = &zap_out {
+            validate_zap_out(
+                deps.storage,
+                deps.api,
+                &deps.querier,
+                &staking_token,
+                zap_out,
+            )?;
         }
 
-        let (_, reward_assets) = _decrease_bond_amount(
+        let (period, fee_amount) = _unbond_period_and_fee(
+            deps.storage,
+            &staking_token,
+            &asset_key,
+            amount,
+            unbond_period,
+        )?;
+        let amount_after_fee = amount - fee_amount;
+
+        let (reward_assets, fee_assets) = _decrease_bond_amount(
             deps.storage,
             deps.api,
-            env.block.height,
+            &env.block,
             &staker_addr_raw,
             &staking_token,
             amount,
         )?;
+        // the stakers share of the fee goes to the ones left in the pool
+        response = response.add_messages(distribute_unbond_fee(
+            deps.storage,
+            deps.api,
+            &staking_token,
+            &asset_key,
+            fee_amount,
+        )?);
         // withdraw pending_withdraw assets (accumulated when changing reward_per_sec)
+        response =
+            add_performance_fees(deps.api, &deps.querier, deps.storage, response, fee_assets)?;
         response = response.add_messages(
             reward_assets
                 .iter()
@@ -103,6 +118,41 @@ pub fn unbond(
 
         if period > 0 {
             let unlock_time = env.block.time.plus_seconds(period);
+            let lock_key = (
+                staking_token.as_bytes(),
+                staker_addr.as_bytes(),
+                unlock_time.seconds(),
+            );
+
+            // an unbond in the same block adds to the same lock, so both must zap out the same way
+            let zap_out =
+                match read_lock_amount(
+                    deps.storage,
+                    staking_token.as_bytes(),
+                    staker_addr.as_bytes(),
+                    unlock_time.seconds(),
+                )? {
+                    None => zap_out,
+                    Some(lock_amount) => {
+                        match (LOCK_ZAP_OUT.may_load(deps.storage, lock_key)?, zap_out) {
+                            (None, None) => None,
+                            (Some(lock_zap_out), Some(zap_out))
+                                if lock_zap_out.pair_addr.eq(&zap_out.pair_addr) =>
+                            {
+                                let (_, zap_out) = merge_zap_out(
+                                    Some((lock_amount, lock_zap_out)),
+                                    amount_after_fee,
+                                    zap_out,
+                                );
+                                Some(zap_out)
+                            }
+                            _ => return Err(StdError::generic_err(
+                                "A lock unlocking at the same time has another zap out preference",
+                            )),
+                        }
+                    }
+                };
+
             insert_lock_info(
                 deps.storage,
                 staking_token.as_bytes(),
@@ -113,6 +163,12 @@ pub fn unbond(
                 },
             )?;
 
+            // keep the zap out preference along with the lock
+            match &zap_out {
+                Some(zap_out) => LOCK_ZAP_OUT.save(deps.storage, lock_key, zap_out)?,
+                None => LOCK_ZAP_OUT.remove(deps.storage, lock_key),
+            }
+
             response = response.add_attributes([
                 attr("action", "unbonding"),
                 attr("staker_addr", staker_addr.as_str()),
@@ -121,13 +177,135 @@ pub fn unbond(
                 attr("unlock_time", unlock_time.seconds().to_string()),
             ])
         } else {
-            let unbond_response = _unbond(&staker_addr, &staking_token, amount_after_fee)?;
-            response = response
-                .add_submessages(unbond_response.messages)
-                .add_attributes(unbond_response.attributes);
+            match zap_out {
+                Some(zap_out) => zap = Some(merge_zap_out(zap, amount_after_fee, zap_out)),
+                None => {
+                    let unbond_response = _unbond(&staker_addr, &staking_token, amount_after_fee)?;
+                    response = response
+                        .add_submessages(unbond_response.messages)
+                        .add_attributes(unbond_response.attributes);
+                }
+            }
         }
     }
-    Ok(response)
+
+    _add_zap_out(deps, &env, response, &staker_addr, &staking_token, zap)
+}
+
+// the zap out reply compares balances, so the withdrawal goes before any other transfer
+fn _add_zap_out(
+    deps: DepsMut,
+    env: &Env,
+    mut response: Response,
+    staker_addr: &Addr,
+    staking_token: &Addr,
+    zap: Option<(Uint128, ZapOutMsg)>,
+) -> StdResult<Response> {
+    let (zap_amount, zap_out) = match zap {
+        None => return Ok(response),
+        Some(zap) => zap,
+    };
+    let zap_response = zap_out_lp(
+        deps.storage,
+        deps.api,
+        &deps.querier,
+        env,
+        staker_addr,
+        staking_token,
+        zap_amount,
+        &zap_out,
+    )?;
+    response.messages.splice(0..0, zap_response.messages);
+    Ok(response.add_attributes(zap_response.attributes))
+}
+
+// same as unbond, without writing to state
+pub fn simulate_unbond(
+    deps: Deps,
+    env: Env,
+    staker_addr: Addr,
+    staking_token: Addr,
+    amount: Uint128,
+    unbond_period: Option<u64>,
+) -> StdResult<SimulateUnbondResponse> {
+    let staker_addr_raw = deps.api.addr_canonicalize(staker_addr.as_str())?;
+    let asset_key = deps.api.addr_canonicalize(staking_token.as_str())?;
+    let synced = simulate_pool_sync(deps.storage, &asset_key, &env.block)?;
+    let pool_info = read_pool_info(&synced, &asset_key)?;
+
+    let (period, fee_amount) = _unbond_period_and_fee(
+        deps.storage,
+        &staking_token,
+        &asset_key,
+        amount,
+        unbond_period,
+    )?;
+
+    let mut reward_info = rewards_read(deps.storage, &staker_addr_raw)
+        .may_load(&asset_key)?
+        .unwrap_or_else(|| RewardInfo {
+            native_token: false,
+            index: Decimal256::zero(),
+            bond_amount: Uint128::zero(),
+            pending_reward: Uint128::zero(),
+            pending_withdraw: vec![],
+            epoch: pool_info.epoch,
+            fee_index: pool_info.fee_index,
+            pending_fee_share: Uint128::zero(),
+        });
+    let exceed_bond_amount = reward_info.bond_amount < amount;
+
+    let reward_assets = if exceed_bond_amount || amount.is_zero() {
+        vec![]
+    } else {
+        settle_reward_info(&synced, &asset_key, &pool_info, &mut reward_info)?;
+        reward_info.bond_amount -= amount;
+        let (claimed_assets, _, fee_shares) = _release_pending_withdraw(
+            &synced,
+            &asset_key,
+            &pool_info.staking_token,
+            &mut reward_info,
+        )?;
+        claimed_assets
+            .into_iter()
+            .chain(fee_shares)
+            .map(|ra| ra.to_normal(deps.api))
+            .collect::<StdResult<Vec<Asset>>>()?
+    };
+
+    Ok(SimulateUnbondResponse {
+        fee_amount,
+        amount_after_fee: amount - fee_amount,
+        unlock_time: if period > 0 {
+            Some(env.block.time.plus_seconds(period))
+        } else {
+            None
+        },
+        reward_assets,
+        exceed_bond_amount,
+    })
+}
+
+pub fn claim_unbonded(
+    deps: DepsMut,
+    env: Env,
+    staker_addr: Addr,
+    staking_token: Addr,
+    limit: Option<u32>,
+) -> StdResult<Response> {
+    let (response, zap) = _withdraw_lock(
+        deps.storage,
+        &env,
+        &staker_addr,
+        &staking_token,
+        limit.unwrap_or(MAX_LOCKS_PER_TX).min(MAX_LOCKS_PER_TX),
+    )?;
+
+    if response.messages.is_empty() && zap.is_none() {
+        return Err(StdError::generic_err("No unlocked lp to claim"));
+    }
+
+    _add_zap_out(deps, &env, response, &staker_addr, &staking_token, zap)
 }
 
 pub fn restake(
@@ -135,15 +313,32 @@ pub fn restake(
     env: Env,
     staker_addr: Addr,
     staking_token: Addr,
+    limit: Option<u32>,
 ) -> StdResult<Response> {
-    // execute 10 lock a time
-    let restake_amount = remove_and_accumulate_lock_info_restake(
+    assert_not_paused(deps.storage)?;
+
+    // execute at most MAX_LOCKS_PER_TX locks a time, the latest first
+    let lock_infos = remove_lock_infos_after(
         deps.storage,
         staking_token.as_bytes(),
         staker_addr.as_bytes(),
         env.block.time,
+        limit.unwrap_or(MAX_LOCKS_PER_TX).min(MAX_LOCKS_PER_TX) as usize,
     )?;
 
+    let mut restake_amount = Uint128::zero();
+    for lock_info in lock_infos {
+        restake_amount += lock_info.amount;
+        LOCK_ZAP_OUT.remove(
+            deps.storage,
+            (
+                staking_token.as_bytes(),
+                staker_addr.as_bytes(),
+                lock_info.unlock_time.seconds(),
+            ),
+        );
+    }
+
     if restake_amount.is_zero() {
         return Ok(Response::new());
     }
@@ -151,7 +346,7 @@ pub fn restake(
     _increase_bond_amount(
         deps.storage,
         deps.api,
-        env.block.height,
+        &env.block,
         &deps.api.addr_canonicalize(staker_addr.as_ref())?,
         &staking_token,
         restake_amount,
@@ -164,53 +359,76 @@ pub fn restake(
         .add_attribute("staking_token", staking_token.to_string()))
 }
 
+// the released lp to zap out is returned along with the response
 pub fn _withdraw_lock(
     storage: &mut dyn Storage,
     env: &Env,
     staker_addr: &Addr,
     staking_token: &Addr,
-) -> StdResult<Response> {
-    // execute 10 lock a time
-    let unlock_amount = remove_and_accumulate_lock_info(
+    limit: u32,
+) -> StdResult<(Response, Option<(Uint128, ZapOutMsg)>)> {
+    // execute at most limit locks a time, the earliest first
+    let lock_infos = remove_lock_infos_until(
         storage,
         staking_token.as_bytes(),
         staker_addr.as_bytes(),
         env.block.time,
+        limit as usize,
     )?;
 
-    if unlock_amount.is_zero() {
-        return Ok(Response::new());
+    let mut unlock_amount = Uint128::zero();
+    let mut zap: Option<(Uint128, ZapOutMsg)> = None;
+    for lock_info in lock_infos {
+        let lock_key = (
+            staking_token.as_bytes(),
+            staker_addr.as_bytes(),
+            lock_info.unlock_time.seconds(),
+        );
+        match LOCK_ZAP_OUT.may_load(storage, lock_key)? {
+            Some(lock_zap_out) => {
+                LOCK_ZAP_OUT.remove(storage, lock_key);
+                zap = Some(merge_zap_out(zap, lock_info.amount, lock_zap_out));
+            }
+            None => unlock_amount += lock_info.amount,
+        }
     }
 
-    let unbond_response = _unbond(staker_addr, staking_token, unlock_amount)?;
+    if unlock_amount.is_zero() {
+        return Ok((Response::new(), zap));
+    }
 
-    Ok(unbond_response)
+    Ok((_unbond(staker_addr, staking_token, unlock_amount)?, zap))
 }
 
 fn _increase_bond_amount(
     storage: &mut dyn Storage,
     api: &dyn Api,
-    height: u64,
+    block: &BlockInfo,
     staker_addr: &CanonicalAddr,
     staking_token: &Addr,
     amount: Uint128,
 ) -> StdResult<()> {
     let asset_key = api.addr_canonicalize(staking_token.as_str())?.to_vec();
+    sync_pool_rewards(storage, &asset_key, block)?;
     let mut pool_info = read_pool_info(storage, &asset_key)?;
     let mut reward_info: RewardInfo = rewards_read(storage, staker_addr)
         .load(&asset_key)
         .unwrap_or_else(|_| RewardInfo {
             native_token: false,
-            index: Decimal::zero(),
+            index: Decimal256::zero(),
             bond_amount: Uint128::zero(),
             pending_reward: Uint128::zero(),
             pending_withdraw: vec![],
+            epoch: pool_info.epoch,
+            fee_index: pool_info.fee_index,
+            pending_fee_share: Uint128::zero(),
         });
 
     // Withdraw reward to pending reward; before changing share
-    before_share_change(pool_info.reward_index, &mut reward_info)?;
+    settle_reward_info(storage, &asset_key, &pool_info, &mut reward_info)?;
 
-    // Increase total bond amount
+    // Increase total bond amount, the rewards deposited while the pool was empty are only
+    // released with the next reward, so bonding right away does not capture them
     pool_info.total_bond_amount += amount;
 
     reward_info.bond_amount += amount;
@@ -218,49 +436,60 @@ fn _increase_bond_amount(
     STAKED_BALANCES.update(
         storage,
         (&asset_key, &api.addr_humanize(staker_addr)?),
-        height,
+        block.height,
         |bal| -> StdResult<Uint128> { Ok(bal.unwrap_or_default().checked_add(amount)?) },
     )?;
 
-    STAKED_TOTAL.update(storage, &asset_key, height, |total| -> StdResult<Uint128> {
-        // Initialized during instantiate - OK to unwrap.
-        Ok(total.unwrap_or_default().checked_add(amount)?)
-    })?;
+    STAKED_TOTAL.update(
+        storage,
+        &asset_key,
+        block.height,
+        |total| -> StdResult<Uint128> {
+            // Initialized during instantiate - OK to unwrap.
+            Ok(total.unwrap_or_default().checked_add(amount)?)
+        },
+    )?;
 
     rewards_store(storage, staker_addr).save(&asset_key, &reward_info)?;
+    update_staker_position(storage, &asset_key, staker_addr, reward_info.bond_amount)?;
 
     store_pool_info(storage, &asset_key, &pool_info)?;
+    checkpoint_reward_index(storage, &asset_key, block.height)?;
 
     // mark this staker belong to the pool the first time
     let mut stakers_bucket = stakers_store(storage, &asset_key);
-    if stakers_bucket.may_load(staker_addr)?.is_none() {
+    let new_staker = stakers_bucket.may_load(staker_addr)?.is_none();
+    if new_staker {
         stakers_bucket.save(staker_addr, &true)?;
     }
 
+    update_pool_stats(storage, &asset_key, |stats| stats.total_bonded += amount)?;
+    if new_staker {
+        count_new_staker(storage, &asset_key, staker_addr)?;
+    }
     Ok(())
 }
 
 fn _decrease_bond_amount(
     storage: &mut dyn Storage,
     api: &dyn Api,
-    height: u64,
+    block: &BlockInfo,
     staker_addr: &CanonicalAddr,
     staking_token: &Addr,
     amount: Uint128,
-) -> StdResult<(CanonicalAddr, Vec<Asset>)> {
+) -> StdResult<(Vec<Asset>, Vec<AssetRaw>)> {
     let asset_key = api.addr_canonicalize(staking_token.as_str())?.to_vec();
+    sync_pool_rewards(storage, &asset_key, block)?;
     let mut pool_info: PoolInfo = read_pool_info(storage, &asset_key)?;
     let mut reward_info: RewardInfo = rewards_read(storage, staker_addr).load(&asset_key)?;
-    let mut reward_assets = vec![];
     if reward_info.bond_amount < amount {
         return Err(StdError::generic_err("Cannot unbond more than bond amount"));
     }
 
-    // if the lp token was migrated, and the user did not close their position yet, cap the reward at the snapshot
-    let (pool_index, staking_token) = (pool_info.reward_index, pool_info.staking_token.clone());
+    let staking_token = pool_info.staking_token.clone();
 
     // Distribute reward to pending reward; before changing share
-    before_share_change(pool_index, &mut reward_info)?;
+    settle_reward_info(storage, &asset_key, &pool_info, &mut reward_info)?;
 
     // Update rewards info
     reward_info.bond_amount = reward_info.bond_amount.checked_sub(amount)?;
@@ -272,29 +501,162 @@ fn _decrease_bond_amount(
     STAKED_BALANCES.update(
         storage,
         (&asset_key, &api.addr_humanize(staker_addr)?),
-        height,
+        block.height,
         |bal| -> StdResult<Uint128> { Ok(bal.unwrap_or_default().checked_sub(amount)?) },
     )?;
-    STAKED_TOTAL.update(storage, &asset_key, height, |total| -> StdResult<Uint128> {
-        // Initialized during instantiate - OK to unwrap.
-        Ok(total.unwrap_or_default().checked_sub(amount)?)
-    })?;
-
-    if reward_info.pending_reward.is_zero() && reward_info.bond_amount.is_zero() {
-        // if pending_withdraw is not empty, then return reward_assets to withdraw money
-        reward_assets = reward_info
-            .pending_withdraw
-            .iter()
-            .map(|ra| ra.to_normal(api))
-            .collect::<StdResult<Vec<Asset>>>()?;
-        reward_info.pending_withdraw = vec![];
-    }
+    STAKED_TOTAL.update(
+        storage,
+        &asset_key,
+        block.height,
+        |total| -> StdResult<Uint128> {
+            // Initialized during instantiate - OK to unwrap.
+            Ok(total.unwrap_or_default().checked_sub(amount)?)
+        },
+    )?;
+
+    let (claimed_assets, fee_assets, fee_shares) =
+        _release_pending_withdraw(storage, &asset_key, &staking_token, &mut reward_info)?;
     rewards_store(storage, staker_addr).save(&asset_key, &reward_info)?;
+    update_staker_position(storage, &asset_key, staker_addr, reward_info.bond_amount)?;
 
     // Update pool info
     store_pool_info(storage, &asset_key, &pool_info)?;
 
-    Ok((staking_token, reward_assets))
+    update_pool_stats(storage, &asset_key, |stats| stats.total_unbonded += amount)?;
+    record_reward_withdraw(
+        storage,
+        staker_addr,
+        &asset_key,
+        &claimed_assets,
+        &fee_assets,
+    )?;
+
+    let reward_assets = claimed_assets
+        .into_iter()
+        .chain(fee_shares)
+        .map(|ra| ra.to_normal(api))
+        .collect::<StdResult<Vec<Asset>>>()?;
+    Ok((reward_assets, fee_assets))
+}
+
+// if pending_withdraw is not empty when the position is closed, return reward_assets to withdraw money.
+// The performance fee is kept as on withdraw, the stakers share of the unbond fees is paid along
+fn _release_pending_withdraw(
+    storage: &dyn Storage,
+    asset_key: &[u8],
+    staking_token: &CanonicalAddr,
+    reward_info: &mut RewardInfo,
+) -> StdResult<(Vec<AssetRaw>, Vec<AssetRaw>, Vec<AssetRaw>)> {
+    if !reward_info.pending_reward.is_zero() || !reward_info.bond_amount.is_zero() {
+        return Ok((vec![], vec![], vec![]));
+    }
+    let (claimed_assets, fee_assets) = split_performance_fee(
+        storage,
+        asset_key,
+        std::mem::take(&mut reward_info.pending_withdraw),
+    )?;
+
+    let mut fee_shares = vec![];
+    if !reward_info.pending_fee_share.is_zero() {
+        fee_shares.push(fee_share_asset(
+            staking_token,
+            reward_info.pending_fee_share,
+        ));
+        reward_info.pending_fee_share = Uint128::zero();
+    }
+    Ok((claimed_assets, fee_assets, fee_shares))
+}
+
+// unbond options charge a fee to skip the default unbonding period
+fn _unbond_period_and_fee(
+    storage: &dyn Storage,
+    staking_token: &Addr,
+    asset_key: &[u8],
+    amount: Uint128,
+    unbond_period: Option<u64>,
+) -> StdResult<(u64, Uint128)> {
+    match unbond_period {
+        Some(unbond_period) => {
+            let fee_percent = UNBOND_OPTIONS
+                .load(storage, (staking_token, unbond_period))
+                .map_err(|_e| StdError::generic_err("This unbond options doesn't exist"))?;
+            Ok((unbond_period, amount * fee_percent))
+        }
+        None => Ok((
+            read_unbonding_period(storage, asset_key).unwrap_or_default(),
+            Uint128::zero(),
+        )),
+    }
+}
+
+// an option skips part of the default unbonding period, a longer period must cost less
+// the unbond options must stay shorter than the default unbonding period
+pub fn validate_unbonding_period(
+    storage: &dyn Storage,
+    staking_token: &Addr,
+    unbonding_period: u64,
+) -> StdResult<()> {
+    let longest = UNBOND_OPTIONS
+        .prefix(staking_token)
+        .keys(storage, None, None, Order::Descending)
+        .next()
+        .transpose()?;
+    if longest.map_or(false, |period| period >= unbonding_period) {
+        return Err(StdError::generic_err(
+            "Unbonding period must be longer than the unbond options",
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_max_unbond_fee(max_fee: Decimal) -> StdResult<()> {
+    if max_fee > Decimal::one() {
+        return Err(StdError::generic_err(
+            "Max unbond fee must be less than or equal 1",
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_unbond_option(
+    storage: &dyn Storage,
+    api: &dyn Api,
+    staking_token: &Addr,
+    period: u64,
+    fee: Decimal,
+) -> StdResult<()> {
+    let max_fee = MAX_UNBOND_FEE.may_load(storage)?.unwrap_or(Decimal::one());
+    if fee > max_fee {
+        return Err(StdError::generic_err(format!(
+            "Unbond fee must be less than or equal {}",
+            max_fee
+        )));
+    }
+
+    let asset_key = api.addr_canonicalize(staking_token.as_str())?;
+    if read_pool_info(storage, &asset_key).is_err() {
+        return Err(StdError::generic_err("Staking token is not registered"));
+    }
+    if period >= read_unbonding_period(storage, &asset_key).unwrap_or_default() {
+        return Err(StdError::generic_err(
+            "Unbond period must be shorter than the default unbonding period",
+        ));
+    }
+
+    for item in UNBOND_OPTIONS
+        .prefix(staking_token)
+        .range(storage, None, None, Order::Ascending)
+    {
+        let (other_period, other_fee) = item?;
+        if (other_period < period && other_fee <= fee)
+            || (other_period > period && other_fee >= fee)
+        {
+            return Err(StdError::generic_err(
+                "Unbond fee must decrease as the period grows",
+            ));
+        }
+    }
+    Ok(())
 }
 
 fn _unbond(staker_addr: &Addr, staking_token_addr: &Addr, amount: Uint128) -> StdResult<Response> {
@@ -1,10 +1,17 @@
-use crate::msg::LockInfo;
+use crate::msg::{
+    AdminChange, EmptyPoolPolicy, GaugeWeight, LockInfo, OperatorScope, UnbondFeeSplit, ZapOutMsg,
+};
 use cosmwasm_schema::cw_serde;
-use cw_storage_plus::{Map, SnapshotMap, Strategy};
-use oraiswap::{asset::AssetRaw, querier::calc_range_start};
+use cw20::Expiration;
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex, SnapshotMap, Strategy};
+use oraiswap::{
+    asset::{Asset, AssetRaw},
+    querier::calc_range_start,
+};
 
 use cosmwasm_std::{
-    Addr, CanonicalAddr, Decimal, Order, StdError, StdResult, Storage, Timestamp, Uint128,
+    Addr, CanonicalAddr, Decimal, Decimal256, Order, StdError, StdResult, Storage, Timestamp,
+    Uint128,
 };
 use cosmwasm_storage::{singleton, singleton_read, Bucket, ReadonlyBucket};
 
@@ -19,15 +26,209 @@ pub static LOCK_INFO: &[u8] = b"locking_users";
 
 // unbond option
 pub const UNBOND_OPTIONS: Map<(&Addr, u64), Decimal> = Map::new("unbond_options");
+// cap on the fee of new unbond options, 1 by default
+pub const MAX_UNBOND_FEE: Item<Decimal> = Item::new("max_unbond_fee");
+// the whole fee goes to the withdraw_fee_receiver by default
+pub const UNBOND_FEE_SPLIT: Item<UnbondFeeSplit> = Item::new("unbond_fee_split");
+pub const UNBOND_FEE_TOTALS: Map<&[u8], UnbondFeeTotals> = Map::new("unbond_fee_totals");
+
+// share of the withdrawn rewards kept by the protocol, per pool
+pub const PERFORMANCE_FEES: Map<&[u8], Decimal> = Map::new("performance_fees");
+pub const MAX_PERFORMANCE_FEE: Decimal = Decimal::percent(20);
+pub const PERFORMANCE_FEE_TOTALS: Map<&[u8], Vec<AssetRaw>> = Map::new("performance_fee_totals");
+
+// lifetime totals per pool
+pub const POOL_STATS: Map<&[u8], PoolStats> = Map::new("pool_stats");
+// the last staker counted into unique_stakers of a pool migrated before the stats, an empty key
+// when none is counted yet, removed once every staker is counted
+pub const UNIQUE_STAKERS_BACKFILL: Map<&[u8], Vec<u8>> = Map::new("unique_stakers_backfill");
+
+// lifetime rewards paid to each staker, keyed by (staker, staking_token)
+pub const STAKER_CLAIMED_REWARDS: Map<(&[u8], &[u8]), Vec<AssetRaw>> =
+    Map::new("staker_claimed_rewards");
+
+// zap out preference of a lock, keyed by (staking_token, staker, unlock_time)
+pub const LOCK_ZAP_OUT: Map<(&[u8], &[u8], u64), ZapOutMsg> = Map::new("lock_zap_out");
+
+// zap bond, cached between providing liquidity and bonding the minted lp in the reply
+pub const ZAP_BOND_CACHE: Item<ZapBondCache> = Item::new("zap_bond_cache");
+
+// zap out, cached between withdrawing liquidity and forwarding the received assets in the reply
+pub const ZAP_OUT_CACHE: Item<ZapOutCache> = Item::new("zap_out_cache");
+
+// keyed by (asset_key, epoch)
+pub const REWARD_EPOCHS: Map<(&[u8], u64), RewardEpoch> = Map::new("reward_epochs");
+// pending_reward of the pool split by the rewards_per_sec it was deposited with
+pub const STRANDED_REWARDS: Map<&[u8], Vec<AssetRaw>> = Map::new("stranded_rewards");
+
+// reward schedules of each pool, keyed by (asset_key, id)
+pub const REWARD_SCHEDULES: Map<(&[u8], u64), RewardSchedule> = Map::new("reward_schedules");
+pub const REWARD_SCHEDULE_COUNT: Item<u64> = Item::new("reward_schedule_count");
+// the schedules not finished at the last sync, the only ones the sync goes through
+pub const ACTIVE_REWARD_SCHEDULES: Map<(&[u8], u64), RewardSchedule> =
+    Map::new("active_reward_schedules");
+pub const MAX_ACTIVE_REWARD_SCHEDULES: usize = 10;
+// the last time the schedules of the pool were emitted, only set for pools with schedules
+pub const REWARD_SCHEDULE_SYNC: Map<&[u8], u64> = Map::new("reward_schedule_sync");
+// the rates of the schedules running at the last sync, paid on top of the rewards_per_sec
+pub const SCHEDULE_REWARDS_PER_SEC: Map<&[u8], Vec<AssetRaw>> =
+    Map::new("schedule_rewards_per_sec");
+
+// a single reward budget split among the pools by alloc point
+pub const REWARD_ALLOCATION: Item<RewardAllocation> = Item::new("reward_allocation");
+// alloc point of each pool, keyed by asset_key
+pub const POOL_ALLOCATIONS: Map<&[u8], PoolAllocation> = Map::new("pool_allocations");
+
+// gauge voting, stakers of the governance pool steer the alloc points of the other pools
+pub const GAUGE_CONFIG: Item<GaugeConfig> = Item::new("gauge_config");
+pub const GAUGE_EPOCH_ID: Item<u64> = Item::new("gauge_epoch_id");
+pub const GAUGE_EPOCHS: Map<u64, GaugeEpoch> = Map::new("gauge_epochs");
+// keyed by (epoch, voter)
+pub const GAUGE_VOTES: Map<(u64, &Addr), GaugeVoteInfo> = Map::new("gauge_votes");
+// votes of each pool, keyed by (epoch, staking_token)
+pub const GAUGE_TALLIES: Map<(u64, &Addr), Uint128> = Map::new("gauge_tallies");
+
+// role members besides the owner, keyed by (role, addr)
+pub const ROLE_MEMBERS: Map<(&str, &Addr), bool> = Map::new("role_members");
+pub const PAUSED: Item<bool> = Item::new("paused");
+
+// sensitive admin changes wait this many seconds, they apply right away while it is zero
+pub const TIMELOCK_DELAY: Item<u64> = Item::new("timelock_delay");
+pub const DEFAULT_TIMELOCK_DELAY: u64 = 86400;
+pub const PENDING_ADMIN_CHANGES: Map<u64, PendingAdminChange> = Map::new("pending_admin_changes");
+pub const PENDING_ADMIN_CHANGE_COUNT: Item<u64> = Item::new("pending_admin_change_count");
+
+// operator approvals, keyed by (staker, operator)
+pub const OPERATORS: Map<(&Addr, &Addr), OperatorApproval> = Map::new("operators");
+
+// reward assets being swapped on withdraw, refunded as is if the swap fails
+pub const SWAP_REWARD_REFUNDS: Map<u64, SwapRewardRefund> = Map::new("swap_reward_refunds");
 
 pub const DEFAULT_LIMIT: u32 = 10;
 pub const MAX_LIMIT: u32 = 30;
+// bound the gas used to release or restake locks in a single transaction
+pub const MAX_LOCKS_PER_TX: u32 = 30;
 
 #[cw_serde]
 pub struct Config {
     pub owner: CanonicalAddr,
     pub rewarder: CanonicalAddr,
     pub withdraw_fee_receiver: CanonicalAddr,
+    pub router: Option<CanonicalAddr>,
+    #[serde(default)]
+    pub empty_pool_policy: EmptyPoolPolicy,
+    #[serde(default)]
+    pub fee_collector: Option<CanonicalAddr>,
+    #[serde(default)]
+    pub factory: Option<CanonicalAddr>,
+}
+
+#[cw_serde]
+pub struct ZapBondCache {
+    pub staker_addr: Addr,
+    pub staking_token: Addr,
+    // the minted share is read from the events of this pair
+    pub pair_addr: Addr,
+    pub minimum_bond_amount: Option<Uint128>,
+}
+
+#[cw_serde]
+pub struct ZapOutCache {
+    pub staker_addr: Addr,
+    // balances of the pair assets held by this contract before withdrawing liquidity
+    pub prev_balances: Vec<Asset>,
+    pub minimum_receive: Vec<Asset>,
+}
+
+#[cw_serde]
+pub struct RewardSchedule {
+    // funded amount, emitted linearly from start_time to end_time
+    pub asset: AssetRaw,
+    pub start_time: u64,
+    pub end_time: u64,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct RewardAllocation {
+    pub total_alloc_point: u64,
+    // accumulated reward of a single alloc point
+    pub reward_per_point: Decimal256,
+    // part of the deposits truncated from reward_per_point, carried over to the next one
+    #[serde(default)]
+    pub reward_dust: Uint128,
+    // deposited per second since the previous deposit, the allocated emission of the apr
+    #[serde(default)]
+    pub reward_per_sec: Uint128,
+    #[serde(default)]
+    pub last_deposit_time: u64,
+}
+
+#[cw_serde]
+pub struct PoolAllocation {
+    pub alloc_point: u64,
+    // reward_per_point when the pool was last settled
+    pub reward_per_point: Decimal256,
+}
+
+#[cw_serde]
+pub struct GaugeConfig {
+    pub governance_token: Addr,
+    pub epoch_duration: u64,
+}
+
+#[cw_serde]
+pub struct GaugeEpoch {
+    pub start_time: u64,
+    // voting power is the governance pool bond at this height
+    pub start_height: u64,
+    pub end_time: u64,
+}
+
+#[cw_serde]
+pub struct GaugeVoteInfo {
+    pub power: Uint128,
+    pub weights: Vec<GaugeWeight>,
+}
+
+#[cw_serde]
+pub struct OperatorApproval {
+    pub expires: Expiration,
+    pub scopes: Vec<OperatorScope>,
+}
+
+#[cw_serde]
+pub struct SwapRewardRefund {
+    pub recipient: Addr,
+    pub asset: Asset,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct PoolStats {
+    // distributed to the stakers, split by the rewards_per_sec they are paid with
+    pub rewards_deposited: Vec<AssetRaw>,
+    // paid to the stakers, after the performance fee
+    pub rewards_claimed: Vec<AssetRaw>,
+    pub total_bonded: Uint128,
+    pub total_unbonded: Uint128,
+    pub unbond_fees: Uint128,
+    // the stakers of a pool migrated before the stats are counted by CountUniqueStakers
+    pub unique_stakers: u64,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct UnbondFeeTotals {
+    pub treasury: Uint128,
+    pub stakers: Uint128,
+    pub burned: Uint128,
+}
+
+#[cw_serde]
+pub struct PendingAdminChange {
+    pub change: AdminChange,
+    pub eta: u64,
 }
 
 pub fn store_config(storage: &mut dyn Storage, config: &Config) -> StdResult<()> {
@@ -43,7 +244,16 @@ pub struct PoolInfo {
     pub staking_token: CanonicalAddr,
     pub pending_reward: Uint128, // not distributed amount due to zero bonding
     pub total_bond_amount: Uint128,
-    pub reward_index: Decimal,
+    pub reward_index: Decimal256,
+    // part of the deposits truncated from reward_index, carried over to the next one
+    #[serde(default)]
+    pub reward_dust: Uint128,
+    // increased each time rewards_per_sec changes
+    #[serde(default)]
+    pub epoch: u64,
+    // staking token per bond from the stakers share of the unbond fees
+    #[serde(default)]
+    pub fee_index: Decimal256,
 }
 
 pub fn store_pool_info(
@@ -58,6 +268,10 @@ pub fn read_pool_info(storage: &dyn Storage, asset_key: &[u8]) -> StdResult<Pool
     ReadonlyBucket::new(storage, PREFIX_POOL_INFO).load(asset_key)
 }
 
+pub fn pool_infos_read(storage: &dyn Storage) -> ReadonlyBucket<PoolInfo> {
+    ReadonlyBucket::new(storage, PREFIX_POOL_INFO)
+}
+
 pub fn read_all_pool_infos(storage: &dyn Storage) -> StdResult<Vec<(Vec<u8>, PoolInfo)>> {
     ReadonlyBucket::<PoolInfo>::new(storage, PREFIX_POOL_INFO)
         .range(None, None, cosmwasm_std::Order::Ascending)
@@ -67,11 +281,27 @@ pub fn read_all_pool_infos(storage: &dyn Storage) -> StdResult<Vec<(Vec<u8>, Poo
 #[cw_serde]
 pub struct RewardInfo {
     pub native_token: bool,
-    pub index: Decimal,
+    pub index: Decimal256,
     pub bond_amount: Uint128,
     pub pending_reward: Uint128,
     // this is updated by the owner of this contract, when changing the reward_per_sec
     pub pending_withdraw: Vec<AssetRaw>,
+    // the pool epoch of pending_reward, older epochs are settled lazily
+    #[serde(default)]
+    pub epoch: u64,
+    #[serde(default)]
+    pub fee_index: Decimal256,
+    // staking token from the stakers share of the unbond fees, paid apart from the rewards
+    #[serde(default)]
+    pub pending_fee_share: Uint128,
+}
+
+// a finished reward epoch of a pool
+#[cw_serde]
+pub struct RewardEpoch {
+    pub rewards_per_sec: Vec<AssetRaw>,
+    // the pool reward_index when the epoch ended
+    pub end_index: Decimal256,
 }
 
 /// returns a bucket with all rewards owned by this staker (query it by staker)
@@ -111,6 +341,24 @@ pub fn read_rewards_per_sec(storage: &dyn Storage, asset_key: &[u8]) -> StdResul
     weight_bucket.load(asset_key)
 }
 
+// the rewards_per_sec set for the pool plus the rates of its running reward schedules
+pub fn read_total_rewards_per_sec(
+    storage: &dyn Storage,
+    asset_key: &[u8],
+) -> StdResult<Vec<AssetRaw>> {
+    let mut total = read_rewards_per_sec(storage, asset_key).unwrap_or_default();
+    let schedule_rates = SCHEDULE_REWARDS_PER_SEC
+        .may_load(storage, asset_key)?
+        .unwrap_or_default();
+    for rate in schedule_rates {
+        match total.iter_mut().find(|rw| rw.info.eq(&rate.info)) {
+            Some(rw) => rw.amount = rw.amount.checked_add(rate.amount)?,
+            None => total.push(rate),
+        }
+    }
+    Ok(total)
+}
+
 pub fn store_unbonding_period(
     storage: &mut dyn Storage,
     asset_key: &[u8],
@@ -123,16 +371,27 @@ pub fn read_unbonding_period(storage: &dyn Storage, asset_key: &[u8]) -> StdResu
     ReadonlyBucket::new(storage, UNBONDING_PERIOD).load(asset_key)
 }
 
+// unbonds unlocking at the same time are merged into one lock
 pub fn insert_lock_info(
     storage: &mut dyn Storage,
     asset_key: &[u8],
     user: &[u8],
     lock_info: LockInfo,
 ) -> StdResult<()> {
-    Bucket::multilevel(storage, &[LOCK_INFO, asset_key, user]).save(
-        &lock_info.unlock_time.seconds().to_be_bytes(),
-        &lock_info.amount,
-    )
+    let mut bucket = Bucket::multilevel(storage, &[LOCK_INFO, asset_key, user]);
+    let key = lock_info.unlock_time.seconds().to_be_bytes();
+    let amount: Uint128 = bucket.may_load(&key)?.unwrap_or_default();
+    bucket.save(&key, &(amount + lock_info.amount))
+}
+
+pub fn read_lock_amount(
+    storage: &dyn Storage,
+    asset_key: &[u8],
+    user: &[u8],
+    unlock_time: u64,
+) -> StdResult<Option<Uint128>> {
+    ReadonlyBucket::multilevel(storage, &[LOCK_INFO, asset_key, user])
+        .may_load(&unlock_time.to_be_bytes())
 }
 
 pub fn read_user_lock_info(
@@ -170,66 +429,112 @@ pub fn read_user_lock_info(
         .collect()
 }
 
-pub fn remove_and_accumulate_lock_info(
+pub fn read_all_user_lock_info(
+    storage: &dyn Storage,
+    asset_key: &[u8],
+    user: &[u8],
+) -> StdResult<Vec<LockInfo>> {
+    ReadonlyBucket::multilevel(storage, &[LOCK_INFO, asset_key, user])
+        .range(None, None, Order::Ascending)
+        .map(|item| {
+            let (time, amount) = item?;
+            Ok(LockInfo {
+                unlock_time: Timestamp::from_seconds(u64::from_be_bytes(
+                    time.try_into()
+                        .map_err(|_| StdError::generic_err("Casting u64 to timestamp fail"))?,
+                )),
+                amount,
+            })
+        })
+        .collect()
+}
+
+// remove the locks that are unlocked at the given timestamp, returning them in ascending order
+pub fn remove_lock_infos_until(
     storage: &mut dyn Storage,
     asset_key: &[u8],
     user: &[u8],
     timestamp: Timestamp,
-) -> StdResult<Uint128> {
+    limit: usize,
+) -> StdResult<Vec<LockInfo>> {
     let mut bucket = Bucket::<Uint128>::multilevel(storage, &[LOCK_INFO, asset_key, user]);
-    let mut remove_timestamps = vec![];
-    let mut accumulate_amount = Uint128::zero();
+    let mut lock_infos = vec![];
 
     // use temporay cursor
     {
-        let mut cursor = bucket.range(None, None, Order::Ascending);
+        let mut cursor = bucket.range(None, None, Order::Ascending).take(limit);
         let time_in_seconds = timestamp.seconds().to_be_bytes().to_vec();
         while let Some(Ok((time, amount))) = cursor.next() {
             if time.cmp(&time_in_seconds) == std::cmp::Ordering::Greater {
                 break;
             }
-            remove_timestamps.push(time);
-            accumulate_amount += amount;
+            lock_infos.push((time, amount));
         }
     }
 
-    // remove timestamp
-    for time in remove_timestamps {
-        bucket.remove(&time);
-    }
-
-    Ok(accumulate_amount)
+    _remove_lock_infos(&mut bucket, lock_infos)
 }
 
-pub fn remove_and_accumulate_lock_info_restake(
+// remove the locks that are still locked at the given timestamp, returning them in descending order
+pub fn remove_lock_infos_after(
     storage: &mut dyn Storage,
     asset_key: &[u8],
     user: &[u8],
     timestamp: Timestamp,
-) -> StdResult<Uint128> {
+    limit: usize,
+) -> StdResult<Vec<LockInfo>> {
     let mut bucket = Bucket::<Uint128>::multilevel(storage, &[LOCK_INFO, asset_key, user]);
-    let mut remove_timestamps = vec![];
-    let mut accumulate_amount = Uint128::zero();
+    let mut lock_infos = vec![];
 
     // use temporay cursor
     {
-        let mut cursor = bucket.range(None, None, Order::Descending);
+        let mut cursor = bucket.range(None, None, Order::Descending).take(limit);
         let time_in_seconds = timestamp.seconds().to_be_bytes().to_vec();
         while let Some(Ok((time, amount))) = cursor.next() {
             if time.cmp(&time_in_seconds) == std::cmp::Ordering::Less {
                 break;
             }
-            remove_timestamps.push(time);
-            accumulate_amount += amount;
+            lock_infos.push((time, amount));
         }
     }
 
-    // remove timestamp
-    for time in remove_timestamps {
-        bucket.remove(&time);
-    }
+    _remove_lock_infos(&mut bucket, lock_infos)
+}
+
+// the number and the total amount of locks that are unlocked at the given timestamp
+pub fn count_unlocked_lock_infos(
+    storage: &dyn Storage,
+    asset_key: &[u8],
+    user: &[u8],
+    timestamp: Timestamp,
+) -> StdResult<(u64, Uint128)> {
+    let time_in_seconds = timestamp.seconds().to_be_bytes().to_vec();
+    let end = calc_range_start(Some(time_in_seconds));
+    ReadonlyBucket::<Uint128>::multilevel(storage, &[LOCK_INFO, asset_key, user])
+        .range(None, end.as_deref(), Order::Ascending)
+        .try_fold((0u64, Uint128::zero()), |(count, total), item| {
+            let (_, amount) = item?;
+            Ok((count + 1, total + amount))
+        })
+}
 
-    Ok(accumulate_amount)
+fn _remove_lock_infos(
+    bucket: &mut Bucket<Uint128>,
+    lock_infos: Vec<(Vec<u8>, Uint128)>,
+) -> StdResult<Vec<LockInfo>> {
+    lock_infos
+        .into_iter()
+        .map(|(time, amount)| {
+            bucket.remove(&time);
+            Ok(LockInfo {
+                unlock_time: Timestamp::from_seconds(u64::from_be_bytes(
+                    time.try_into()
+                        .map_err(|_| StdError::generic_err("Casting u64 to timestamp fail"))?,
+                )),
+                amount,
+            })
+        })
+        .collect()
 }
 
 pub const STAKED_BALANCES: SnapshotMap<(&[u8], &Addr), Uint128> = SnapshotMap::new(
@@ -245,3 +550,57 @@ pub const STAKED_TOTAL: SnapshotMap<&[u8], Uint128> = SnapshotMap::new(
     "total_staked__changelog",
     Strategy::EveryBlock,
 );
+
+// reward_index of each pool, saved at the heights it changed
+pub const REWARD_INDEX_HISTORY: SnapshotMap<&[u8], Decimal256> = SnapshotMap::new(
+    "reward_index_history",
+    "reward_index_history__checkpoints",
+    "reward_index_history__changelog",
+    Strategy::EveryBlock,
+);
+
+// a non zero bond of a staker in a pool, indexed for the pool, staker and leaderboard lookups
+#[cw_serde]
+pub struct StakerPosition {
+    pub staking_token: CanonicalAddr,
+    pub staker_addr: CanonicalAddr,
+    pub bond_amount: Uint128,
+}
+
+// (staking_token, staker)
+type StakerPositionKey = (Vec<u8>, Vec<u8>);
+
+pub struct StakerPositionIndexes<'a> {
+    // pools of a staker
+    pub staker: MultiIndex<'a, Vec<u8>, StakerPosition, StakerPositionKey>,
+    // stakers of a pool by bond amount
+    pub bond_amount: MultiIndex<'a, (Vec<u8>, u128), StakerPosition, StakerPositionKey>,
+}
+
+impl<'a> IndexList<StakerPosition> for StakerPositionIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<StakerPosition>> + '_> {
+        let v: Vec<&dyn Index<StakerPosition>> = vec![&self.staker, &self.bond_amount];
+        Box::new(v.into_iter())
+    }
+}
+
+// keyed by (staking_token, staker)
+pub fn staker_positions<'a>(
+) -> IndexedMap<'a, (&'a [u8], &'a [u8]), StakerPosition, StakerPositionIndexes<'a>> {
+    let indexes = StakerPositionIndexes {
+        staker: MultiIndex::new(
+            |_pk, position| position.staker_addr.to_vec(),
+            "staker_positions",
+            "staker_positions__staker",
+        ),
+        bond_amount: MultiIndex::new(
+            |_pk, position| (position.staking_token.to_vec(), position.bond_amount.u128()),
+            "staker_positions",
+            "staker_positions__bond_amount",
+        ),
+    };
+    IndexedMap::new("staker_positions", indexes)
+}
+
+// number of stakers with a non zero bond in each pool
+pub const POOL_STAKER_COUNTS: Map<&[u8], u64> = Map::new("pool_staker_counts");
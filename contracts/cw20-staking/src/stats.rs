@@ -0,0 +1,188 @@
+use crate::msg::{ClaimedRewardsItem, ClaimedRewardsResponse, PoolStatsResponse, Role};
+use crate::roles::assert_role;
+use crate::state::{
+    stakers_read, PoolStats, DEFAULT_LIMIT, MAX_LIMIT, POOL_STATS, STAKER_CLAIMED_REWARDS,
+    UNIQUE_STAKERS_BACKFILL,
+};
+use cosmwasm_std::{
+    Addr, CanonicalAddr, Deps, DepsMut, MessageInfo, Order, Response, StdError, StdResult, Storage,
+};
+use oraiswap::asset::{Asset, AssetRaw};
+use oraiswap::querier::calc_range_start;
+
+pub fn update_pool_stats<F>(storage: &mut dyn Storage, asset_key: &[u8], update: F) -> StdResult<()>
+where
+    F: FnOnce(&mut PoolStats),
+{
+    let mut stats = POOL_STATS.may_load(storage, asset_key)?.unwrap_or_default();
+    update(&mut stats);
+    POOL_STATS.save(storage, asset_key, &stats)
+}
+
+// a new staker the backfill of the pool has not reached yet is counted by the backfill
+pub fn count_new_staker(
+    storage: &mut dyn Storage,
+    asset_key: &[u8],
+    staker_addr: &CanonicalAddr,
+) -> StdResult<()> {
+    if let Some(last_counted) = UNIQUE_STAKERS_BACKFILL.may_load(storage, asset_key)? {
+        if staker_addr.as_slice() > last_counted.as_slice() {
+            return Ok(());
+        }
+    }
+    update_pool_stats(storage, asset_key, |stats| stats.unique_stakers += 1)
+}
+
+// count a page of the stakers of a migrated pool, from where the last call stopped
+pub fn count_unique_stakers(
+    deps: DepsMut,
+    info: MessageInfo,
+    staking_token: Addr,
+    limit: Option<u32>,
+) -> StdResult<Response> {
+    assert_role(deps.as_ref(), &info.sender, Role::Keeper)?;
+
+    let asset_key = deps.api.addr_canonicalize(staking_token.as_str())?;
+    let last_counted = UNIQUE_STAKERS_BACKFILL
+        .may_load(deps.storage, &asset_key)?
+        .ok_or_else(|| StdError::generic_err("The stakers of the pool are counted already"))?;
+    let start_after = if last_counted.is_empty() {
+        None
+    } else {
+        Some(last_counted)
+    };
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    let staker_keys = stakers_read(deps.storage, &asset_key)
+        .range(
+            calc_range_start(start_after).as_deref(),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| item.map(|(staker_key, _)| staker_key))
+        .collect::<StdResult<Vec<Vec<u8>>>>()?;
+
+    let counted = staker_keys.len() as u64;
+    update_pool_stats(deps.storage, &asset_key, |stats| {
+        stats.unique_stakers += counted
+    })?;
+    // a short page is the last one
+    let done = staker_keys.len() < limit;
+    if done {
+        UNIQUE_STAKERS_BACKFILL.remove(deps.storage, &asset_key);
+    } else if let Some(last_staker) = staker_keys.last() {
+        UNIQUE_STAKERS_BACKFILL.save(deps.storage, &asset_key, last_staker)?;
+    }
+
+    Ok(Response::new().add_attributes([
+        ("action", "count_unique_stakers"),
+        ("staking_token", staking_token.as_str()),
+        ("counted", &counted.to_string()),
+        ("done", &done.to_string()),
+    ]))
+}
+
+// add the amounts to the totals of the same asset
+pub fn add_asset_totals(totals: &mut Vec<AssetRaw>, assets: &[AssetRaw]) {
+    for asset in assets.iter().filter(|asset| !asset.amount.is_zero()) {
+        match totals.iter_mut().find(|total| total.info.eq(&asset.info)) {
+            Some(total) => total.amount += asset.amount,
+            None => totals.push(asset.clone()),
+        }
+    }
+}
+
+pub fn record_rewards_deposited(
+    storage: &mut dyn Storage,
+    asset_key: &[u8],
+    assets: &[AssetRaw],
+) -> StdResult<()> {
+    update_pool_stats(storage, asset_key, |stats| {
+        add_asset_totals(&mut stats.rewards_deposited, assets)
+    })
+}
+
+// count the rewards paid to the staker in both the pool and the staker totals
+pub fn record_rewards_claimed(
+    storage: &mut dyn Storage,
+    staker_addr: &CanonicalAddr,
+    asset_key: &[u8],
+    assets: &[AssetRaw],
+) -> StdResult<()> {
+    if assets.is_empty() {
+        return Ok(());
+    }
+    let mut claimed = STAKER_CLAIMED_REWARDS
+        .may_load(storage, (staker_addr, asset_key))?
+        .unwrap_or_default();
+    add_asset_totals(&mut claimed, assets);
+    STAKER_CLAIMED_REWARDS.save(storage, (staker_addr, asset_key), &claimed)?;
+
+    update_pool_stats(storage, asset_key, |stats| {
+        add_asset_totals(&mut stats.rewards_claimed, assets)
+    })
+}
+
+pub fn query_pool_stats(deps: Deps, staking_token: Addr) -> StdResult<PoolStatsResponse> {
+    let asset_key = deps.api.addr_canonicalize(staking_token.as_str())?;
+    let stats = POOL_STATS
+        .may_load(deps.storage, &asset_key)?
+        .unwrap_or_default();
+    let to_normal = |assets: Vec<AssetRaw>| {
+        assets
+            .into_iter()
+            .map(|asset| asset.to_normal(deps.api))
+            .collect::<StdResult<Vec<Asset>>>()
+    };
+
+    Ok(PoolStatsResponse {
+        staking_token,
+        rewards_deposited: to_normal(stats.rewards_deposited)?,
+        rewards_claimed: to_normal(stats.rewards_claimed)?,
+        total_bonded: stats.total_bonded,
+        total_unbonded: stats.total_unbonded,
+        unbond_fees: stats.unbond_fees,
+        unique_stakers: stats.unique_stakers,
+    })
+}
+
+// pools the staker has left are kept, their rewards info is removed on the last withdraw
+pub fn query_claimed_rewards(
+    deps: Deps,
+    staker_addr: Addr,
+    staking_token: Option<Addr>,
+) -> StdResult<ClaimedRewardsResponse> {
+    let staker_addr_raw = deps.api.addr_canonicalize(staker_addr.as_str())?;
+    let claimed_pairs = match staking_token {
+        Some(staking_token) => {
+            let asset_key = deps.api.addr_canonicalize(staking_token.as_str())?;
+            STAKER_CLAIMED_REWARDS
+                .may_load(deps.storage, (&staker_addr_raw, &asset_key))?
+                .map(|claimed| vec![(asset_key.to_vec(), claimed)])
+                .unwrap_or_default()
+        }
+        None => STAKER_CLAIMED_REWARDS
+            .prefix(&staker_addr_raw)
+            .range(deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<(Vec<u8>, Vec<AssetRaw>)>>>()?,
+    };
+
+    let claimed_rewards = claimed_pairs
+        .into_iter()
+        .map(|(asset_key, claimed)| {
+            Ok(ClaimedRewardsItem {
+                staking_token: deps.api.addr_humanize(&CanonicalAddr::from(asset_key))?,
+                claimed: claimed
+                    .into_iter()
+                    .map(|asset| asset.to_normal(deps.api))
+                    .collect::<StdResult<Vec<Asset>>>()?,
+            })
+        })
+        .collect::<StdResult<Vec<ClaimedRewardsItem>>>()?;
+
+    Ok(ClaimedRewardsResponse {
+        staker_addr,
+        claimed_rewards,
+    })
+}
@@ -1,12 +1,22 @@
-use crate::contract::{execute, instantiate, query};
+use crate::contract::{execute, instantiate, migrate, query};
 use crate::msg::{
-    ConfigResponse, Cw20HookMsg, ExecuteMsg, InstantiateMsg, PoolInfoResponse, QueryMsg,
-    RewardInfoResponse, UnbondOptionResponse,
+    AdminChange, ConfigResponse, Cw20HookMsg, EmptyPoolPolicy, ExecuteMsg, InstantiateMsg,
+    MigrateMsg, PendingAdminChangeResponse, PoolInfoResponse, PoolStatsResponse, QueryMsg,
+    RewardIndexAtHeightResponse, RewardInfoResponse, Role, UnbondFeeSplit, UnbondOptionResponse,
 };
+use crate::state::{
+    read_pool_info, stakers_store, DEFAULT_TIMELOCK_DELAY, PREFIX_POOL_INFO, TIMELOCK_DELAY,
+};
+use cosmwasm_schema::cw_serde;
 use cosmwasm_std::testing::{
     mock_dependencies, mock_dependencies_with_balance, mock_env, mock_info,
 };
-use cosmwasm_std::{attr, coin, from_binary, to_binary, Addr, Decimal, Order, StdError, Uint128};
+use cosmwasm_std::{
+    attr, coin, from_binary, to_binary, Addr, Api, CanonicalAddr, Decimal, Decimal256, Deps, Order,
+    StdError, Uint128,
+};
+use cosmwasm_storage::Bucket;
+use cw2::get_contract_version;
 use cw20::Cw20ReceiveMsg;
 use oraiswap::asset::{Asset, AssetInfo, ORAI_DENOM};
 
@@ -18,6 +28,7 @@ fn proper_initialization() {
         owner: Some(Addr::unchecked("owner")),
         rewarder: Addr::unchecked("reward"),
         withdraw_fee_receiver: Addr::unchecked("withdraw_fee_receiver"),
+        timelock_delay: None,
     };
 
     let info = mock_info("addr", &[]);
@@ -33,9 +44,17 @@ fn proper_initialization() {
             owner: Addr::unchecked("owner"),
             rewarder: Addr::unchecked("reward"),
             withdraw_fee_receiver: Addr::unchecked("withdraw_fee_receiver"),
+            router: None,
+            empty_pool_policy: EmptyPoolPolicy::Accumulate,
+            fee_collector: None,
+            factory: None,
         },
         config
     );
+
+    // admin changes are timelocked by default
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::TimelockDelay {}).unwrap();
+    assert_eq!(from_binary::<u64>(&res).unwrap(), DEFAULT_TIMELOCK_DELAY);
 }
 
 #[test]
@@ -46,30 +65,83 @@ fn update_config() {
         owner: Some(Addr::unchecked("owner")),
         rewarder: Addr::unchecked("reward"),
         withdraw_fee_receiver: Addr::unchecked("withdraw_fee_receiver"),
+        timelock_delay: None,
     };
 
     let info = mock_info("addr", &[]);
     let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-    // update owner
+    // update owner and rewarder
     let info = mock_info("owner", &[]);
     let msg = ExecuteMsg::UpdateConfig {
         owner: Some(Addr::unchecked("owner2")),
-        rewarder: None,
+        rewarder: Some(Addr::unchecked("reward2")),
         withdraw_fee_receiver: None,
+        router: None,
+        empty_pool_policy: None,
+        fee_collector: None,
+        factory: None,
     };
 
     let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
     assert_eq!(0, res.messages.len());
 
+    // both changes are queued behind the timelock
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let config: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!(config.owner, Addr::unchecked("owner"));
+    assert_eq!(config.rewarder, Addr::unchecked("reward"));
+
+    let eta = mock_env().block.time.seconds() + DEFAULT_TIMELOCK_DELAY;
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::PendingAdminChanges {
+            start_after: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+    let pending: Vec<PendingAdminChangeResponse> = from_binary(&res).unwrap();
+    assert_eq!(
+        pending,
+        vec![
+            PendingAdminChangeResponse {
+                id: 1,
+                change: AdminChange::Owner {
+                    owner: Addr::unchecked("owner2"),
+                },
+                eta,
+            },
+            PendingAdminChangeResponse {
+                id: 2,
+                change: AdminChange::Rewarder {
+                    rewarder: Addr::unchecked("reward2"),
+                },
+                eta,
+            },
+        ]
+    );
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(DEFAULT_TIMELOCK_DELAY);
+    for id in [1, 2] {
+        let msg = ExecuteMsg::ExecuteAdminChange { id };
+        execute(deps.as_mut(), env.clone(), mock_info("addr", &[]), msg).unwrap();
+    }
+
     // it worked, let's query the state
     let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
     let config: ConfigResponse = from_binary(&res).unwrap();
     assert_eq!(
         ConfigResponse {
             owner: Addr::unchecked("owner2"),
-            rewarder: Addr::unchecked("reward"),
+            rewarder: Addr::unchecked("reward2"),
             withdraw_fee_receiver: Addr::unchecked("withdraw_fee_receiver"),
+            router: None,
+            empty_pool_policy: EmptyPoolPolicy::Accumulate,
+            fee_collector: None,
+            factory: None,
         },
         config
     );
@@ -80,6 +152,10 @@ fn update_config() {
         rewarder: None,
         owner: None,
         withdraw_fee_receiver: None,
+        router: None,
+        empty_pool_policy: None,
+        fee_collector: None,
+        factory: None,
     };
 
     let res = execute(deps.as_mut(), mock_env(), info, msg);
@@ -97,6 +173,7 @@ fn test_register() {
         owner: Some(Addr::unchecked("owner")),
         rewarder: Addr::unchecked("reward"),
         withdraw_fee_receiver: Addr::unchecked("withdraw_fee_receiver"),
+        timelock_delay: None,
     };
 
     let info = mock_info("addr", &[]);
@@ -142,9 +219,10 @@ fn test_register() {
         PoolInfoResponse {
             staking_token: Addr::unchecked("staking"),
             total_bond_amount: Uint128::zero(),
-            reward_index: Decimal::zero(),
+            reward_index: Decimal256::zero(),
             pending_reward: Uint128::zero(),
             unbonding_period: None,
+            performance_fee: Decimal::zero(),
         }
     );
 }
@@ -157,6 +235,7 @@ fn test_query_staker_pagination() {
         owner: Some(Addr::unchecked("owner")),
         rewarder: Addr::unchecked("reward"),
         withdraw_fee_receiver: Addr::unchecked("withdraw_fee_receiver"),
+        timelock_delay: None,
     };
 
     let info = mock_info("addr", &[]);
@@ -226,6 +305,7 @@ fn test_register_unbond_options() {
         owner: Some(Addr::unchecked("owner")),
         rewarder: Addr::unchecked("reward"),
         withdraw_fee_receiver: Addr::unchecked("withdraw_fee_receiver"),
+        timelock_delay: Some(0),
     };
 
     let info = mock_info("addr", &[]);
@@ -260,6 +340,48 @@ fn test_register_unbond_options() {
         _ => panic!("Must return invalid fee"),
     }
 
+    // register failed, the pool is not registered
+    let msg = ExecuteMsg::UpdateUnbondOption {
+        staking_token: Addr::unchecked("staking_token"),
+        period: 86400,
+        fee: Decimal::from_ratio(1u128, 10u128),
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        msg.clone(),
+    );
+    assert_eq!(
+        res.unwrap_err(),
+        StdError::generic_err("Staking token is not registered")
+    );
+
+    let register_msg = ExecuteMsg::RegisterAsset {
+        staking_token: Addr::unchecked("staking_token"),
+        unbonding_period: Some(86400),
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        register_msg,
+    )
+    .unwrap();
+
+    // register failed, the period is not shorter than the default one
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg);
+    assert_eq!(
+        res.unwrap_err(),
+        StdError::generic_err("Unbond period must be shorter than the default unbonding period")
+    );
+
+    let msg = ExecuteMsg::UpdateUnbondingPeriod {
+        staking_token: Addr::unchecked("staking_token"),
+        unbonding_period: 100000,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
     // register successful
     let msg = ExecuteMsg::UpdateUnbondOption {
         staking_token: Addr::unchecked("staking_token"),
@@ -283,6 +405,47 @@ fn test_register_unbond_options() {
     .unwrap();
     assert_eq!(fee, Decimal::from_ratio(1u128, 10u128));
 
+    // register failed, a shorter period must cost more
+    let msg = ExecuteMsg::UpdateUnbondOption {
+        staking_token: Addr::unchecked("staking_token"),
+        period: 50000,
+        fee: Decimal::from_ratio(1u128, 10u128),
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg);
+    assert_eq!(
+        res.unwrap_err(),
+        StdError::generic_err("Unbond fee must decrease as the period grows")
+    );
+
+    // register failed, above the max fee
+    let msg = ExecuteMsg::UpdateMaxUnbondFee {
+        max_fee: Decimal::from_ratio(15u128, 100u128),
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr", &[]),
+        msg.clone(),
+    );
+    assert_eq!(res.unwrap_err(), StdError::generic_err("unauthorized"));
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::UpdateUnbondOption {
+        staking_token: Addr::unchecked("staking_token"),
+        period: 50000,
+        fee: Decimal::from_ratio(2u128, 10u128),
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg);
+    assert_eq!(
+        res.unwrap_err(),
+        StdError::generic_err("Unbond fee must be less than or equal 0.15")
+    );
+
+    let msg = ExecuteMsg::UpdateMaxUnbondFee {
+        max_fee: Decimal::from_ratio(3u128, 10u128),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
     // add other option
     let msg = ExecuteMsg::UpdateUnbondOption {
         staking_token: Addr::unchecked("staking_token"),
@@ -317,6 +480,17 @@ fn test_register_unbond_options() {
         ]
     );
 
+    // the default period can not drop to an option period
+    let msg = ExecuteMsg::UpdateUnbondingPeriod {
+        staking_token: Addr::unchecked("staking_token"),
+        unbonding_period: 86400,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg);
+    assert_eq!(
+        res.unwrap_err(),
+        StdError::generic_err("Unbonding period must be longer than the unbond options")
+    );
+
     // remove unbond option
     let msg = ExecuteMsg::RemoveUnbondOption {
         staking_token: Addr::unchecked("staking_token"),
@@ -355,3 +529,548 @@ fn test_register_unbond_options() {
         }]
     );
 }
+
+#[test]
+fn test_migrate_reward_index() {
+    let mut deps = mock_dependencies();
+
+    let msg = InstantiateMsg {
+        owner: Some(Addr::unchecked("owner")),
+        rewarder: Addr::unchecked("reward"),
+        withdraw_fee_receiver: Addr::unchecked("withdraw_fee_receiver"),
+        timelock_delay: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr", &[]), msg).unwrap();
+
+    // pool info of the previous version, with a Decimal index and no reward_dust
+    #[cw_serde]
+    struct LegacyPoolInfo {
+        staking_token: CanonicalAddr,
+        pending_reward: Uint128,
+        total_bond_amount: Uint128,
+        reward_index: Decimal,
+        epoch: u64,
+    }
+    let asset_key = deps.api.addr_canonicalize("staking").unwrap();
+    Bucket::new(&mut deps.storage, PREFIX_POOL_INFO)
+        .save(
+            &asset_key,
+            &LegacyPoolInfo {
+                staking_token: asset_key.clone(),
+                pending_reward: Uint128::zero(),
+                total_bond_amount: Uint128::from(100u128),
+                reward_index: Decimal::from_ratio(123456789u128, 1000000000000u128),
+                epoch: 1,
+            },
+        )
+        .unwrap();
+    // older versions had no timelock and did not count the stakers
+    TIMELOCK_DELAY.remove(&mut deps.storage);
+    for staker in ["addr", "addr2"] {
+        let staker_addr = deps.api.addr_canonicalize(staker).unwrap();
+        stakers_store(&mut deps.storage, &asset_key)
+            .save(&staker_addr, &true)
+            .unwrap();
+    }
+
+    let msg = MigrateMsg {
+        owner: Addr::unchecked("owner"),
+        rewarder: Addr::unchecked("reward"),
+        withdraw_fee_receiver: Addr::unchecked("withdraw_fee_receiver"),
+    };
+    migrate(deps.as_mut(), mock_env(), msg).unwrap();
+
+    let pool_info = read_pool_info(&deps.storage, &asset_key).unwrap();
+    assert_eq!(
+        pool_info.reward_index,
+        Decimal256::from_ratio(123456789u128, 1000000000000u128)
+    );
+    assert_eq!(pool_info.reward_dust, Uint128::zero());
+    assert_eq!(
+        TIMELOCK_DELAY.load(&deps.storage).unwrap(),
+        DEFAULT_TIMELOCK_DELAY
+    );
+    // the stakers are counted a page at a time, a staker bonding in between is counted once
+    let unique_stakers = |deps: Deps| {
+        let res = query(
+            deps,
+            mock_env(),
+            QueryMsg::PoolStats {
+                staking_token: Addr::unchecked("staking"),
+            },
+        )
+        .unwrap();
+        from_binary::<PoolStatsResponse>(&res)
+            .unwrap()
+            .unique_stakers
+    };
+    assert_eq!(unique_stakers(deps.as_ref()), 0);
+    let count = |limit: Option<u32>| ExecuteMsg::CountUniqueStakers {
+        staking_token: Addr::unchecked("staking"),
+        limit,
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr", &[]),
+        count(None),
+    );
+    assert_eq!(res, Err(StdError::generic_err("unauthorized")));
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        count(Some(1)),
+    )
+    .unwrap();
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr3".to_string(),
+        amount: Uint128::from(100u128),
+        msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+    });
+    execute(deps.as_mut(), mock_env(), mock_info("staking", &[]), msg).unwrap();
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        count(None),
+    )
+    .unwrap();
+    assert!(res.attributes.contains(&attr("done", "true")));
+    assert_eq!(unique_stakers(deps.as_ref()), 3);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        count(None),
+    );
+    assert_eq!(
+        res,
+        Err(StdError::generic_err(
+            "The stakers of the pool are counted already"
+        ))
+    );
+
+    // the reward index history starts with the migration
+    let mut env = mock_env();
+    env.block.height += 1;
+    let res = query(
+        deps.as_ref(),
+        env,
+        QueryMsg::RewardIndexAtHeight {
+            staking_token: Addr::unchecked("staking"),
+            height: None,
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        from_binary::<RewardIndexAtHeightResponse>(&res)
+            .unwrap()
+            .reward_index,
+        Decimal256::from_ratio(123456789u128, 1000000000000u128)
+    );
+    assert_eq!(
+        get_contract_version(&deps.storage).unwrap().version,
+        env!("CARGO_PKG_VERSION")
+    );
+}
+
+#[test]
+fn test_roles() {
+    let mut deps = mock_dependencies();
+
+    let msg = InstantiateMsg {
+        owner: Some(Addr::unchecked("owner")),
+        rewarder: Addr::unchecked("reward"),
+        withdraw_fee_receiver: Addr::unchecked("withdraw_fee_receiver"),
+        timelock_delay: None,
+    };
+    let _res = instantiate(deps.as_mut(), mock_env(), mock_info("addr", &[]), msg).unwrap();
+
+    let register_msg = ExecuteMsg::RegisterAsset {
+        staking_token: Addr::unchecked("staking"),
+        unbonding_period: None,
+    };
+
+    // only the owner can grant roles
+    let msg = ExecuteMsg::GrantRole {
+        role: Role::PoolManager,
+        addr: Addr::unchecked("ops"),
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("ops", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(res, StdError::generic_err("unauthorized"));
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("ops", &[]),
+        register_msg.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(res, StdError::generic_err("unauthorized"));
+
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+    let msg = ExecuteMsg::GrantRole {
+        role: Role::Pauser,
+        addr: Addr::unchecked("guardian"),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::RoleMembers {
+            role: Role::PoolManager,
+            start_after: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+    let members: Vec<Addr> = from_binary(&res).unwrap();
+    assert_eq!(members, vec![Addr::unchecked("ops")]);
+
+    // the pool manager can register, but holds no other role
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("ops", &[]),
+        register_msg,
+    )
+    .unwrap();
+    let msg = ExecuteMsg::UpdateRewardsPerSec {
+        staking_token: Addr::unchecked("staking"),
+        assets: vec![Asset {
+            info: AssetInfo::NativeToken {
+                denom: ORAI_DENOM.to_string(),
+            },
+            amount: Uint128::from(100u128),
+        }],
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("ops", &[]), msg).unwrap_err();
+    assert_eq!(res, StdError::generic_err("unauthorized"));
+
+    let msg = ExecuteMsg::RevokeRole {
+        role: Role::PoolManager,
+        addr: Addr::unchecked("ops"),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+    let msg = ExecuteMsg::RegisterAsset {
+        staking_token: Addr::unchecked("staking2"),
+        unbonding_period: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("ops", &[]), msg).unwrap_err();
+    assert_eq!(res, StdError::generic_err("unauthorized"));
+
+    let bond_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr".to_string(),
+        amount: Uint128::from(100u128),
+        msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+    });
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("staking", &[]),
+        bond_msg.clone(),
+    )
+    .unwrap();
+
+    let msg = ExecuteMsg::SetPaused { paused: true };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("ops", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(res, StdError::generic_err("unauthorized"));
+    execute(deps.as_mut(), mock_env(), mock_info("guardian", &[]), msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Paused {}).unwrap();
+    assert!(from_binary::<bool>(&res).unwrap());
+
+    // bonding is stopped, unbonding still works
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("staking", &[]),
+        bond_msg,
+    )
+    .unwrap_err();
+    assert_eq!(res, StdError::generic_err("Contract is paused"));
+
+    let msg = ExecuteMsg::Unbond {
+        staking_token: Addr::unchecked("staking"),
+        amount: Uint128::from(100u128),
+        unbond_period: None,
+        zap_out: None,
+        staker_addr: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("addr", &[]), msg).unwrap();
+}
+
+#[test]
+fn test_timelock() {
+    let mut deps = mock_dependencies();
+
+    let msg = InstantiateMsg {
+        owner: Some(Addr::unchecked("owner")),
+        rewarder: Addr::unchecked("reward"),
+        withdraw_fee_receiver: Addr::unchecked("withdraw_fee_receiver"),
+        timelock_delay: Some(0),
+    };
+    let _res = instantiate(deps.as_mut(), mock_env(), mock_info("addr", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::UpdateTimelockDelay { delay: 100 };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(res, StdError::generic_err("unauthorized"));
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::RegisterAsset {
+        staking_token: Addr::unchecked("staking"),
+        unbonding_period: Some(100),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    // the fee is queued, not applied
+    let msg = ExecuteMsg::UpdateUnbondOption {
+        staking_token: Addr::unchecked("staking"),
+        period: 10,
+        fee: Decimal::percent(1),
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+    let eta = mock_env().block.time.seconds() + 100;
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "queue_admin_change"),
+            attr("change_id", "1"),
+            attr("eta", eta.to_string()),
+        ]
+    );
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::UnbondOptions {
+            staking_token: Addr::unchecked("staking"),
+        },
+    )
+    .unwrap();
+    let options: Vec<UnbondOptionResponse> = from_binary(&res).unwrap();
+    assert!(options.is_empty());
+
+    let msg = ExecuteMsg::UpdateConfig {
+        owner: None,
+        rewarder: None,
+        withdraw_fee_receiver: Some(Addr::unchecked("attacker")),
+        router: None,
+        empty_pool_policy: None,
+        fee_collector: None,
+        factory: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::PendingAdminChanges {
+            start_after: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+    let pending: Vec<PendingAdminChangeResponse> = from_binary(&res).unwrap();
+    assert_eq!(
+        pending,
+        vec![
+            PendingAdminChangeResponse {
+                id: 1,
+                change: AdminChange::UnbondOption {
+                    staking_token: Addr::unchecked("staking"),
+                    period: 10,
+                    fee: Decimal::percent(1),
+                },
+                eta,
+            },
+            PendingAdminChangeResponse {
+                id: 2,
+                change: AdminChange::WithdrawFeeReceiver {
+                    withdraw_fee_receiver: Addr::unchecked("attacker"),
+                },
+                eta,
+            },
+        ]
+    );
+
+    // a pauser can cancel the fee receiver change
+    let msg = ExecuteMsg::GrantRole {
+        role: Role::Pauser,
+        addr: Addr::unchecked("guardian"),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+    let msg = ExecuteMsg::CancelAdminChange { id: 2 };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(res, StdError::generic_err("unauthorized"));
+    execute(deps.as_mut(), mock_env(), mock_info("guardian", &[]), msg).unwrap();
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr", &[]),
+        ExecuteMsg::ExecuteAdminChange { id: 2 },
+    )
+    .unwrap_err();
+    assert_eq!(res, StdError::generic_err("Admin change not found"));
+
+    // anyone can execute once the delay has passed
+    let msg = ExecuteMsg::ExecuteAdminChange { id: 1 };
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(99);
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(
+        res,
+        StdError::generic_err("Admin change is still timelocked")
+    );
+    env.block.time = env.block.time.plus_seconds(1);
+    execute(deps.as_mut(), env.clone(), mock_info("addr", &[]), msg).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::UnbondOptions {
+            staking_token: Addr::unchecked("staking"),
+        },
+    )
+    .unwrap();
+    let options: Vec<UnbondOptionResponse> = from_binary(&res).unwrap();
+    assert_eq!(
+        options,
+        vec![UnbondOptionResponse {
+            period: 10,
+            fee: Decimal::percent(1),
+        }]
+    );
+    let res = query(deps.as_ref(), env.clone(), QueryMsg::Config {}).unwrap();
+    let config: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        config.withdraw_fee_receiver,
+        Addr::unchecked("withdraw_fee_receiver")
+    );
+
+    // a shorter delay waits for the current one
+    let msg = ExecuteMsg::UpdateTimelockDelay { delay: 0 };
+    execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), msg).unwrap();
+    let res = query(deps.as_ref(), env.clone(), QueryMsg::TimelockDelay {}).unwrap();
+    assert_eq!(from_binary::<u64>(&res).unwrap(), 100);
+
+    // the performance fee and its collector are queued as well
+    let msg = ExecuteMsg::UpdatePerformanceFee {
+        staking_token: Addr::unchecked("staking"),
+        fee: Decimal::percent(10),
+    };
+    execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), msg).unwrap();
+    let msg = ExecuteMsg::UpdateConfig {
+        owner: None,
+        rewarder: None,
+        withdraw_fee_receiver: None,
+        router: None,
+        empty_pool_policy: None,
+        fee_collector: Some(Addr::unchecked("attacker")),
+        factory: None,
+    };
+    execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), msg).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::PoolInfo {
+            staking_token: Addr::unchecked("staking"),
+        },
+    )
+    .unwrap();
+    let pool_info: PoolInfoResponse = from_binary(&res).unwrap();
+    assert_eq!(pool_info.performance_fee, Decimal::zero());
+    let res = query(deps.as_ref(), env.clone(), QueryMsg::Config {}).unwrap();
+    let config: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!(config.fee_collector, None);
+
+    // so are the unbond fee split and the max unbond fee
+    let msg = ExecuteMsg::UpdateUnbondFeeSplit {
+        treasury: Decimal::percent(50),
+        stakers: Decimal::percent(50),
+        burn: Decimal::zero(),
+    };
+    execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), msg).unwrap();
+    let msg = ExecuteMsg::UpdateMaxUnbondFee {
+        max_fee: Decimal::percent(5),
+    };
+    execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), msg).unwrap();
+
+    let res = query(deps.as_ref(), env.clone(), QueryMsg::UnbondFeeSplit {}).unwrap();
+    assert_eq!(
+        from_binary::<UnbondFeeSplit>(&res).unwrap(),
+        UnbondFeeSplit::default()
+    );
+    let res = query(deps.as_ref(), env.clone(), QueryMsg::MaxUnbondFee {}).unwrap();
+    assert_eq!(from_binary::<Decimal>(&res).unwrap(), Decimal::one());
+
+    let res = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::PendingAdminChanges {
+            start_after: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+    let pending: Vec<PendingAdminChangeResponse> = from_binary(&res).unwrap();
+    let ids: Vec<u64> = pending
+        .iter()
+        .filter(|item| {
+            matches!(
+                item.change,
+                AdminChange::UnbondFeeSplit { .. } | AdminChange::MaxUnbondFee { .. }
+            )
+        })
+        .map(|item| item.id)
+        .collect();
+    assert_eq!(ids.len(), 2);
+
+    env.block.time = env.block.time.plus_seconds(100);
+    for id in ids {
+        let msg = ExecuteMsg::ExecuteAdminChange { id };
+        execute(deps.as_mut(), env.clone(), mock_info("addr", &[]), msg).unwrap();
+    }
+    let res = query(deps.as_ref(), env.clone(), QueryMsg::UnbondFeeSplit {}).unwrap();
+    assert_eq!(
+        from_binary::<UnbondFeeSplit>(&res).unwrap(),
+        UnbondFeeSplit {
+            treasury: Decimal::percent(50),
+            stakers: Decimal::percent(50),
+            burn: Decimal::zero(),
+        }
+    );
+    let res = query(deps.as_ref(), env, QueryMsg::MaxUnbondFee {}).unwrap();
+    assert_eq!(from_binary::<Decimal>(&res).unwrap(), Decimal::percent(5));
+}
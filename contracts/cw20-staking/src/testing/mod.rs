@@ -1,3 +1,4 @@
 mod contract_test;
 mod reward_test;
 mod staking_test;
+mod zap_test;
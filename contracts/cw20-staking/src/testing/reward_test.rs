@@ -1,16 +1,33 @@
-use crate::contract::{execute, instantiate, query};
+use crate::contract::{execute, instantiate, query, reply};
 use crate::msg::{
-    Cw20HookMsg, ExecuteMsg, InstantiateMsg, PoolInfoResponse, QueryMsg, RewardInfoResponse,
-    RewardInfoResponseItem, RewardMsg,
+    AllocPoint, AllocPointsResponse, BeliefPrice, Cw20HookMsg, EmptyPoolPolicy, ExecuteMsg,
+    GaugeEpochResponse, GaugeTally, GaugeVoteResponse, GaugeWeight, InstantiateMsg,
+    PoolAprResponse, PoolInfoResponse, QueryMsg, RewardEmission, RewardIndexAtHeightResponse,
+    RewardInfoResponse, RewardInfoResponseItem, RewardMsg, RewardScheduleResponse,
+    RewardScheduleStatus, RewardsPerSecResponse, StrandedRewardResponse,
 };
-use crate::state::{read_pool_info, rewards_read, store_pool_info, PoolInfo, RewardInfo};
+use crate::rewards::{MAX_SWAP_REWARDS, SWAP_REWARD_REPLY_ID};
+use crate::state::{
+    read_pool_info, rewards_read, store_pool_info, PoolInfo, RewardInfo,
+    MAX_ACTIVE_REWARD_SCHEDULES, REWARD_ALLOCATION, STRANDED_REWARDS,
+};
+use cosmwasm_schema::cw_serde;
 use cosmwasm_std::testing::{mock_dependencies_with_balance, mock_env, mock_info};
-use cosmwasm_std::{coin, from_binary, to_binary, Addr, Api, Decimal, Uint128};
+use cosmwasm_std::{
+    attr, coin, from_binary, to_binary, Addr, Api, BankMsg, ContractResult, CosmosMsg, Decimal,
+    Decimal256, Deps, Env, Reply, StdError, SubMsg, SubMsgResult, SystemResult, Uint128, WasmMsg,
+    WasmQuery,
+};
 use cw20::Cw20ReceiveMsg;
-use oraiswap::asset::{Asset, AssetInfo, ORAI_DENOM};
+use oraiswap::asset::{Asset, AssetInfo, PairInfo, ORAI_DENOM};
 use oraiswap::create_entry_points_testing;
+use oraiswap::oracle::{ExchangeRateItem, ExchangeRateResponse};
+use oraiswap::pair::{PoolResponse, QueryMsg as PairQueryMsg};
+use oraiswap::router::{ExecuteMsg as RouterExecuteMsg, SwapOperation};
 use oraiswap::testing::{MockApp, ATOM_DENOM};
 
+const USD_DENOM: &str = "usdt";
+
 #[test]
 fn test_deposit_reward() {
     let mut deps = mock_dependencies_with_balance(&[
@@ -23,6 +40,7 @@ fn test_deposit_reward() {
         owner: Some(Addr::unchecked("owner")),
         rewarder: Addr::unchecked("rewarder"),
         withdraw_fee_receiver: Addr::unchecked("withdraw_fee_receiver"),
+        timelock_delay: None,
     };
 
     let info = mock_info("addr", &[]);
@@ -100,7 +118,7 @@ fn test_deposit_reward() {
         res_cmp,
         PoolInfoResponse {
             total_bond_amount: Uint128::from(100u128),
-            reward_index: Decimal::from_ratio(100u128, 100u128),
+            reward_index: Decimal256::from_ratio(100u128, 100u128),
             ..res
         }
     );
@@ -110,7 +128,7 @@ fn test_deposit_reward() {
         &mut deps.storage,
         &staking_token_cannonical,
         &PoolInfo {
-            reward_index: Decimal::zero(),
+            reward_index: Decimal256::zero(),
             ..pool_info
         },
     )
@@ -134,7 +152,7 @@ fn test_deposit_reward() {
         res_cmp,
         PoolInfoResponse {
             total_bond_amount: Uint128::from(100u128),
-            reward_index: Decimal::from_ratio(100u128, 100u128),
+            reward_index: Decimal256::from_ratio(100u128, 100u128),
             ..res
         }
     );
@@ -151,6 +169,7 @@ fn test_deposit_reward_when_no_bonding() {
         owner: Some(Addr::unchecked("owner")),
         rewarder: Addr::unchecked("rewarder"),
         withdraw_fee_receiver: Addr::unchecked("withdraw_fee_receiver"),
+        timelock_delay: None,
     };
 
     let info = mock_info("addr", &[]);
@@ -215,8 +234,8 @@ fn test_deposit_reward_when_no_bonding() {
     assert_eq!(
         res_cmp,
         PoolInfoResponse {
-            reward_index: Decimal::zero(),
-            pending_reward: Uint128::zero(),
+            reward_index: Decimal256::zero(),
+            pending_reward: Uint128::from(100u128),
             ..res
         }
     );
@@ -231,6 +250,7 @@ fn test_deposit_reward_when_no_bonding() {
         },
     )
     .unwrap();
+    STRANDED_REWARDS.remove(&mut deps.storage, &asset_key);
 
     let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
@@ -249,11 +269,314 @@ fn test_deposit_reward_when_no_bonding() {
     assert_eq!(
         res_cmp,
         PoolInfoResponse {
-            reward_index: Decimal::zero(),
-            pending_reward: Uint128::zero(),
+            reward_index: Decimal256::zero(),
+            pending_reward: Uint128::from(100u128),
             ..res
         }
     );
+
+    let res: Vec<StrandedRewardResponse> =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::StrandedRewards {}).unwrap())
+            .unwrap();
+    assert_eq!(
+        res,
+        vec![StrandedRewardResponse {
+            staking_token: Addr::unchecked("staking"),
+            pending_reward: Uint128::from(100u128),
+            assets: vec![
+                Asset {
+                    info: AssetInfo::NativeToken {
+                        denom: ORAI_DENOM.to_string(),
+                    },
+                    amount: 33u128.into(),
+                },
+                Asset {
+                    info: AssetInfo::NativeToken {
+                        denom: ATOM_DENOM.to_string(),
+                    },
+                    amount: 66u128.into(),
+                },
+            ],
+        }]
+    );
+
+    // bonding does not release the stranded rewards, so a bond right before them gets nothing
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr".into(),
+        amount: Uint128::from(100u128),
+        msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+    });
+    execute(deps.as_mut(), mock_env(), mock_info("staking", &[]), msg).unwrap();
+
+    let res: Vec<StrandedRewardResponse> =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::StrandedRewards {}).unwrap())
+            .unwrap();
+    assert_eq!(
+        res,
+        vec![StrandedRewardResponse {
+            staking_token: Addr::unchecked("staking"),
+            pending_reward: Uint128::from(100u128),
+            assets: vec![
+                Asset {
+                    info: AssetInfo::NativeToken {
+                        denom: ORAI_DENOM.to_string(),
+                    },
+                    amount: 33u128.into(),
+                },
+                Asset {
+                    info: AssetInfo::NativeToken {
+                        denom: ATOM_DENOM.to_string(),
+                    },
+                    amount: 66u128.into(),
+                },
+            ],
+        }]
+    );
+
+    // they go out along with the next reward
+    let msg = ExecuteMsg::DepositReward {
+        rewards: vec![RewardMsg {
+            staking_token: Addr::unchecked("staking"),
+            total_accumulation_amount: Uint128::from(100u128),
+        }],
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("rewarder", &[]), msg).unwrap();
+
+    let res: Vec<StrandedRewardResponse> =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::StrandedRewards {}).unwrap())
+            .unwrap();
+    assert_eq!(res, vec![]);
+    let res: RewardInfoResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::RewardInfo {
+                staker_addr: Addr::unchecked("addr"),
+                staking_token: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    // the stranded ones are paid in the assets they were deposited with, the 1 the split truncated
+    // goes with the next reward
+    assert_eq!(res.reward_infos[0].pending_reward, Uint128::from(101u128));
+    assert_eq!(
+        res.reward_infos[0].pending_withdraw,
+        vec![
+            Asset {
+                info: AssetInfo::NativeToken {
+                    denom: ORAI_DENOM.to_string(),
+                },
+                amount: Uint128::from(32u128),
+            },
+            Asset {
+                info: AssetInfo::NativeToken {
+                    denom: ATOM_DENOM.to_string(),
+                },
+                amount: Uint128::from(65u128),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_stranded_rewards_keep_their_assets() {
+    let mut deps = mock_dependencies_with_balance(&[
+        coin(10000000000u128, ORAI_DENOM),
+        coin(20000000000u128, ATOM_DENOM),
+    ]);
+
+    let msg = InstantiateMsg {
+        owner: Some(Addr::unchecked("owner")),
+        rewarder: Addr::unchecked("rewarder"),
+        withdraw_fee_receiver: Addr::unchecked("withdraw_fee_receiver"),
+        timelock_delay: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr", &[]), msg).unwrap();
+
+    let rewards_per_sec = |orai: u128, atom: u128| ExecuteMsg::UpdateRewardsPerSec {
+        staking_token: Addr::unchecked("staking"),
+        assets: vec![
+            Asset {
+                info: AssetInfo::NativeToken {
+                    denom: ORAI_DENOM.to_string(),
+                },
+                amount: orai.into(),
+            },
+            Asset {
+                info: AssetInfo::NativeToken {
+                    denom: ATOM_DENOM.to_string(),
+                },
+                amount: atom.into(),
+            },
+        ],
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        rewards_per_sec(100, 300),
+    )
+    .unwrap();
+    let msg = ExecuteMsg::RegisterAsset {
+        staking_token: Addr::unchecked("staking"),
+        unbonding_period: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let deposit = ExecuteMsg::DepositReward {
+        rewards: vec![RewardMsg {
+            staking_token: Addr::unchecked("staking"),
+            total_accumulation_amount: Uint128::from(400u128),
+        }],
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("rewarder", &[]),
+        deposit.clone(),
+    )
+    .unwrap();
+
+    // the mix changes before anybody bonds
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        rewards_per_sec(0, 100),
+    )
+    .unwrap();
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr".into(),
+        amount: Uint128::from(100u128),
+        msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+    });
+    execute(deps.as_mut(), mock_env(), mock_info("staking", &[]), msg).unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("rewarder", &[]),
+        deposit,
+    )
+    .unwrap();
+
+    // the stranded rewards are paid in the assets they were deposited with
+    let res: RewardInfoResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::RewardInfo {
+                staker_addr: Addr::unchecked("addr"),
+                staking_token: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.reward_infos[0].pending_reward, Uint128::from(400u128));
+    assert_eq!(
+        res.reward_infos[0].pending_withdraw,
+        vec![
+            Asset {
+                info: AssetInfo::NativeToken {
+                    denom: ATOM_DENOM.to_string(),
+                },
+                amount: Uint128::from(300u128),
+            },
+            Asset {
+                info: AssetInfo::NativeToken {
+                    denom: ORAI_DENOM.to_string(),
+                },
+                amount: Uint128::from(100u128),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_deposit_reward_refund_when_no_bonding() {
+    let mut deps = mock_dependencies_with_balance(&[
+        coin(10000000000u128, ORAI_DENOM),
+        coin(20000000000u128, ATOM_DENOM),
+    ]);
+
+    let msg = InstantiateMsg {
+        owner: Some(Addr::unchecked("owner")),
+        rewarder: Addr::unchecked("rewarder"),
+        withdraw_fee_receiver: Addr::unchecked("withdraw_fee_receiver"),
+        timelock_delay: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::UpdateConfig {
+        rewarder: None,
+        owner: None,
+        withdraw_fee_receiver: None,
+        router: None,
+        empty_pool_policy: Some(EmptyPoolPolicy::Refund),
+        fee_collector: None,
+        factory: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::UpdateRewardsPerSec {
+        staking_token: Addr::unchecked("staking"),
+        assets: vec![
+            Asset {
+                info: AssetInfo::NativeToken {
+                    denom: ORAI_DENOM.to_string(),
+                },
+                amount: 100u128.into(),
+            },
+            Asset {
+                info: AssetInfo::NativeToken {
+                    denom: ATOM_DENOM.to_string(),
+                },
+                amount: 200u128.into(),
+            },
+        ],
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+    let msg = ExecuteMsg::RegisterAsset {
+        staking_token: Addr::unchecked("staking"),
+        unbonding_period: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::DepositReward {
+        rewards: vec![RewardMsg {
+            staking_token: Addr::unchecked("staking"),
+            total_accumulation_amount: Uint128::from(300u128),
+        }],
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("rewarder", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "rewarder".to_string(),
+                amount: vec![coin(99u128, ORAI_DENOM)],
+            })),
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "rewarder".to_string(),
+                amount: vec![coin(199u128, ATOM_DENOM)],
+            })),
+        ]
+    );
+
+    // the 2 truncated by the split are kept for the next reward
+    let res: Vec<StrandedRewardResponse> =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::StrandedRewards {}).unwrap())
+            .unwrap();
+    assert_eq!(
+        res,
+        vec![StrandedRewardResponse {
+            staking_token: Addr::unchecked("staking"),
+            pending_reward: Uint128::from(2u128),
+            assets: vec![],
+        }]
+    );
 }
 
 #[test]
@@ -267,6 +590,7 @@ fn test_before_share_changes() {
         owner: Some(Addr::unchecked("owner")),
         rewarder: Addr::unchecked("rewarder"),
         withdraw_fee_receiver: Addr::unchecked("withdraw_fee_receiver"),
+        timelock_delay: None,
     };
 
     let info = mock_info("addr", &[]);
@@ -332,9 +656,12 @@ fn test_before_share_changes() {
         RewardInfo {
             pending_reward: Uint128::zero(),
             bond_amount: Uint128::from(100u128),
-            index: Decimal::zero(),
+            index: Decimal256::zero(),
             native_token: false,
             pending_withdraw: vec![],
+            epoch: 0,
+            fee_index: Decimal256::zero(),
+            pending_fee_share: Uint128::zero(),
         },
         reward_info
     );
@@ -354,9 +681,12 @@ fn test_before_share_changes() {
         RewardInfo {
             pending_reward: Uint128::from(100u128),
             bond_amount: Uint128::from(200u128),
-            index: Decimal::from_ratio(100u128, 100u128),
+            index: Decimal256::from_ratio(100u128, 100u128),
             native_token: false,
             pending_withdraw: vec![],
+            epoch: 0,
+            fee_index: Decimal256::zero(),
+            pending_fee_share: Uint128::zero(),
         },
         reward_info
     );
@@ -376,6 +706,8 @@ fn test_before_share_changes() {
         staking_token: Addr::unchecked("staking"),
         amount: Uint128::from(100u128),
         unbond_period: None,
+        zap_out: None,
+        staker_addr: None,
     };
     let info = mock_info("addr", &[]);
     let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -386,9 +718,12 @@ fn test_before_share_changes() {
         RewardInfo {
             pending_reward: Uint128::from(200u128),
             bond_amount: Uint128::from(100u128),
-            index: Decimal::from_ratio(150u128, 100u128),
+            index: Decimal256::from_ratio(150u128, 100u128),
             native_token: false,
             pending_withdraw: vec![],
+            epoch: 0,
+            fee_index: Decimal256::zero(),
+            pending_fee_share: Uint128::zero(),
         },
         reward_info
     );
@@ -436,6 +771,7 @@ fn test_withdraw() {
         owner: Some(Addr::unchecked("owner")),
         rewarder: reward_addr.clone(),
         withdraw_fee_receiver: Addr::unchecked("withdraw_fee_receiver"),
+        timelock_delay: None,
     };
 
     let code_id = app.upload(Box::new(create_entry_points_testing!(crate)));
@@ -547,6 +883,10 @@ fn test_withdraw() {
 
     let msg = ExecuteMsg::Withdraw {
         staking_token: Some(Addr::unchecked("staking")),
+        swap_to: None,
+        max_spread: None,
+        belief_prices: vec![],
+        staker_addr: None,
     };
 
     let res = app
@@ -582,6 +922,7 @@ fn test_update_rewards_per_sec() {
         owner: Some(Addr::unchecked("owner")),
         rewarder: Addr::unchecked("rewarder"),
         withdraw_fee_receiver: Addr::unchecked("withdraw_fee_receiver"),
+        timelock_delay: None,
     };
     let staking_token = Addr::unchecked("staking_token");
 
@@ -708,6 +1049,7 @@ fn test_update_rewards_per_sec() {
                         amount: Uint128::from(199u128)
                     }
                 ],
+                pending_fee_share: Uint128::zero(),
             },],
         }
     );
@@ -724,6 +1066,7 @@ fn test_update_rewards_per_sec_with_multiple_bond() {
         owner: Some(Addr::unchecked("owner")),
         rewarder: Addr::unchecked("rewarder"),
         withdraw_fee_receiver: Addr::unchecked("withdraw_fee_receiver"),
+        timelock_delay: None,
     };
 
     let info = mock_info("addr", &[]);
@@ -834,6 +1177,7 @@ fn test_update_rewards_per_sec_with_multiple_bond() {
                 bond_amount: Uint128::from(300u128),
                 pending_reward: Uint128::zero(),
                 pending_withdraw: vec![],
+                pending_fee_share: Uint128::zero(),
             },],
         }
     );
@@ -881,6 +1225,7 @@ fn test_update_rewards_per_sec_with_multiple_bond() {
                         amount: Uint128::from(199u128)
                     }
                 ],
+                pending_fee_share: Uint128::zero(),
             },],
         }
     );
@@ -905,7 +1250,1682 @@ fn test_update_rewards_per_sec_with_multiple_bond() {
                 bond_amount: Uint128::from(300u128),
                 pending_reward: Uint128::from(49u128),
                 pending_withdraw: vec![],
+                pending_fee_share: Uint128::zero(),
             },],
         }
     );
 }
+
+#[test]
+fn test_withdraw_swap_to() {
+    let mut deps = mock_dependencies_with_balance(&[
+        coin(10000000000u128, ORAI_DENOM),
+        coin(20000000000u128, ATOM_DENOM),
+    ]);
+
+    let msg = InstantiateMsg {
+        owner: Some(Addr::unchecked("owner")),
+        rewarder: Addr::unchecked("rewarder"),
+        withdraw_fee_receiver: Addr::unchecked("withdraw_fee_receiver"),
+        timelock_delay: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::UpdateConfig {
+        rewarder: None,
+        owner: None,
+        withdraw_fee_receiver: None,
+        router: Some(Addr::unchecked("router")),
+        empty_pool_policy: None,
+        fee_collector: None,
+        factory: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::UpdateRewardsPerSec {
+        staking_token: Addr::unchecked("staking"),
+        assets: vec![
+            Asset {
+                info: AssetInfo::NativeToken {
+                    denom: ORAI_DENOM.to_string(),
+                },
+                amount: 100u128.into(),
+            },
+            Asset {
+                info: AssetInfo::NativeToken {
+                    denom: ATOM_DENOM.to_string(),
+                },
+                amount: 200u128.into(),
+            },
+        ],
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::RegisterAsset {
+        staking_token: Addr::unchecked("staking"),
+        unbonding_period: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr".into(),
+        amount: Uint128::from(100u128),
+        msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+    });
+    execute(deps.as_mut(), mock_env(), mock_info("staking", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::DepositReward {
+        rewards: vec![RewardMsg {
+            staking_token: Addr::unchecked("staking"),
+            total_accumulation_amount: Uint128::from(300u128),
+        }],
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("rewarder", &[]), msg).unwrap();
+
+    // swapping without a max spread is unprotected
+    let msg = ExecuteMsg::Withdraw {
+        staking_token: None,
+        swap_to: Some(AssetInfo::NativeToken {
+            denom: ORAI_DENOM.to_string(),
+        }),
+        max_spread: None,
+        belief_prices: vec![],
+        staker_addr: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr", &[]), msg).unwrap_err();
+    assert_eq!(
+        res,
+        StdError::generic_err("Max spread is required to swap rewards")
+    );
+
+    let msg = ExecuteMsg::Withdraw {
+        staking_token: None,
+        swap_to: Some(AssetInfo::NativeToken {
+            denom: ORAI_DENOM.to_string(),
+        }),
+        max_spread: Some(Decimal::percent(10)),
+        belief_prices: vec![BeliefPrice {
+            info: AssetInfo::NativeToken {
+                denom: ATOM_DENOM.to_string(),
+            },
+            price: Decimal::from_ratio(5u128, 1u128),
+        }],
+        staker_addr: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "addr".to_string(),
+                amount: vec![coin(99u128, ORAI_DENOM)],
+            })),
+            SubMsg::reply_always(
+                WasmMsg::Execute {
+                    contract_addr: "router".to_string(),
+                    msg: to_binary(&RouterExecuteMsg::ExecuteSwapOperations {
+                        operations: vec![SwapOperation::OraiSwap {
+                            offer_asset_info: AssetInfo::NativeToken {
+                                denom: ATOM_DENOM.to_string(),
+                            },
+                            ask_asset_info: AssetInfo::NativeToken {
+                                denom: ORAI_DENOM.to_string(),
+                            },
+                        }],
+                        minimum_receive: Some(Uint128::from(896u128)),
+                        to: Some(Addr::unchecked("addr")),
+                    })
+                    .unwrap(),
+                    funds: vec![coin(199u128, ATOM_DENOM)],
+                },
+                SWAP_REWARD_REPLY_ID,
+            ),
+        ]
+    );
+
+    // the swap failed, so the atom rewards are refunded as is
+    let res = reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: SWAP_REWARD_REPLY_ID,
+            result: SubMsgResult::Err("minimum receive amount".to_string()),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "addr".to_string(),
+            amount: vec![coin(199u128, ATOM_DENOM)],
+        }))]
+    );
+
+    // reply ids past the swap range are not swap replies
+    let res = reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: SWAP_REWARD_REPLY_ID + MAX_SWAP_REWARDS,
+            result: SubMsgResult::Err("minimum receive amount".to_string()),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(res, StdError::generic_err("invalid reply id"));
+}
+
+#[test]
+fn test_query_pool_apr() {
+    let mut deps = mock_dependencies_with_balance(&[]);
+
+    let msg = InstantiateMsg {
+        owner: Some(Addr::unchecked("owner")),
+        rewarder: Addr::unchecked("rewarder"),
+        withdraw_fee_receiver: Addr::unchecked("withdraw_fee_receiver"),
+        timelock_delay: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr", &[]), msg).unwrap();
+
+    let orai = AssetInfo::NativeToken {
+        denom: ORAI_DENOM.to_string(),
+    };
+    let atom = AssetInfo::NativeToken {
+        denom: ATOM_DENOM.to_string(),
+    };
+    let msg = ExecuteMsg::UpdateRewardsPerSec {
+        staking_token: Addr::unchecked("staking"),
+        assets: vec![
+            Asset {
+                info: orai.clone(),
+                amount: 1u128.into(),
+            },
+            Asset {
+                info: atom.clone(),
+                amount: 2u128.into(),
+            },
+        ],
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::RegisterAsset {
+        staking_token: Addr::unchecked("staking"),
+        unbonding_period: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr".into(),
+        amount: Uint128::from(100000000u128),
+        msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+    });
+    execute(deps.as_mut(), mock_env(), mock_info("staking", &[]), msg).unwrap();
+
+    // the exchange rate query of the oracle
+    #[cw_serde]
+    enum OracleQuery {
+        Exchange(ExchangeQuery),
+    }
+    #[cw_serde]
+    enum ExchangeQuery {
+        ExchangeRate {
+            base_denom: String,
+            quote_denom: String,
+        },
+    }
+
+    // 1 orai = 3 usd and 1 atom = 6 usd, the pool holds 1000 orai and 500 atom for 1000 lp
+    let pair_assets = [orai.clone(), atom.clone()];
+    deps.querier.update_wasm(move |query| match query {
+        WasmQuery::Smart { contract_addr, msg } if contract_addr == "pair" => {
+            let res = match from_binary::<PairQueryMsg>(msg).unwrap() {
+                PairQueryMsg::Pair {} => to_binary(&PairInfo {
+                    oracle_addr: Addr::unchecked("oracle"),
+                    asset_infos: pair_assets.clone(),
+                    contract_addr: Addr::unchecked("pair"),
+                    liquidity_token: Addr::unchecked("staking"),
+                    commission_rate: "0.003".to_string(),
+                }),
+                _ => to_binary(&PoolResponse {
+                    assets: [
+                        Asset {
+                            info: pair_assets[0].clone(),
+                            amount: Uint128::from(1000000000u128),
+                        },
+                        Asset {
+                            info: pair_assets[1].clone(),
+                            amount: Uint128::from(500000000u128),
+                        },
+                    ],
+                    total_share: Uint128::from(1000000000u128),
+                }),
+            };
+            SystemResult::Ok(ContractResult::Ok(res.unwrap()))
+        }
+        WasmQuery::Smart { msg, .. } => {
+            let OracleQuery::Exchange(ExchangeQuery::ExchangeRate {
+                base_denom,
+                quote_denom,
+            }) = from_binary(msg).unwrap();
+            let exchange_rate = match (base_denom.as_str(), quote_denom.as_str()) {
+                (ORAI_DENOM, USD_DENOM) => Decimal::from_ratio(3u128, 1u128),
+                (ATOM_DENOM, USD_DENOM) => Decimal::from_ratio(6u128, 1u128),
+                _ => return SystemResult::Ok(ContractResult::Err("no price".to_string())),
+            };
+            SystemResult::Ok(ContractResult::Ok(
+                to_binary(&ExchangeRateResponse {
+                    base_denom,
+                    item: ExchangeRateItem {
+                        quote_denom,
+                        exchange_rate,
+                    },
+                })
+                .unwrap(),
+            ))
+        }
+        _ => panic!("unexpected query"),
+    });
+
+    let emissions = vec![
+        RewardEmission {
+            info: orai,
+            per_second: Uint128::from(1u128),
+            per_year: Uint128::from(31536000u128),
+            per_bond_unit_per_day: Decimal::from_ratio(86400u128, 100000000u128),
+        },
+        RewardEmission {
+            info: atom,
+            per_second: Uint128::from(2u128),
+            per_year: Uint128::from(63072000u128),
+            per_bond_unit_per_day: Decimal::from_ratio(172800u128, 100000000u128),
+        },
+    ];
+
+    // without the pair there is no price
+    let res: PoolAprResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::PoolApr {
+                staking_token: Addr::unchecked("staking"),
+                pair_addr: None,
+                usd_denom: Some(USD_DENOM.to_string()),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res,
+        PoolAprResponse {
+            staking_token: Addr::unchecked("staking"),
+            total_bond_amount: Uint128::from(100000000u128),
+            emissions: emissions.clone(),
+            usd_apr: None,
+        }
+    );
+
+    // yearly rewards are worth 473040000 usd, the bonded lp 600000000 usd
+    let pool_apr = |deps: Deps, env: Env, usd_denom: &str| -> PoolAprResponse {
+        from_binary(
+            &query(
+                deps,
+                env,
+                QueryMsg::PoolApr {
+                    staking_token: Addr::unchecked("staking"),
+                    pair_addr: Some(Addr::unchecked("pair")),
+                    usd_denom: Some(usd_denom.to_string()),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+    let res = pool_apr(deps.as_ref(), mock_env(), USD_DENOM);
+    assert_eq!(res.emissions, emissions);
+    assert_eq!(
+        res.usd_apr,
+        Some(Decimal::from_ratio(473040000u128, 600000000u128))
+    );
+
+    // without a usd price there is no apr
+    let res = pool_apr(deps.as_ref(), mock_env(), "unknown");
+    assert_eq!(res.usd_apr, None);
+
+    // the running schedules and the allocated reward are emitted along with rewards_per_sec
+    let now = mock_env().block.time.seconds();
+    let msg = ExecuteMsg::AddRewardSchedule {
+        staking_token: Addr::unchecked("staking"),
+        asset: Asset {
+            info: AssetInfo::NativeToken {
+                denom: ATOM_DENOM.to_string(),
+            },
+            amount: Uint128::from(1000u128),
+        },
+        start_time: now,
+        end_time: now + 1000,
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[coin(1000u128, ATOM_DENOM)]),
+        msg,
+    )
+    .unwrap();
+    let msg = ExecuteMsg::UpdateAllocPoints {
+        alloc_points: vec![AllocPoint {
+            staking_token: Addr::unchecked("staking"),
+            alloc_point: 1,
+        }],
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+    let msg = ExecuteMsg::DepositAllocatedReward {
+        amount: Uint128::from(30000u128),
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("rewarder", &[]),
+        msg.clone(),
+    )
+    .unwrap();
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(100);
+    execute(deps.as_mut(), env.clone(), mock_info("rewarder", &[]), msg).unwrap();
+
+    // 1 atom per second from the schedule, 300 allocated per second split as 99 orai and 199 atom
+    let res = pool_apr(deps.as_ref(), env, USD_DENOM);
+    assert_eq!(
+        res.emissions
+            .iter()
+            .map(|emission| emission.per_second)
+            .collect::<Vec<Uint128>>(),
+        vec![Uint128::from(100u128), Uint128::from(202u128)]
+    );
+    assert_eq!(
+        res.usd_apr,
+        Some(Decimal::from_ratio(
+            (100u128 * 3 + 202 * 6) * 31536000,
+            600000000u128
+        ))
+    );
+
+    // a yearly emission past u128 is an error, not a panic
+    let msg = ExecuteMsg::UpdateRewardsPerSec {
+        staking_token: Addr::unchecked("staking"),
+        assets: vec![Asset {
+            info: AssetInfo::NativeToken {
+                denom: ORAI_DENOM.to_string(),
+            },
+            amount: Uint128::MAX,
+        }],
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::PoolApr {
+            staking_token: Addr::unchecked("staking"),
+            pair_addr: None,
+            usd_denom: None,
+        },
+    );
+    assert!(matches!(res, Err(StdError::Overflow { .. })));
+}
+
+#[test]
+fn test_update_rewards_per_sec_lazy_epochs() {
+    let mut deps = mock_dependencies_with_balance(&[
+        coin(10000000000u128, ORAI_DENOM),
+        coin(20000000000u128, ATOM_DENOM),
+    ]);
+
+    let msg = InstantiateMsg {
+        owner: Some(Addr::unchecked("owner")),
+        rewarder: Addr::unchecked("rewarder"),
+        withdraw_fee_receiver: Addr::unchecked("withdraw_fee_receiver"),
+        timelock_delay: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr", &[]), msg).unwrap();
+
+    let rewards_per_sec = |orai: u128, atom: u128| ExecuteMsg::UpdateRewardsPerSec {
+        staking_token: Addr::unchecked("staking"),
+        assets: vec![
+            Asset {
+                info: AssetInfo::NativeToken {
+                    denom: ORAI_DENOM.to_string(),
+                },
+                amount: orai.into(),
+            },
+            Asset {
+                info: AssetInfo::NativeToken {
+                    denom: ATOM_DENOM.to_string(),
+                },
+                amount: atom.into(),
+            },
+        ],
+    };
+    let deposit_reward = ExecuteMsg::DepositReward {
+        rewards: vec![RewardMsg {
+            staking_token: Addr::unchecked("staking"),
+            total_accumulation_amount: Uint128::from(100u128),
+        }],
+    };
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        rewards_per_sec(1, 0),
+    )
+    .unwrap();
+    let msg = ExecuteMsg::RegisterAsset {
+        staking_token: Addr::unchecked("staking"),
+        unbonding_period: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr".into(),
+        amount: Uint128::from(100u128),
+        msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+    });
+    execute(deps.as_mut(), mock_env(), mock_info("staking", &[]), msg).unwrap();
+
+    // orai only, then atom only, then both
+    for (orai, atom) in [(0, 1), (1, 1)] {
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("rewarder", &[]),
+            deposit_reward.clone(),
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            rewards_per_sec(orai, atom),
+        )
+        .unwrap();
+    }
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("rewarder", &[]),
+        deposit_reward,
+    )
+    .unwrap();
+
+    // the rate updates did not touch the staker
+    let asset_key = deps.api.addr_canonicalize("staking").unwrap();
+    let addr_raw = deps.api.addr_canonicalize("addr").unwrap();
+    let reward_info: RewardInfo = rewards_read(&deps.storage, &addr_raw)
+        .load(asset_key.as_slice())
+        .unwrap();
+    assert_eq!(reward_info.epoch, 0);
+    assert_eq!(reward_info.pending_withdraw, vec![]);
+    assert_eq!(read_pool_info(&deps.storage, &asset_key).unwrap().epoch, 2);
+
+    // each finished epoch is split with its own rates
+    let res: RewardInfoResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::RewardInfo {
+                staker_addr: Addr::unchecked("addr"),
+                staking_token: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.reward_infos,
+        vec![RewardInfoResponseItem {
+            staking_token: Addr::unchecked("staking"),
+            bond_amount: Uint128::from(100u128),
+            pending_reward: Uint128::from(100u128),
+            pending_withdraw: vec![
+                Asset {
+                    info: AssetInfo::NativeToken {
+                        denom: ORAI_DENOM.to_string(),
+                    },
+                    amount: Uint128::from(100u128),
+                },
+                Asset {
+                    info: AssetInfo::NativeToken {
+                        denom: ATOM_DENOM.to_string(),
+                    },
+                    amount: Uint128::from(100u128),
+                },
+            ],
+            pending_fee_share: Uint128::zero(),
+        }]
+    );
+
+    let msg = ExecuteMsg::Withdraw {
+        staking_token: None,
+        swap_to: None,
+        max_spread: None,
+        belief_prices: vec![],
+        staker_addr: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "addr".to_string(),
+                amount: vec![coin(150u128, ORAI_DENOM)],
+            })),
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "addr".to_string(),
+                amount: vec![coin(150u128, ATOM_DENOM)],
+            })),
+        ]
+    );
+}
+
+#[test]
+fn test_reward_schedules() {
+    let mut deps = mock_dependencies_with_balance(&[
+        coin(10000000000u128, ORAI_DENOM),
+        coin(20000000000u128, ATOM_DENOM),
+    ]);
+
+    let msg = InstantiateMsg {
+        owner: Some(Addr::unchecked("owner")),
+        rewarder: Addr::unchecked("rewarder"),
+        withdraw_fee_receiver: Addr::unchecked("withdraw_fee_receiver"),
+        timelock_delay: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::RegisterAsset {
+        staking_token: Addr::unchecked("staking"),
+        unbonding_period: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr".into(),
+        amount: Uint128::from(100u128),
+        msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+    });
+    execute(deps.as_mut(), mock_env(), mock_info("staking", &[]), msg).unwrap();
+
+    let now = mock_env().block.time.seconds();
+    let orai_schedule = |start_time: u64, end_time: u64| ExecuteMsg::AddRewardSchedule {
+        staking_token: Addr::unchecked("staking"),
+        asset: Asset {
+            info: AssetInfo::NativeToken {
+                denom: ORAI_DENOM.to_string(),
+            },
+            amount: Uint128::from(1000u128),
+        },
+        start_time,
+        end_time,
+    };
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[coin(1000u128, ORAI_DENOM)]),
+        orai_schedule(now - 1, now + 100),
+    );
+    assert_eq!(
+        res,
+        Err(StdError::generic_err(
+            "Reward schedule must not start in the past"
+        ))
+    );
+
+    // the schedule must be funded when it is queued
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[coin(999u128, ORAI_DENOM)]),
+        orai_schedule(now + 100, now + 200),
+    );
+    assert_eq!(
+        res,
+        Err(StdError::generic_err(
+            "Native token balance mismatch between the argument and the transferred"
+        ))
+    );
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[coin(1000u128, ORAI_DENOM)]),
+        orai_schedule(now + 100, now + 200),
+    )
+    .unwrap();
+
+    // only the owner can fund a cw20 schedule
+    let hook = |sender: &str| {
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: sender.into(),
+            amount: Uint128::from(2000u128),
+            msg: to_binary(&Cw20HookMsg::AddRewardSchedule {
+                staking_token: Addr::unchecked("staking"),
+                start_time: now + 150,
+                end_time: now + 250,
+            })
+            .unwrap(),
+        })
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("reward_token", &[]),
+        hook("addr"),
+    );
+    assert_eq!(res, Err(StdError::generic_err("unauthorized")));
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("reward_token", &[]),
+        hook("owner"),
+    )
+    .unwrap();
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(160);
+    let res: Vec<RewardScheduleResponse> = from_binary(
+        &query(
+            deps.as_ref(),
+            env,
+            QueryMsg::RewardSchedules {
+                staking_token: Addr::unchecked("staking"),
+                start_after: Some(1),
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res,
+        vec![RewardScheduleResponse {
+            id: 2,
+            asset: Asset {
+                info: AssetInfo::Token {
+                    contract_addr: Addr::unchecked("reward_token"),
+                },
+                amount: Uint128::from(2000u128),
+            },
+            start_time: now + 150,
+            end_time: now + 250,
+            status: RewardScheduleStatus::Active,
+        }]
+    );
+
+    // queries emit the schedules until now without waiting for a sync
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(300);
+    let res: RewardInfoResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::RewardInfo {
+                staker_addr: Addr::unchecked("addr"),
+                staking_token: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    // the schedules ended, so their epochs are closed and split already. The epoch both schedules
+    // run in splits 1500 by 10:20 per sec, which truncates 1 of each asset
+    assert_eq!(res.reward_infos[0].pending_reward, Uint128::zero());
+    let pending_withdraw = vec![
+        Asset {
+            info: AssetInfo::NativeToken {
+                denom: ORAI_DENOM.to_string(),
+            },
+            amount: Uint128::from(999u128),
+        },
+        Asset {
+            info: AssetInfo::Token {
+                contract_addr: Addr::unchecked("reward_token"),
+            },
+            amount: Uint128::from(1999u128),
+        },
+    ];
+    assert_eq!(res.reward_infos[0].pending_withdraw, pending_withdraw);
+
+    // both schedules are emitted, each asset at its own rate
+    let msg = ExecuteMsg::WithdrawOthers {
+        staking_token: None,
+        staker_addrs: vec![Addr::unchecked("addr")],
+    };
+    execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), msg).unwrap();
+    let res: RewardInfoResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::RewardInfo {
+                staker_addr: Addr::unchecked("addr"),
+                staking_token: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.reward_infos[0].pending_withdraw, pending_withdraw);
+
+    // the finished schedules no longer count, the running ones are capped
+    let later = now + 300;
+    for _ in 0..MAX_ACTIVE_REWARD_SCHEDULES {
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[coin(1000u128, ORAI_DENOM)]),
+            orai_schedule(later + 100, later + 200),
+        )
+        .unwrap();
+    }
+    let res = execute(
+        deps.as_mut(),
+        env,
+        mock_info("owner", &[coin(1000u128, ORAI_DENOM)]),
+        orai_schedule(later + 100, later + 200),
+    );
+    assert_eq!(
+        res,
+        Err(StdError::generic_err(
+            "Too many reward schedules are running for this pool"
+        ))
+    );
+}
+
+#[test]
+fn test_reward_schedules_keep_rewards_per_sec() {
+    let mut deps = mock_dependencies_with_balance(&[
+        coin(10000000000u128, ORAI_DENOM),
+        coin(20000000000u128, ATOM_DENOM),
+    ]);
+
+    let msg = InstantiateMsg {
+        owner: Some(Addr::unchecked("owner")),
+        rewarder: Addr::unchecked("rewarder"),
+        withdraw_fee_receiver: Addr::unchecked("withdraw_fee_receiver"),
+        timelock_delay: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::RegisterAsset {
+        staking_token: Addr::unchecked("staking"),
+        unbonding_period: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let rewards_per_sec = vec![
+        Asset {
+            info: AssetInfo::NativeToken {
+                denom: ORAI_DENOM.to_string(),
+            },
+            amount: 100u128.into(),
+        },
+        Asset {
+            info: AssetInfo::NativeToken {
+                denom: ATOM_DENOM.to_string(),
+            },
+            amount: 100u128.into(),
+        },
+    ];
+    let msg = ExecuteMsg::UpdateRewardsPerSec {
+        staking_token: Addr::unchecked("staking"),
+        assets: rewards_per_sec.clone(),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr".into(),
+        amount: Uint128::from(100u128),
+        msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+    });
+    execute(deps.as_mut(), mock_env(), mock_info("staking", &[]), msg).unwrap();
+
+    let now = mock_env().block.time.seconds();
+    let msg = ExecuteMsg::AddRewardSchedule {
+        staking_token: Addr::unchecked("staking"),
+        asset: Asset {
+            info: AssetInfo::NativeToken {
+                denom: ORAI_DENOM.to_string(),
+            },
+            amount: Uint128::from(1000u128),
+        },
+        start_time: now + 100,
+        end_time: now + 200,
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[coin(1000u128, ORAI_DENOM)]),
+        msg,
+    )
+    .unwrap();
+
+    // once the schedule ended, the deposits are split by the rates of the owner again
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(300);
+    let msg = ExecuteMsg::DepositReward {
+        rewards: vec![RewardMsg {
+            staking_token: Addr::unchecked("staking"),
+            total_accumulation_amount: Uint128::from(200u128),
+        }],
+    };
+    execute(deps.as_mut(), env.clone(), mock_info("rewarder", &[]), msg).unwrap();
+
+    let res: RewardsPerSecResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::RewardsPerSec {
+                staking_token: Addr::unchecked("staking"),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.assets, rewards_per_sec);
+
+    // the schedule is paid on top of the rates of the owner, its epoch splits 1000 by 110:100
+    let res: RewardInfoResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env,
+            QueryMsg::RewardInfo {
+                staker_addr: Addr::unchecked("addr"),
+                staking_token: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.reward_infos[0].pending_reward, Uint128::from(200u128));
+    assert_eq!(
+        res.reward_infos[0].pending_withdraw,
+        vec![
+            Asset {
+                info: AssetInfo::NativeToken {
+                    denom: ORAI_DENOM.to_string(),
+                },
+                amount: Uint128::from(523u128),
+            },
+            Asset {
+                info: AssetInfo::NativeToken {
+                    denom: ATOM_DENOM.to_string(),
+                },
+                amount: Uint128::from(476u128),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_allocated_reward() {
+    let mut deps = mock_dependencies_with_balance(&[
+        coin(10000000000u128, ORAI_DENOM),
+        coin(20000000000u128, ATOM_DENOM),
+    ]);
+
+    let msg = InstantiateMsg {
+        owner: Some(Addr::unchecked("owner")),
+        rewarder: Addr::unchecked("rewarder"),
+        withdraw_fee_receiver: Addr::unchecked("withdraw_fee_receiver"),
+        timelock_delay: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr", &[]), msg).unwrap();
+
+    for staking_token in ["staking", "staking2"] {
+        let msg = ExecuteMsg::UpdateRewardsPerSec {
+            staking_token: Addr::unchecked(staking_token),
+            assets: vec![Asset {
+                info: AssetInfo::NativeToken {
+                    denom: ORAI_DENOM.to_string(),
+                },
+                amount: 1u128.into(),
+            }],
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+        let msg = ExecuteMsg::RegisterAsset {
+            staking_token: Addr::unchecked(staking_token),
+            unbonding_period: None,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "addr".into(),
+            amount: Uint128::from(100u128),
+            msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+        });
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(staking_token, &[]),
+            msg,
+        )
+        .unwrap();
+    }
+
+    let deposit = ExecuteMsg::DepositAllocatedReward {
+        amount: Uint128::from(600u128),
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("rewarder", &[]),
+        deposit.clone(),
+    );
+    assert_eq!(
+        res,
+        Err(StdError::generic_err("No pool has an alloc point"))
+    );
+
+    let alloc_points = |first: u64, second: u64| ExecuteMsg::UpdateAllocPoints {
+        alloc_points: vec![
+            AllocPoint {
+                staking_token: Addr::unchecked("staking"),
+                alloc_point: first,
+            },
+            AllocPoint {
+                staking_token: Addr::unchecked("staking2"),
+                alloc_point: second,
+            },
+        ],
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr", &[]),
+        alloc_points(1, 3),
+    );
+    assert_eq!(res, Err(StdError::generic_err("unauthorized")));
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        alloc_points(1, 3),
+    )
+    .unwrap();
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr", &[]),
+        deposit.clone(),
+    );
+    assert_eq!(res, Err(StdError::generic_err("unauthorized")));
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("rewarder", &[]),
+        deposit.clone(),
+    )
+    .unwrap();
+
+    // the first deposit is shared 1:3, the second one 3:3
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        alloc_points(3, 3),
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("rewarder", &[]),
+        deposit,
+    )
+    .unwrap();
+
+    let res: AllocPointsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::AllocPoints {}).unwrap()).unwrap();
+    assert_eq!(res.total_alloc_point, 6);
+    assert_eq!(res.alloc_points[0].alloc_point, 3);
+
+    // queries see the deposits not settled by the pools yet
+    let res: RewardInfoResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::RewardInfo {
+                staker_addr: Addr::unchecked("addr"),
+                staking_token: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    let mut pending = res
+        .reward_infos
+        .into_iter()
+        .map(|item| (item.staking_token, item.pending_reward))
+        .collect::<Vec<_>>();
+    pending.sort();
+    assert_eq!(
+        pending,
+        vec![
+            (Addr::unchecked("staking"), Uint128::from(450u128)),
+            (Addr::unchecked("staking2"), Uint128::from(750u128)),
+        ]
+    );
+
+    let msg = ExecuteMsg::WithdrawOthers {
+        staking_token: None,
+        staker_addrs: vec![Addr::unchecked("addr")],
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+    let res: RewardInfoResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::RewardInfo {
+                staker_addr: Addr::unchecked("addr"),
+                staking_token: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    let mut pending = res
+        .reward_infos
+        .into_iter()
+        .map(|item| (item.staking_token, item.pending_withdraw[0].amount))
+        .collect::<Vec<_>>();
+    pending.sort();
+    assert_eq!(
+        pending,
+        vec![
+            (Addr::unchecked("staking"), Uint128::from(450u128)),
+            (Addr::unchecked("staking2"), Uint128::from(750u128)),
+        ]
+    );
+
+    // the part truncated from reward_per_point is carried over to the next deposit
+    let deposit = |amount: u128| ExecuteMsg::DepositAllocatedReward {
+        amount: Uint128::from(amount),
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("rewarder", &[]),
+        deposit(100),
+    )
+    .unwrap();
+    let allocation = REWARD_ALLOCATION.load(deps.as_ref().storage).unwrap();
+    assert_eq!(allocation.reward_dust, Uint128::from(1u128));
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("rewarder", &[]),
+        deposit(5),
+    )
+    .unwrap();
+    let allocation = REWARD_ALLOCATION.load(deps.as_ref().storage).unwrap();
+    assert_eq!(allocation.reward_dust, Uint128::zero());
+}
+
+#[test]
+fn test_gauge_voting() {
+    let mut deps = mock_dependencies_with_balance(&[]);
+
+    let msg = InstantiateMsg {
+        owner: Some(Addr::unchecked("owner")),
+        rewarder: Addr::unchecked("rewarder"),
+        withdraw_fee_receiver: Addr::unchecked("withdraw_fee_receiver"),
+        timelock_delay: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr", &[]), msg).unwrap();
+
+    for staking_token in ["governance", "staking", "staking2"] {
+        let msg = ExecuteMsg::RegisterAsset {
+            staking_token: Addr::unchecked(staking_token),
+            unbonding_period: None,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+    }
+    for (staker, amount) in [("addr", 300u128), ("addr2", 100u128)] {
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: staker.into(),
+            amount: Uint128::from(amount),
+            msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+        });
+        execute(deps.as_mut(), mock_env(), mock_info("governance", &[]), msg).unwrap();
+    }
+
+    // voting power is taken at the next block
+    let mut env = mock_env();
+    env.block.height += 1;
+    let msg = ExecuteMsg::UpdateGaugeConfig {
+        governance_token: Addr::unchecked("governance"),
+        epoch_duration: 100,
+    };
+    execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), msg).unwrap();
+
+    let vote = |staking_token: &str, weight: Decimal| ExecuteMsg::VoteGauge {
+        weights: vec![GaugeWeight {
+            staking_token: Addr::unchecked(staking_token),
+            weight,
+        }],
+    };
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr", &[]),
+        vote("governance", Decimal::one()),
+    );
+    assert_eq!(
+        res,
+        Err(StdError::generic_err("Cannot vote for the governance pool"))
+    );
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr3", &[]),
+        vote("staking", Decimal::one()),
+    );
+    assert_eq!(
+        res,
+        Err(StdError::generic_err(
+            "No governance bond at the start of the epoch"
+        ))
+    );
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr", &[]),
+        vote("staking", Decimal::one()),
+    )
+    .unwrap();
+    // a new vote replaces the previous one
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr2", &[]),
+        vote("staking", Decimal::one()),
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr2", &[]),
+        vote("staking2", Decimal::percent(50)),
+    )
+    .unwrap();
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr", &[]),
+        ExecuteMsg::AdvanceGaugeEpoch {},
+    );
+    assert_eq!(
+        res,
+        Err(StdError::generic_err("Gauge epoch is not finished yet"))
+    );
+
+    env.block.time = env.block.time.plus_seconds(100);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr", &[]),
+        ExecuteMsg::AdvanceGaugeEpoch {},
+    )
+    .unwrap();
+
+    let res: GaugeEpochResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::GaugeEpoch { epoch: Some(1) },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.tallies,
+        vec![
+            GaugeTally {
+                staking_token: Addr::unchecked("staking"),
+                votes: Uint128::from(300u128),
+            },
+            GaugeTally {
+                staking_token: Addr::unchecked("staking2"),
+                votes: Uint128::from(50u128),
+            },
+        ]
+    );
+
+    let res: Option<GaugeVoteResponse> = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::GaugeVote {
+                voter: Addr::unchecked("addr2"),
+                epoch: Some(1),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.unwrap().power, Uint128::from(100u128));
+
+    // the tally of the finished epoch is applied as alloc points
+    let res: AllocPointsResponse =
+        from_binary(&query(deps.as_ref(), env.clone(), QueryMsg::AllocPoints {}).unwrap()).unwrap();
+    let mut alloc_points = res
+        .alloc_points
+        .into_iter()
+        .map(|a| (a.staking_token, a.alloc_point))
+        .collect::<Vec<_>>();
+    alloc_points.sort();
+    assert_eq!(
+        alloc_points,
+        vec![
+            (Addr::unchecked("staking"), 8571),
+            (Addr::unchecked("staking2"), 1428),
+        ]
+    );
+
+    let res: Vec<GaugeEpochResponse> = from_binary(
+        &query(
+            deps.as_ref(),
+            env,
+            QueryMsg::GaugeEpochs {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.len(), 2);
+    assert_eq!(res[1].tallies, vec![]);
+}
+
+#[test]
+fn test_reward_index_dust() {
+    let mut deps = mock_dependencies_with_balance(&[]);
+
+    let msg = InstantiateMsg {
+        owner: Some(Addr::unchecked("owner")),
+        rewarder: Addr::unchecked("rewarder"),
+        withdraw_fee_receiver: Addr::unchecked("withdraw_fee_receiver"),
+        timelock_delay: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::RegisterAsset {
+        staking_token: Addr::unchecked("staking"),
+        unbonding_period: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr".into(),
+        amount: Uint128::from(3u128),
+        msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+    });
+    execute(deps.as_mut(), mock_env(), mock_info("staking", &[]), msg).unwrap();
+
+    let deposit = |amount: u128| ExecuteMsg::DepositReward {
+        rewards: vec![RewardMsg {
+            staking_token: Addr::unchecked("staking"),
+            total_accumulation_amount: Uint128::from(amount),
+        }],
+    };
+    let asset_key = deps.api.addr_canonicalize("staking").unwrap();
+
+    // 10 / 3 is truncated, the index only covers 9.999999999999999999 of it
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("rewarder", &[]),
+        deposit(10),
+    )
+    .unwrap();
+    let pool_info = read_pool_info(&deps.storage, &asset_key).unwrap();
+    assert_eq!(
+        pool_info.reward_index,
+        Decimal256::from_ratio(10u128, 3u128)
+    );
+    assert_eq!(pool_info.reward_dust, Uint128::new(1));
+
+    // the dust is carried over to the next deposit
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("rewarder", &[]),
+        deposit(2),
+    )
+    .unwrap();
+    let pool_info = read_pool_info(&deps.storage, &asset_key).unwrap();
+    assert_eq!(
+        pool_info.reward_index,
+        Decimal256::from_ratio(10u128, 3u128) + Decimal256::one()
+    );
+    assert_eq!(pool_info.reward_dust, Uint128::zero());
+}
+
+#[test]
+fn test_performance_fee() {
+    let mut deps = mock_dependencies_with_balance(&[
+        coin(10000000000u128, ORAI_DENOM),
+        coin(20000000000u128, ATOM_DENOM),
+    ]);
+
+    let msg = InstantiateMsg {
+        owner: Some(Addr::unchecked("owner")),
+        rewarder: Addr::unchecked("rewarder"),
+        withdraw_fee_receiver: Addr::unchecked("withdraw_fee_receiver"),
+        timelock_delay: Some(0),
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::RegisterAsset {
+        staking_token: Addr::unchecked("staking"),
+        unbonding_period: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+    let msg = ExecuteMsg::UpdateRewardsPerSec {
+        staking_token: Addr::unchecked("staking"),
+        assets: vec![
+            Asset {
+                info: AssetInfo::NativeToken {
+                    denom: ORAI_DENOM.to_string(),
+                },
+                amount: 100u128.into(),
+            },
+            Asset {
+                info: AssetInfo::NativeToken {
+                    denom: ATOM_DENOM.to_string(),
+                },
+                amount: 100u128.into(),
+            },
+        ],
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::UpdateConfig {
+        rewarder: None,
+        owner: None,
+        withdraw_fee_receiver: None,
+        router: None,
+        empty_pool_policy: None,
+        fee_collector: Some(Addr::unchecked("collector")),
+        factory: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::UpdatePerformanceFee {
+        staking_token: Addr::unchecked("staking"),
+        fee: Decimal::percent(30),
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap_err();
+    assert_eq!(
+        res,
+        StdError::generic_err("Performance fee must be less than or equal 0.2")
+    );
+
+    let msg = ExecuteMsg::UpdatePerformanceFee {
+        staking_token: Addr::unchecked("staking"),
+        fee: Decimal::percent(10),
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(res, StdError::generic_err("unauthorized"));
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let res: PoolInfoResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::PoolInfo {
+                staking_token: Addr::unchecked("staking"),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.performance_fee, Decimal::percent(10));
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr".into(),
+        amount: Uint128::from(100u128),
+        msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+    });
+    execute(deps.as_mut(), mock_env(), mock_info("staking", &[]), msg).unwrap();
+    let msg = ExecuteMsg::DepositReward {
+        rewards: vec![RewardMsg {
+            staking_token: Addr::unchecked("staking"),
+            total_accumulation_amount: Uint128::from(200u128),
+        }],
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("rewarder", &[]), msg).unwrap();
+
+    // 10% of the 100 orai and 100 atom are kept for the collector
+    let msg = ExecuteMsg::Withdraw {
+        staking_token: None,
+        swap_to: None,
+        max_spread: None,
+        belief_prices: vec![],
+        staker_addr: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr", &[]), msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "withdraw_reward"),
+            attr("performance_fee", format!("10{}", ORAI_DENOM)),
+            attr("performance_fee", format!("10{}", ATOM_DENOM)),
+        ]
+    );
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "collector".to_string(),
+                amount: vec![coin(10u128, ORAI_DENOM)],
+            })),
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "collector".to_string(),
+                amount: vec![coin(10u128, ATOM_DENOM)],
+            })),
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "addr".to_string(),
+                amount: vec![coin(90u128, ORAI_DENOM)],
+            })),
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "addr".to_string(),
+                amount: vec![coin(90u128, ATOM_DENOM)],
+            })),
+        ]
+    );
+
+    let res: Vec<Asset> = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::PerformanceFeeTotals {
+                staking_token: Addr::unchecked("staking"),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res,
+        vec![
+            Asset {
+                info: AssetInfo::NativeToken {
+                    denom: ORAI_DENOM.to_string(),
+                },
+                amount: Uint128::from(10u128),
+            },
+            Asset {
+                info: AssetInfo::NativeToken {
+                    denom: ATOM_DENOM.to_string(),
+                },
+                amount: Uint128::from(10u128),
+            },
+        ]
+    );
+
+    // the rewards released when the position is closed are charged as well
+    let msg = ExecuteMsg::DepositReward {
+        rewards: vec![RewardMsg {
+            staking_token: Addr::unchecked("staking"),
+            total_accumulation_amount: Uint128::from(200u128),
+        }],
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("rewarder", &[]), msg).unwrap();
+    let msg = ExecuteMsg::UpdateRewardsPerSec {
+        staking_token: Addr::unchecked("staking"),
+        assets: vec![Asset {
+            info: AssetInfo::NativeToken {
+                denom: ORAI_DENOM.to_string(),
+            },
+            amount: 100u128.into(),
+        }],
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::Unbond {
+        staking_token: Addr::unchecked("staking"),
+        amount: Uint128::from(100u128),
+        unbond_period: None,
+        zap_out: None,
+        staker_addr: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr", &[]), msg).unwrap();
+    for (recipient, amount, denom) in [
+        ("collector", 10u128, ORAI_DENOM),
+        ("collector", 10u128, ATOM_DENOM),
+        ("addr", 90u128, ORAI_DENOM),
+        ("addr", 90u128, ATOM_DENOM),
+    ] {
+        assert!(res
+            .messages
+            .contains(&SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: recipient.to_string(),
+                amount: vec![coin(amount, denom)],
+            }))));
+    }
+}
+
+#[test]
+fn test_reward_index_at_height() {
+    let mut deps = mock_dependencies_with_balance(&[]);
+
+    let msg = InstantiateMsg {
+        owner: Some(Addr::unchecked("owner")),
+        rewarder: Addr::unchecked("rewarder"),
+        withdraw_fee_receiver: Addr::unchecked("withdraw_fee_receiver"),
+        timelock_delay: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::RegisterAsset {
+        staking_token: Addr::unchecked("staking"),
+        unbonding_period: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr".into(),
+        amount: Uint128::from(100u128),
+        msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+    });
+    execute(deps.as_mut(), mock_env(), mock_info("staking", &[]), msg).unwrap();
+
+    let deposit = |amount: u128| ExecuteMsg::DepositReward {
+        rewards: vec![RewardMsg {
+            staking_token: Addr::unchecked("staking"),
+            total_accumulation_amount: Uint128::from(amount),
+        }],
+    };
+    let start_height = mock_env().block.height;
+    let mut env = mock_env();
+    env.block.height = start_height + 1;
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("rewarder", &[]),
+        deposit(100),
+    )
+    .unwrap();
+    env.block.height = start_height + 3;
+    execute(deps.as_mut(), env, mock_info("rewarder", &[]), deposit(300)).unwrap();
+
+    let reward_index_at = |height: Option<u64>| {
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::RewardIndexAtHeight {
+                staking_token: Addr::unchecked("staking"),
+                height,
+            },
+        )
+        .unwrap();
+        from_binary::<RewardIndexAtHeightResponse>(&res).unwrap()
+    };
+
+    // the index at a height does not include the deposits of that block
+    assert_eq!(
+        reward_index_at(Some(start_height + 1)).reward_index,
+        Decimal256::zero()
+    );
+    assert_eq!(
+        reward_index_at(Some(start_height + 2)).reward_index,
+        Decimal256::one()
+    );
+    assert_eq!(
+        reward_index_at(Some(start_height + 3)).reward_index,
+        Decimal256::one()
+    );
+    assert_eq!(
+        reward_index_at(Some(start_height + 4)).reward_index,
+        Decimal256::from_ratio(4u128, 1u128)
+    );
+
+    // the history starts at the block after the pool was registered
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::RewardIndexAtHeight {
+            staking_token: Addr::unchecked("staking"),
+            height: None,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        res,
+        StdError::generic_err(format!(
+            "Reward index is not recorded at height {}",
+            start_height
+        ))
+    );
+
+    // without a height, the current block is used
+    let mut env = mock_env();
+    env.block.height = start_height + 2;
+    let res = query(
+        deps.as_ref(),
+        env,
+        QueryMsg::RewardIndexAtHeight {
+            staking_token: Addr::unchecked("staking"),
+            height: None,
+        },
+    )
+    .unwrap();
+    let res = from_binary::<RewardIndexAtHeightResponse>(&res).unwrap();
+    assert_eq!(res.height, start_height + 2);
+    assert_eq!(res.reward_index, Decimal256::one());
+}
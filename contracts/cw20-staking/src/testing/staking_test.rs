@@ -1,20 +1,29 @@
-use crate::contract::{execute, instantiate, query, query_get_pools_infomation};
+use crate::contract::{execute, instantiate, query, query_get_pools_infomation, reply};
 use crate::msg::{
-    Cw20HookMsg, ExecuteMsg, InstantiateMsg, LockInfoResponse, LockInfosResponse, PoolInfoResponse,
-    QueryMsg, RewardInfoResponse, RewardInfoResponseItem, RewardMsg, StakedBalanceAtHeightResponse,
-    TotalStakedAtHeightResponse,
+    ClaimedRewardsItem, ClaimedRewardsResponse, Cw20HookMsg, ExecuteMsg, InstantiateMsg,
+    LockInfoResponse, LockInfosResponse, LockStatus, MaturedLocksResponse, OperatorResponse,
+    OperatorScope, PoolInfoResponse, PoolStakersResponse, PoolStatsResponse, PoolStatus,
+    PoolsResponse, PoolsResponseItem, PortfolioItem, QueryMsg, RewardInfoResponse,
+    RewardInfoResponseItem, RewardMsg, SimulateUnbondResponse, StakedBalanceAtHeightResponse,
+    StakerPortfolioResponse, StakerPositionResponse, TotalStakedAtHeightResponse,
+    UnbondFeeTotalsResponse, UnbondOptionResponse, ZapOutMsg,
 };
-use crate::state::{store_pool_info, PoolInfo, MAX_LIMIT};
+use crate::state::{
+    staker_positions, store_pool_info, PoolInfo, MAX_LIMIT, MAX_LOCKS_PER_TX, POOL_STAKER_COUNTS,
+};
+use crate::zap::ZAP_OUT_REPLY_ID;
 use cosmwasm_std::testing::{
     mock_dependencies, mock_dependencies_with_balance, mock_env, mock_info, MockApi, MockQuerier,
-    MockStorage,
+    MockStorage, MOCK_CONTRACT_ADDR,
 };
 use cosmwasm_std::{
-    attr, coin, from_binary, to_binary, Addr, Api, BankMsg, CosmosMsg, Decimal, OwnedDeps,
-    StdError, SubMsg, Uint128, WasmMsg,
+    attr, coin, from_binary, to_binary, Addr, Api, BankMsg, ContractResult, CosmosMsg, Decimal,
+    Decimal256, OwnedDeps, Reply, StdError, SubMsg, SubMsgResponse, SubMsgResult, SystemError,
+    SystemResult, Uint128, WasmMsg, WasmQuery,
 };
-use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
-use oraiswap::asset::{Asset, AssetInfo, ORAI_DENOM};
+use cw20::{BalanceResponse, Cw20ExecuteMsg, Cw20ReceiveMsg, Expiration};
+use oraiswap::asset::{Asset, AssetInfo, PairInfo, ORAI_DENOM};
+use oraiswap::pair::QueryMsg as PairQueryMsg;
 use oraiswap::testing::ATOM_DENOM;
 
 #[test]
@@ -46,7 +55,10 @@ fn test_query_all_pool_keys() {
             staking_token: staking_token.clone(),
             pending_reward: amount.clone(),
             total_bond_amount: amount.clone(),
-            reward_index: Decimal::zero(),
+            reward_index: Decimal256::zero(),
+            reward_dust: Uint128::zero(),
+            epoch: 0,
+            fee_index: Decimal256::zero(),
         };
         store_pool_info(storage, &asset_key, &pool_info).unwrap();
     }
@@ -71,6 +83,7 @@ fn test_bond_tokens() {
         owner: Some(Addr::unchecked("owner")),
         rewarder: Addr::unchecked("rewarder"),
         withdraw_fee_receiver: Addr::unchecked("withdraw_fee_receiver"),
+        timelock_delay: None,
     };
 
     let info = mock_info("addr", &[]);
@@ -111,6 +124,7 @@ fn test_bond_tokens() {
                 pending_reward: Uint128::zero(),
                 pending_withdraw: vec![],
                 bond_amount: Uint128::from(100u128),
+                pending_fee_share: Uint128::zero(),
             }],
         }
     );
@@ -130,9 +144,10 @@ fn test_bond_tokens() {
         PoolInfoResponse {
             staking_token: Addr::unchecked("staking"),
             total_bond_amount: Uint128::from(100u128),
-            reward_index: Decimal::zero(),
+            reward_index: Decimal256::zero(),
             pending_reward: Uint128::zero(),
             unbonding_period: None,
+            performance_fee: Decimal::zero(),
         }
     );
 
@@ -159,13 +174,179 @@ fn test_bond_tokens() {
         PoolInfoResponse {
             staking_token: Addr::unchecked("staking"),
             total_bond_amount: Uint128::from(200u128),
-            reward_index: Decimal::zero(),
+            reward_index: Decimal256::zero(),
             pending_reward: Uint128::zero(),
-            unbonding_period: None
+            unbonding_period: None,
+            performance_fee: Decimal::zero(),
         }
     );
 }
 
+#[test]
+fn test_operator_unbond() {
+    let mut deps = _setup_staking(None);
+
+    // the operator must be a valid address
+    let msg = ExecuteMsg::ApproveOperator {
+        operator: Addr::unchecked("Operator"),
+        expires: None,
+        scopes: vec![OperatorScope::Unbond],
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("addr", &[]), msg).unwrap_err();
+
+    let msg = ExecuteMsg::ApproveOperator {
+        operator: Addr::unchecked("operator"),
+        expires: Some(Expiration::AtHeight(mock_env().block.height + 10)),
+        scopes: vec![OperatorScope::Unbond],
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("addr", &[]), msg).unwrap();
+
+    let res: Vec<OperatorResponse> = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::Operators {
+                staker_addr: Addr::unchecked("addr"),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res,
+        vec![OperatorResponse {
+            operator: Addr::unchecked("operator"),
+            expires: Expiration::AtHeight(mock_env().block.height + 10),
+            scopes: vec![OperatorScope::Unbond],
+        }]
+    );
+
+    // the operator is not allowed to restake
+    let msg = ExecuteMsg::Restake {
+        staking_token: Addr::unchecked("staking"),
+        staker_addr: Some(Addr::unchecked("addr")),
+        limit: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("operator", &[]), msg).unwrap_err();
+    assert_eq!(
+        res,
+        StdError::generic_err("Operator is not allowed to Restake")
+    );
+
+    let msg = ExecuteMsg::Unbond {
+        staking_token: Addr::unchecked("staking"),
+        amount: Uint128::from(100u128),
+        unbond_period: None,
+        zap_out: None,
+        staker_addr: Some(Addr::unchecked("addr")),
+    };
+
+    // not approved
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("other", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(res, StdError::generic_err("unauthorized"));
+
+    // expired
+    let mut expired_env = mock_env();
+    expired_env.block.height += 10;
+    let res = execute(
+        deps.as_mut(),
+        expired_env,
+        mock_info("operator", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(res, StdError::generic_err("Operator approval is expired"));
+
+    // only the staker picks the pair to zap out through
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("operator", &[]),
+        ExecuteMsg::Unbond {
+            staking_token: Addr::unchecked("staking"),
+            amount: Uint128::from(100u128),
+            unbond_period: None,
+            zap_out: Some(ZapOutMsg {
+                pair_addr: Addr::unchecked("pair"),
+                minimum_receive: vec![],
+            }),
+            staker_addr: Some(Addr::unchecked("addr")),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        res,
+        StdError::generic_err("Operator is not allowed to zap out")
+    );
+
+    // and the unbond period
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("operator", &[]),
+        ExecuteMsg::Unbond {
+            staking_token: Addr::unchecked("staking"),
+            amount: Uint128::from(100u128),
+            unbond_period: Some(10),
+            zap_out: None,
+            staker_addr: Some(Addr::unchecked("addr")),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        res,
+        StdError::generic_err("Operator is not allowed to choose the unbond period")
+    );
+
+    // the lp and the rewards are sent to the staker, not the operator
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("operator", &[]),
+        msg.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages.last(),
+        Some(&SubMsg::new(WasmMsg::Execute {
+            contract_addr: "staking".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "addr".to_string(),
+                amount: Uint128::from(100u128),
+            })
+            .unwrap(),
+            funds: vec![],
+        }))
+    );
+
+    let msg = ExecuteMsg::RevokeOperator {
+        operator: Addr::unchecked("operator"),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("addr", &[]), msg).unwrap();
+    let res: Vec<OperatorResponse> = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::Operators {
+                staker_addr: Addr::unchecked("addr"),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res, vec![]);
+}
+
 #[test]
 fn test_unbond() {
     let mut deps = _setup_staking(None);
@@ -175,6 +356,8 @@ fn test_unbond() {
         staking_token: Addr::unchecked("staking"),
         amount: Uint128::from(150u128),
         unbond_period: None,
+        zap_out: None,
+        staker_addr: None,
     };
 
     let info = mock_info("addr", &[]);
@@ -191,6 +374,8 @@ fn test_unbond() {
         staking_token: Addr::unchecked("staking"),
         amount: Uint128::from(100u128),
         unbond_period: None,
+        zap_out: None,
+        staker_addr: None,
     };
 
     let info = mock_info("addr", &[]);
@@ -232,9 +417,10 @@ fn test_unbond() {
         PoolInfoResponse {
             staking_token: Addr::unchecked("staking"),
             total_bond_amount: Uint128::zero(),
-            reward_index: Decimal::from_ratio(300u128, 100u128),
+            reward_index: Decimal256::from_ratio(300u128, 100u128),
             pending_reward: Uint128::zero(),
-            unbonding_period: None
+            unbonding_period: None,
+            performance_fee: Decimal::zero(),
         }
     );
 
@@ -257,6 +443,7 @@ fn test_unbond() {
                 bond_amount: Uint128::from(0u128),
                 pending_reward: Uint128::from(0u128),
                 pending_withdraw: vec![],
+                pending_fee_share: Uint128::zero(),
             }],
         }
     );
@@ -271,6 +458,8 @@ fn test_unbonding_period_happy_case() {
         staking_token: Addr::unchecked("staking"),
         amount: Uint128::from(50u128),
         unbond_period: None,
+        zap_out: None,
+        staker_addr: None,
     };
     let info = mock_info("addr", &[]);
     let mut unbond_env = mock_env();
@@ -332,6 +521,8 @@ fn test_unbonding_period_happy_case() {
         staking_token: Addr::unchecked("staking"),
         amount: Uint128::from(50u128),
         unbond_period: None,
+        zap_out: None,
+        staker_addr: None,
     };
     let mut _res = execute(deps.as_mut(), unbond_env.clone(), info.clone(), msg).unwrap();
     _res.attributes.sort_by(|a, b| a.key.cmp(&b.key));
@@ -403,6 +594,8 @@ fn test_unbonding_period_happy_case() {
         staking_token: Addr::unchecked("staking"),
         amount: Uint128::from(0u128),
         unbond_period: None,
+        zap_out: None,
+        staker_addr: None,
     };
     let _res = execute(deps.as_mut(), unbond_env.clone(), info, msg).unwrap();
 
@@ -457,6 +650,8 @@ pub fn test_multiple_lock() {
             staking_token: Addr::unchecked("staking"),
             amount: Uint128::from(1u128),
             unbond_period: None,
+            zap_out: None,
+            staker_addr: None,
         };
         let mut clone_unbonded = unbond_env.clone();
         clone_unbonded.block.time = clone_unbonded
@@ -490,6 +685,8 @@ pub fn test_multiple_lock() {
         staking_token: Addr::unchecked("staking"),
         amount: Uint128::from(0u128),
         unbond_period: None,
+        zap_out: None,
+        staker_addr: None,
     };
 
     let res = execute(deps.as_mut(), unbond_env.clone(), info.clone(), msg).unwrap();
@@ -617,6 +814,8 @@ fn test_balance_and_bonded_snapshot() {
         staking_token: Addr::unchecked("staking"),
         amount: Uint128::from(100u128),
         unbond_period: None,
+        zap_out: None,
+        staker_addr: None,
     };
 
     let mut skip_200_blocks_env = mock_env.clone();
@@ -670,6 +869,8 @@ fn test_restake() {
             staking_token: Addr::unchecked("staking"),
             amount: Uint128::from(1u128),
             unbond_period: None,
+            zap_out: None,
+            staker_addr: None,
         };
         let mut clone_unbonded = unbond_env.clone();
         clone_unbonded.block.time = clone_unbonded
@@ -708,6 +909,8 @@ fn test_restake() {
     // Act
     let msg = ExecuteMsg::Restake {
         staking_token: Addr::unchecked("staking"),
+        staker_addr: None,
+        limit: None,
     };
     let _res = execute(deps.as_mut(), unbond_env.clone(), info, msg).unwrap();
 
@@ -732,6 +935,7 @@ fn _setup_staking(unbonding_period: Option<u64>) -> OwnedDeps<MockStorage, MockA
         owner: Some(Addr::unchecked("owner")),
         rewarder: Addr::unchecked("rewarder"),
         withdraw_fee_receiver: Addr::unchecked("withdraw_fee_receiver"),
+        timelock_delay: Some(0),
     };
 
     let info = mock_info("addr", &[]);
@@ -831,6 +1035,8 @@ fn test_unbond_with_fee() {
         staking_token: Addr::unchecked("staking"),
         amount: Uint128::from(50u128),
         unbond_period: None,
+        zap_out: None,
+        staker_addr: None,
     };
     let info = mock_info("addr", &[]);
     let mut unbond_env = mock_env();
@@ -883,7 +1089,9 @@ fn test_unbond_with_fee() {
                 .block
                 .time
                 .plus_seconds(unbonding_period)
-                .seconds()
+                .seconds(),
+            zap_out: None,
+            status: LockStatus::Locked,
         }]
     );
 
@@ -892,6 +1100,8 @@ fn test_unbond_with_fee() {
         staking_token: Addr::unchecked("staking"),
         amount: Uint128::from(50u128),
         unbond_period: Some(20),
+        zap_out: None,
+        staker_addr: None,
     };
     let res = execute(
         deps.as_mut(),
@@ -926,6 +1136,8 @@ fn test_unbond_with_fee() {
         staking_token: Addr::unchecked("staking"),
         amount: Uint128::from(10u128),
         unbond_period: Some(20),
+        zap_out: None,
+        staker_addr: None,
     };
     let _res = execute(
         deps.as_mut(),
@@ -957,7 +1169,9 @@ fn test_unbond_with_fee() {
         vec![
             LockInfoResponse {
                 amount: Uint128::from(9u128),
-                unlock_time: unbond_env.clone().block.time.plus_seconds(20).seconds()
+                unlock_time: unbond_env.clone().block.time.plus_seconds(20).seconds(),
+                zap_out: None,
+                status: LockStatus::Locked,
             },
             LockInfoResponse {
                 amount: Uint128::from(50u128),
@@ -966,7 +1180,9 @@ fn test_unbond_with_fee() {
                     .block
                     .time
                     .plus_seconds(unbonding_period)
-                    .seconds()
+                    .seconds(),
+                zap_out: None,
+                status: LockStatus::Locked,
             }
         ]
     );
@@ -976,6 +1192,8 @@ fn test_unbond_with_fee() {
         staking_token: Addr::unchecked("staking"),
         amount: Uint128::from(0u128),
         unbond_period: None,
+        zap_out: None,
+        staker_addr: None,
     };
     let _res = execute(deps.as_mut(), unbond_env.clone(), info, msg).unwrap();
 
@@ -1017,3 +1235,1203 @@ fn test_unbond_with_fee() {
         }),]
     )
 }
+
+// the contract holds asset_balance of the cw20 pair asset
+fn _set_pair_querier(deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>, asset_balance: u128) {
+    let pair_info = PairInfo {
+        oracle_addr: Addr::unchecked("oracle"),
+        asset_infos: [
+            AssetInfo::NativeToken {
+                denom: ORAI_DENOM.to_string(),
+            },
+            AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset"),
+            },
+        ],
+        contract_addr: Addr::unchecked("pair"),
+        liquidity_token: Addr::unchecked("staking"),
+        commission_rate: "0.003".to_string(),
+    };
+    let msg = ExecuteMsg::UpdateConfig {
+        rewarder: None,
+        owner: None,
+        withdraw_fee_receiver: None,
+        router: None,
+        empty_pool_policy: None,
+        fee_collector: None,
+        factory: Some(Addr::unchecked("factory")),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    deps.querier.update_wasm(move |query| match query {
+        // the factory lists the pair for its assets
+        WasmQuery::Smart { contract_addr, .. } if contract_addr == "factory" => {
+            SystemResult::Ok(ContractResult::Ok(to_binary(&pair_info).unwrap()))
+        }
+        WasmQuery::Smart { contract_addr, .. } if contract_addr == "asset" => {
+            SystemResult::Ok(ContractResult::Ok(
+                to_binary(&BalanceResponse {
+                    balance: Uint128::from(asset_balance),
+                })
+                .unwrap(),
+            ))
+        }
+        WasmQuery::Smart { msg, .. } => match from_binary::<PairQueryMsg>(msg).unwrap() {
+            PairQueryMsg::Pair {} => {
+                SystemResult::Ok(ContractResult::Ok(to_binary(&pair_info).unwrap()))
+            }
+            _ => SystemResult::Err(SystemError::UnsupportedRequest {
+                kind: "pair".to_string(),
+            }),
+        },
+        _ => SystemResult::Err(SystemError::UnsupportedRequest {
+            kind: "wasm".to_string(),
+        }),
+    });
+}
+
+#[test]
+fn test_simulate_unbond() {
+    let mut deps = _setup_staking(Some(100));
+
+    let msg = ExecuteMsg::UpdateUnbondOption {
+        staking_token: Addr::unchecked("staking"),
+        period: 20,
+        fee: Decimal::from_ratio(1u128, 10u128),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let simulate = |deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>,
+                    amount: u128,
+                    unbond_period: Option<u64>| {
+        query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::SimulateUnbond {
+                staker_addr: Addr::unchecked("addr"),
+                staking_token: Addr::unchecked("staking"),
+                amount: Uint128::from(amount),
+                unbond_period,
+            },
+        )
+        .and_then(|res| from_binary::<SimulateUnbondResponse>(&res))
+    };
+
+    let res = simulate(&deps, 150, None).unwrap();
+    assert!(res.exceed_bond_amount);
+    assert_eq!(res.reward_assets, vec![]);
+
+    let res = simulate(&deps, 100, Some(30)).unwrap_err();
+    assert_eq!(
+        res,
+        StdError::generic_err("This unbond options doesn't exist")
+    );
+
+    // partial unbond with the default period keeps the pending rewards
+    let res = simulate(&deps, 50, None).unwrap();
+    assert_eq!(
+        res,
+        SimulateUnbondResponse {
+            fee_amount: Uint128::zero(),
+            amount_after_fee: Uint128::from(50u128),
+            unlock_time: Some(mock_env().block.time.plus_seconds(100)),
+            reward_assets: vec![],
+            exceed_bond_amount: false,
+        }
+    );
+
+    // closing the position pays out pending_withdraw
+    let expected = SimulateUnbondResponse {
+        fee_amount: Uint128::from(10u128),
+        amount_after_fee: Uint128::from(90u128),
+        unlock_time: Some(mock_env().block.time.plus_seconds(20)),
+        reward_assets: vec![
+            Asset {
+                info: AssetInfo::NativeToken {
+                    denom: ORAI_DENOM.to_string(),
+                },
+                amount: Uint128::from(99u128),
+            },
+            Asset {
+                info: AssetInfo::NativeToken {
+                    denom: ATOM_DENOM.to_string(),
+                },
+                amount: Uint128::from(199u128),
+            },
+        ],
+        exceed_bond_amount: false,
+    };
+    assert_eq!(simulate(&deps, 100, Some(20)).unwrap(), expected);
+
+    // nothing was written, the simulation matches the real unbond
+    assert_eq!(simulate(&deps, 100, Some(20)).unwrap(), expected);
+    let msg = ExecuteMsg::Unbond {
+        staking_token: Addr::unchecked("staking"),
+        amount: Uint128::from(100u128),
+        unbond_period: Some(20),
+        zap_out: None,
+        staker_addr: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(WasmMsg::Execute {
+                contract_addr: "staking".to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: "withdraw_fee_receiver".to_string(),
+                    amount: Uint128::from(10u128),
+                })
+                .unwrap(),
+                funds: vec![],
+            }),
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "addr".to_string(),
+                amount: vec![coin(99u128, ORAI_DENOM)],
+            })),
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "addr".to_string(),
+                amount: vec![coin(199u128, ATOM_DENOM)],
+            })),
+        ]
+    );
+}
+
+#[test]
+fn test_staker_portfolio() {
+    let mut deps = _setup_staking(Some(100));
+
+    let mut env = mock_env();
+    for amount in [30u128, 20u128] {
+        let msg = ExecuteMsg::Unbond {
+            staking_token: Addr::unchecked("staking"),
+            amount: Uint128::from(amount),
+            unbond_period: None,
+            zap_out: None,
+            staker_addr: None,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("addr", &[]), msg).unwrap();
+        env.block.time = env.block.time.plus_seconds(50);
+    }
+
+    // the first lock is unlocked, the second one is not
+    env.block.time = mock_env().block.time.plus_seconds(120);
+    env.block.height += 1;
+    let res: StakerPortfolioResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env,
+            QueryMsg::StakerPortfolio {
+                staker_addr: Addr::unchecked("addr"),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res,
+        StakerPortfolioResponse {
+            staker_addr: Addr::unchecked("addr"),
+            pools: vec![PortfolioItem {
+                staking_token: Addr::unchecked("staking"),
+                bond_amount: Uint128::from(50u128),
+                pending_rewards: vec![
+                    Asset {
+                        info: AssetInfo::NativeToken {
+                            denom: ORAI_DENOM.to_string(),
+                        },
+                        amount: Uint128::from(99u128),
+                    },
+                    Asset {
+                        info: AssetInfo::NativeToken {
+                            denom: ATOM_DENOM.to_string(),
+                        },
+                        amount: Uint128::from(199u128),
+                    },
+                ],
+                claimable_locks: vec![LockInfoResponse {
+                    amount: Uint128::from(30u128),
+                    unlock_time: mock_env().block.time.plus_seconds(100).seconds(),
+                    zap_out: None,
+                    status: LockStatus::Claimable,
+                }],
+                locked: vec![LockInfoResponse {
+                    amount: Uint128::from(20u128),
+                    unlock_time: mock_env().block.time.plus_seconds(150).seconds(),
+                    zap_out: None,
+                    status: LockStatus::Locked,
+                }],
+                staked_balance: Uint128::from(50u128),
+            }],
+        }
+    );
+
+    // no position at all
+    let res: StakerPortfolioResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::StakerPortfolio {
+                staker_addr: Addr::unchecked("addr2"),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.pools, vec![]);
+}
+
+#[test]
+fn test_claim_unbonded() {
+    let mut deps = _setup_staking(Some(100));
+
+    let mut env = mock_env();
+    for amount in [30u128, 20u128] {
+        let msg = ExecuteMsg::Unbond {
+            staking_token: Addr::unchecked("staking"),
+            amount: Uint128::from(amount),
+            unbond_period: None,
+            zap_out: None,
+            staker_addr: None,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("addr", &[]), msg).unwrap();
+        env.block.time = env.block.time.plus_seconds(50);
+    }
+
+    let msg = ExecuteMsg::ClaimUnbonded {
+        staking_token: Addr::unchecked("staking"),
+        staker_addr: None,
+        limit: None,
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(res, StdError::generic_err("No unlocked lp to claim"));
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(120);
+    let query_msg = QueryMsg::LockInfos {
+        staker_addr: Addr::unchecked("addr"),
+        staking_token: Addr::unchecked("staking"),
+        start_after: None,
+        limit: Some(1),
+        order: None,
+    };
+    let res: LockInfosResponse =
+        from_binary(&query(deps.as_ref(), env.clone(), query_msg.clone()).unwrap()).unwrap();
+    assert_eq!(
+        res,
+        LockInfosResponse {
+            staker_addr: Addr::unchecked("addr"),
+            staking_token: Addr::unchecked("staking"),
+            lock_infos: vec![LockInfoResponse {
+                amount: Uint128::from(30u128),
+                unlock_time: mock_env().block.time.plus_seconds(100).seconds(),
+                zap_out: None,
+                status: LockStatus::Claimable,
+            }],
+            claimable_amount: Uint128::from(30u128),
+            locked_amount: Uint128::from(20u128),
+            next_unlock_time: Some(mock_env().block.time.plus_seconds(150).seconds()),
+        }
+    );
+
+    let res = execute(deps.as_mut(), env.clone(), mock_info("addr", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(WasmMsg::Execute {
+            contract_addr: "staking".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "addr".to_string(),
+                amount: Uint128::from(30u128),
+            })
+            .unwrap(),
+            funds: vec![],
+        })]
+    );
+
+    let res: LockInfosResponse =
+        from_binary(&query(deps.as_ref(), env, query_msg).unwrap()).unwrap();
+    assert_eq!(res.lock_infos[0].status, LockStatus::Locked);
+    assert_eq!(res.claimable_amount, Uint128::zero());
+    assert_eq!(res.locked_amount, Uint128::from(20u128));
+}
+
+#[test]
+fn test_lock_processing_limit() {
+    let mut deps = _setup_staking(Some(100));
+
+    for i in 0..45u64 {
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(i);
+        let msg = ExecuteMsg::Unbond {
+            staking_token: Addr::unchecked("staking"),
+            amount: Uint128::from(1u128),
+            unbond_period: None,
+            zap_out: None,
+            staker_addr: None,
+        };
+        execute(deps.as_mut(), env, mock_info("addr", &[]), msg).unwrap();
+    }
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(200);
+    let matured_locks = |deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>| {
+        from_binary::<MaturedLocksResponse>(
+            &query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::MaturedLocks {
+                    staker_addr: Addr::unchecked("addr"),
+                    staking_token: Addr::unchecked("staking"),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+    assert_eq!(
+        matured_locks(&deps),
+        MaturedLocksResponse {
+            count: 45,
+            amount: Uint128::from(45u128),
+            max_per_tx: MAX_LOCKS_PER_TX,
+        }
+    );
+
+    let claim = |deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>, limit: Option<u32>| {
+        let msg = ExecuteMsg::ClaimUnbonded {
+            staking_token: Addr::unchecked("staking"),
+            staker_addr: None,
+            limit,
+        };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("addr", &[]), msg).unwrap();
+        res.attributes
+            .into_iter()
+            .find(|attr| attr.key == "amount")
+            .unwrap()
+            .value
+    };
+
+    assert_eq!(claim(&mut deps, Some(10)), "10");
+    assert_eq!(matured_locks(&deps).count, 35);
+
+    // larger limits are capped
+    assert_eq!(claim(&mut deps, Some(100)), MAX_LOCKS_PER_TX.to_string());
+    assert_eq!(matured_locks(&deps).count, 5);
+
+    assert_eq!(claim(&mut deps, None), "5");
+    assert_eq!(matured_locks(&deps).count, 0);
+}
+
+#[test]
+fn test_unbond_zap_out() {
+    let unbonding_period = 100;
+    let mut deps = _setup_staking(Some(unbonding_period));
+    // the contract holds none of the pair assets
+    _set_pair_querier(&mut deps, 0);
+    deps.querier.update_balance(MOCK_CONTRACT_ADDR, vec![]);
+    let info = mock_info("addr", &[]);
+    let mut unbond_env = mock_env();
+
+    // 50 lp is worth 50 orai and 100 asset
+    let zap_out = ZapOutMsg {
+        pair_addr: Addr::unchecked("pair"),
+        minimum_receive: vec![
+            Asset {
+                info: AssetInfo::NativeToken {
+                    denom: ORAI_DENOM.to_string(),
+                },
+                amount: Uint128::from(50u128),
+            },
+            Asset {
+                info: AssetInfo::Token {
+                    contract_addr: Addr::unchecked("asset"),
+                },
+                amount: Uint128::from(100u128),
+            },
+        ],
+    };
+    let msg = ExecuteMsg::Unbond {
+        staking_token: Addr::unchecked("staking"),
+        amount: Uint128::from(50u128),
+        unbond_period: None,
+        zap_out: Some(zap_out.clone()),
+        staker_addr: None,
+    };
+    execute(deps.as_mut(), unbond_env.clone(), info.clone(), msg).unwrap();
+
+    // another unbond in the same block goes to the same lock
+    let msg = ExecuteMsg::Unbond {
+        staking_token: Addr::unchecked("staking"),
+        amount: Uint128::from(10u128),
+        unbond_period: None,
+        zap_out: None,
+        staker_addr: None,
+    };
+    let res = execute(deps.as_mut(), unbond_env.clone(), info.clone(), msg).unwrap_err();
+    assert_eq!(
+        res,
+        StdError::generic_err("A lock unlocking at the same time has another zap out preference")
+    );
+
+    let lock_ids = from_binary::<LockInfosResponse>(
+        &query(
+            deps.as_ref(),
+            unbond_env.clone(),
+            QueryMsg::LockInfos {
+                staker_addr: Addr::unchecked("addr"),
+                staking_token: Addr::unchecked("staking"),
+                start_after: None,
+                limit: None,
+                order: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(lock_ids.lock_infos[0].zap_out, Some(zap_out));
+
+    // release the lock, the lp goes to the pair and the withdrawn assets to the staker in the reply
+    unbond_env.block.time = unbond_env.block.time.plus_seconds(unbonding_period);
+    let msg = ExecuteMsg::Unbond {
+        staking_token: Addr::unchecked("staking"),
+        amount: Uint128::zero(),
+        unbond_period: None,
+        zap_out: None,
+        staker_addr: None,
+    };
+    let res = execute(deps.as_mut(), unbond_env.clone(), info.clone(), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::reply_on_success(
+            WasmMsg::Execute {
+                contract_addr: "staking".to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Send {
+                    contract: "pair".to_string(),
+                    amount: Uint128::from(50u128),
+                    msg: to_binary(&oraiswap::pair::Cw20HookMsg::WithdrawLiquidity {}).unwrap(),
+                })
+                .unwrap(),
+                funds: vec![],
+            },
+            ZAP_OUT_REPLY_ID,
+        )]
+    );
+
+    deps.querier
+        .update_balance(MOCK_CONTRACT_ADDR, vec![coin(50u128, ORAI_DENOM)]);
+    _set_pair_querier(&mut deps, 100);
+    let res = reply(deps.as_mut(), unbond_env.clone(), _zap_out_reply()).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "addr".to_string(),
+                amount: vec![coin(50u128, ORAI_DENOM)],
+            })),
+            SubMsg::new(WasmMsg::Execute {
+                contract_addr: "asset".to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: "addr".to_string(),
+                    amount: Uint128::from(100u128),
+                })
+                .unwrap(),
+                funds: vec![],
+            }),
+        ]
+    );
+
+    // the zap out goes before the other transfers of the transaction
+    let msg = ExecuteMsg::Unbond {
+        staking_token: Addr::unchecked("staking"),
+        amount: Uint128::from(20u128),
+        unbond_period: None,
+        zap_out: Some(ZapOutMsg {
+            pair_addr: Addr::unchecked("pair"),
+            minimum_receive: vec![Asset {
+                info: AssetInfo::NativeToken {
+                    denom: ORAI_DENOM.to_string(),
+                },
+                amount: Uint128::from(30u128),
+            }],
+        }),
+        staker_addr: None,
+    };
+    execute(deps.as_mut(), unbond_env.clone(), info.clone(), msg).unwrap();
+    let msg = ExecuteMsg::Unbond {
+        staking_token: Addr::unchecked("staking"),
+        amount: Uint128::from(10u128),
+        unbond_period: None,
+        zap_out: None,
+        staker_addr: None,
+    };
+    unbond_env.block.time = unbond_env.block.time.plus_seconds(1);
+    execute(deps.as_mut(), unbond_env.clone(), info.clone(), msg).unwrap();
+
+    unbond_env.block.time = unbond_env.block.time.plus_seconds(unbonding_period);
+    let msg = ExecuteMsg::Unbond {
+        staking_token: Addr::unchecked("staking"),
+        amount: Uint128::zero(),
+        unbond_period: None,
+        zap_out: None,
+        staker_addr: None,
+    };
+    let res = execute(deps.as_mut(), unbond_env.clone(), info, msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::reply_on_success(
+                WasmMsg::Execute {
+                    contract_addr: "staking".to_string(),
+                    msg: to_binary(&Cw20ExecuteMsg::Send {
+                        contract: "pair".to_string(),
+                        amount: Uint128::from(20u128),
+                        msg: to_binary(&oraiswap::pair::Cw20HookMsg::WithdrawLiquidity {}).unwrap(),
+                    })
+                    .unwrap(),
+                    funds: vec![],
+                },
+                ZAP_OUT_REPLY_ID,
+            ),
+            SubMsg::new(WasmMsg::Execute {
+                contract_addr: "staking".to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: "addr".to_string(),
+                    amount: Uint128::from(10u128),
+                })
+                .unwrap(),
+                funds: vec![],
+            }),
+        ]
+    );
+
+    // the pair paid less than the minimum, the whole unbond fails
+    deps.querier
+        .update_balance(MOCK_CONTRACT_ADDR, vec![coin(70u128, ORAI_DENOM)]);
+    _set_pair_querier(&mut deps, 140);
+    let res = reply(deps.as_mut(), unbond_env, _zap_out_reply()).unwrap_err();
+    assert_eq!(
+        res,
+        StdError::generic_err("Withdrawn amount 20 is less than minimum receive amount 30")
+    );
+}
+
+fn _zap_out_reply() -> Reply {
+    Reply {
+        id: ZAP_OUT_REPLY_ID,
+        result: SubMsgResult::Ok(SubMsgResponse {
+            events: vec![],
+            data: None,
+        }),
+    }
+}
+
+#[test]
+fn test_unbond_fee_split() {
+    let mut deps = _setup_staking(Some(100));
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr2".to_string(),
+        amount: Uint128::from(100u128),
+        msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+    });
+    execute(deps.as_mut(), mock_env(), mock_info("staking", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::UpdateUnbondOption {
+        staking_token: Addr::unchecked("staking"),
+        period: 20,
+        fee: Decimal::from_ratio(1u128, 10u128),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::UpdateUnbondFeeSplit {
+        treasury: Decimal::percent(50),
+        stakers: Decimal::percent(30),
+        burn: Decimal::percent(30),
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap_err();
+    assert_eq!(
+        res,
+        StdError::generic_err("Unbond fee split must sum up to 1")
+    );
+    let msg = ExecuteMsg::UpdateUnbondFeeSplit {
+        treasury: Decimal::percent(50),
+        stakers: Decimal::percent(30),
+        burn: Decimal::percent(20),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    // fee 10: 5 to the treasury, 3 to addr2 and 2 burned
+    let msg = ExecuteMsg::Unbond {
+        staking_token: Addr::unchecked("staking"),
+        amount: Uint128::from(100u128),
+        unbond_period: Some(20),
+        zap_out: None,
+        staker_addr: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr", &[]), msg).unwrap();
+    assert!(res.messages.contains(&SubMsg::new(WasmMsg::Execute {
+        contract_addr: "staking".to_string(),
+        msg: to_binary(&Cw20ExecuteMsg::Transfer {
+            recipient: "withdraw_fee_receiver".to_string(),
+            amount: Uint128::from(5u128),
+        })
+        .unwrap(),
+        funds: vec![],
+    })));
+    assert!(res.messages.contains(&SubMsg::new(WasmMsg::Execute {
+        contract_addr: "staking".to_string(),
+        msg: to_binary(&Cw20ExecuteMsg::Burn {
+            amount: Uint128::from(2u128),
+        })
+        .unwrap(),
+        funds: vec![],
+    })));
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::UnbondFeeTotals {
+            staking_token: Addr::unchecked("staking"),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        from_binary::<UnbondFeeTotalsResponse>(&res).unwrap(),
+        UnbondFeeTotalsResponse {
+            staking_token: Addr::unchecked("staking"),
+            treasury: Uint128::from(5u128),
+            stakers: Uint128::from(3u128),
+            burned: Uint128::from(2u128),
+        }
+    );
+
+    // the stakers share is kept apart from the rewards
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::RewardInfo {
+            staker_addr: Addr::unchecked("addr2"),
+            staking_token: Some(Addr::unchecked("staking")),
+        },
+    )
+    .unwrap();
+    let reward_info = &from_binary::<RewardInfoResponse>(&res)
+        .unwrap()
+        .reward_infos[0];
+    assert_eq!(reward_info.pending_withdraw, vec![]);
+    assert_eq!(reward_info.pending_fee_share, Uint128::from(3u128));
+
+    // and paid in the staking token on withdraw, outside of the claimed rewards
+    let msg = ExecuteMsg::Withdraw {
+        staking_token: Some(Addr::unchecked("staking")),
+        swap_to: None,
+        max_spread: None,
+        belief_prices: vec![],
+        staker_addr: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr2", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(WasmMsg::Execute {
+            contract_addr: "staking".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "addr2".to_string(),
+                amount: Uint128::from(3u128),
+            })
+            .unwrap(),
+            funds: vec![],
+        })]
+    );
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::ClaimedRewards {
+            staker_addr: Addr::unchecked("addr2"),
+            staking_token: Some(Addr::unchecked("staking")),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        from_binary::<ClaimedRewardsResponse>(&res)
+            .unwrap()
+            .claimed_rewards,
+        vec![]
+    );
+}
+
+#[test]
+fn test_pool_stats() {
+    let mut deps = _setup_staking(Some(100));
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr2".to_string(),
+        amount: Uint128::from(50u128),
+        msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+    });
+    execute(deps.as_mut(), mock_env(), mock_info("staking", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::UpdateUnbondOption {
+        staking_token: Addr::unchecked("staking"),
+        period: 20,
+        fee: Decimal::from_ratio(1u128, 10u128),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    // closing the position pays out the rewards as well
+    let msg = ExecuteMsg::Unbond {
+        staking_token: Addr::unchecked("staking"),
+        amount: Uint128::from(100u128),
+        unbond_period: Some(20),
+        zap_out: None,
+        staker_addr: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("addr", &[]), msg).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::PoolStats {
+            staking_token: Addr::unchecked("staking"),
+        },
+    )
+    .unwrap();
+    let rewards = vec![
+        Asset {
+            info: AssetInfo::NativeToken {
+                denom: ORAI_DENOM.to_string(),
+            },
+            amount: Uint128::from(99u128),
+        },
+        Asset {
+            info: AssetInfo::NativeToken {
+                denom: ATOM_DENOM.to_string(),
+            },
+            amount: Uint128::from(199u128),
+        },
+    ];
+    assert_eq!(
+        from_binary::<PoolStatsResponse>(&res).unwrap(),
+        PoolStatsResponse {
+            staking_token: Addr::unchecked("staking"),
+            rewards_deposited: rewards.clone(),
+            rewards_claimed: rewards,
+            total_bonded: Uint128::from(150u128),
+            total_unbonded: Uint128::from(100u128),
+            unbond_fees: Uint128::from(10u128),
+            unique_stakers: 2,
+        }
+    );
+}
+
+#[test]
+fn test_pool_stats_stranded_reward() {
+    let mut deps = mock_dependencies_with_balance(&[]);
+    let msg = InstantiateMsg {
+        owner: Some(Addr::unchecked("owner")),
+        rewarder: Addr::unchecked("rewarder"),
+        withdraw_fee_receiver: Addr::unchecked("withdraw_fee_receiver"),
+        timelock_delay: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr", &[]), msg).unwrap();
+    let msg = ExecuteMsg::RegisterAsset {
+        staking_token: Addr::unchecked("staking"),
+        unbonding_period: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let rewards_per_sec = |denom: &str| ExecuteMsg::UpdateRewardsPerSec {
+        staking_token: Addr::unchecked("staking"),
+        assets: vec![Asset {
+            info: AssetInfo::NativeToken {
+                denom: denom.to_string(),
+            },
+            amount: 100u128.into(),
+        }],
+    };
+    let deposit = ExecuteMsg::DepositReward {
+        rewards: vec![RewardMsg {
+            staking_token: Addr::unchecked("staking"),
+            total_accumulation_amount: Uint128::from(100u128),
+        }],
+    };
+
+    // the reward stranded while the pool is empty is paid with the rates it is deposited at
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        rewards_per_sec(ORAI_DENOM),
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("rewarder", &[]),
+        deposit.clone(),
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        rewards_per_sec(ATOM_DENOM),
+    )
+    .unwrap();
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr".to_string(),
+        amount: Uint128::from(100u128),
+        msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+    });
+    execute(deps.as_mut(), mock_env(), mock_info("staking", &[]), msg).unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("rewarder", &[]),
+        deposit,
+    )
+    .unwrap();
+
+    let msg = ExecuteMsg::Withdraw {
+        staking_token: None,
+        swap_to: None,
+        max_spread: None,
+        belief_prices: vec![],
+        staker_addr: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("addr", &[]), msg).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::PoolStats {
+            staking_token: Addr::unchecked("staking"),
+        },
+    )
+    .unwrap();
+    let stats = from_binary::<PoolStatsResponse>(&res).unwrap();
+    let orai = Asset {
+        info: AssetInfo::NativeToken {
+            denom: ORAI_DENOM.to_string(),
+        },
+        amount: Uint128::from(100u128),
+    };
+    let atom = Asset {
+        info: AssetInfo::NativeToken {
+            denom: ATOM_DENOM.to_string(),
+        },
+        amount: Uint128::from(100u128),
+    };
+    assert_eq!(stats.rewards_deposited, vec![orai.clone(), atom.clone()]);
+    assert_eq!(stats.rewards_claimed, vec![atom, orai]);
+}
+
+#[test]
+fn test_claimed_rewards() {
+    let mut deps = _setup_staking(Some(100));
+
+    // the rewards info is removed with the position, the claimed rewards are kept
+    let msg = ExecuteMsg::Unbond {
+        staking_token: Addr::unchecked("staking"),
+        amount: Uint128::from(100u128),
+        unbond_period: None,
+        zap_out: None,
+        staker_addr: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("addr", &[]), msg).unwrap();
+
+    let claimed_rewards = |staker_addr: &str, staking_token: Option<Addr>| {
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::ClaimedRewards {
+                staker_addr: Addr::unchecked(staker_addr),
+                staking_token,
+            },
+        )
+        .unwrap();
+        from_binary::<ClaimedRewardsResponse>(&res).unwrap()
+    };
+
+    let expected = ClaimedRewardsResponse {
+        staker_addr: Addr::unchecked("addr"),
+        claimed_rewards: vec![ClaimedRewardsItem {
+            staking_token: Addr::unchecked("staking"),
+            claimed: vec![
+                Asset {
+                    info: AssetInfo::NativeToken {
+                        denom: ORAI_DENOM.to_string(),
+                    },
+                    amount: Uint128::from(99u128),
+                },
+                Asset {
+                    info: AssetInfo::NativeToken {
+                        denom: ATOM_DENOM.to_string(),
+                    },
+                    amount: Uint128::from(199u128),
+                },
+            ],
+        }],
+    };
+    assert_eq!(claimed_rewards("addr", None), expected);
+    assert_eq!(
+        claimed_rewards("addr", Some(Addr::unchecked("staking"))),
+        expected
+    );
+    assert_eq!(
+        claimed_rewards("addr2", None),
+        ClaimedRewardsResponse {
+            staker_addr: Addr::unchecked("addr2"),
+            claimed_rewards: vec![],
+        }
+    );
+}
+
+#[test]
+fn test_query_pools() {
+    let mut deps = _setup_staking(Some(100));
+
+    let msg = ExecuteMsg::RegisterAsset {
+        staking_token: Addr::unchecked("staking2"),
+        unbonding_period: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+    let msg = ExecuteMsg::UpdateUnbondOption {
+        staking_token: Addr::unchecked("staking"),
+        period: 20,
+        fee: Decimal::percent(10),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let query_pools = |start_after: Option<Addr>,
+                       limit: Option<u32>,
+                       status: Option<PoolStatus>,
+                       has_rewards: Option<bool>| {
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::Pools {
+                start_after,
+                limit,
+                order: None,
+                status,
+                has_rewards,
+            },
+        )
+        .unwrap();
+        from_binary::<PoolsResponse>(&res).unwrap()
+    };
+    let staking_tokens = |pools: Vec<PoolsResponseItem>| {
+        pools
+            .into_iter()
+            .map(|pool| pool.pool_info.staking_token)
+            .collect::<Vec<Addr>>()
+    };
+
+    let res = query_pools(None, None, None, None);
+    assert_eq!(res.next_start_after, None);
+    let pools = res.pools;
+    assert_eq!(pools.len(), 2);
+    let staking_pool = pools
+        .iter()
+        .find(|pool| pool.pool_info.staking_token == Addr::unchecked("staking"))
+        .unwrap();
+    assert_eq!(staking_pool.status, PoolStatus::Active);
+    assert_eq!(staking_pool.pool_info.unbonding_period, Some(100));
+    assert_eq!(
+        staking_pool.unbond_options,
+        vec![UnbondOptionResponse {
+            period: 20,
+            fee: Decimal::percent(10),
+        }]
+    );
+    assert!(!staking_pool.rewards_per_sec.is_empty());
+
+    // the next page starts after the last pool of the previous one
+    let first_page = query_pools(None, Some(1), None, None);
+    assert_eq!(first_page.pools.len(), 1);
+    let second_page = query_pools(first_page.next_start_after.clone(), Some(1), None, None);
+    assert_eq!(second_page.pools.len(), 1);
+    assert_ne!(
+        staking_tokens(first_page.pools),
+        staking_tokens(second_page.pools)
+    );
+
+    assert_eq!(
+        staking_tokens(query_pools(None, None, Some(PoolStatus::Active), None).pools),
+        vec![Addr::unchecked("staking")]
+    );
+    assert_eq!(
+        staking_tokens(query_pools(None, None, Some(PoolStatus::Empty), None).pools),
+        vec![Addr::unchecked("staking2")]
+    );
+    assert_eq!(
+        staking_tokens(query_pools(None, None, None, Some(true)).pools),
+        vec![Addr::unchecked("staking")]
+    );
+    assert_eq!(
+        staking_tokens(query_pools(None, None, None, Some(false)).pools),
+        vec![Addr::unchecked("staking2")]
+    );
+
+    // the limit bounds the scanned pools, the filtered ones still move the page forward
+    let mut matched = vec![];
+    let mut start_after = None;
+    for _ in 0..3 {
+        let res = query_pools(start_after, Some(1), Some(PoolStatus::Empty), None);
+        assert!(res.pools.len() <= 1);
+        matched.extend(staking_tokens(res.pools));
+        start_after = res.next_start_after;
+        if start_after.is_none() {
+            break;
+        }
+    }
+    assert_eq!(start_after, None);
+    assert_eq!(matched, vec![Addr::unchecked("staking2")]);
+}
+
+#[test]
+fn test_staker_positions() {
+    let mut deps = _setup_staking(Some(100));
+
+    let msg = ExecuteMsg::RegisterAsset {
+        staking_token: Addr::unchecked("staking2"),
+        unbonding_period: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+    let bond = |staker: &str, amount: u128| {
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: staker.to_string(),
+            amount: Uint128::from(amount),
+            msg: to_binary(&Cw20HookMsg::Bond {}).unwrap(),
+        })
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("staking", &[]),
+        bond("addr2", 50),
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("staking2", &[]),
+        bond("addr", 30),
+    )
+    .unwrap();
+
+    let position = |staking_token: &str, staker_addr: &str, amount: u128| StakerPositionResponse {
+        staking_token: Addr::unchecked(staking_token),
+        staker_addr: Addr::unchecked(staker_addr),
+        bond_amount: Uint128::from(amount),
+    };
+    let pool_stakers = |deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>| {
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::PoolStakers {
+                staking_token: Addr::unchecked("staking"),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+        from_binary::<PoolStakersResponse>(&res).unwrap()
+    };
+
+    let res = pool_stakers(&deps);
+    assert_eq!(res.staker_count, 2);
+    assert_eq!(res.positions.len(), 2);
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::TopStakers {
+            staking_token: Addr::unchecked("staking"),
+            limit: None,
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        from_binary::<Vec<StakerPositionResponse>>(&res).unwrap(),
+        vec![
+            position("staking", "addr", 100),
+            position("staking", "addr2", 50)
+        ]
+    );
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::StakerPools {
+            staker_addr: Addr::unchecked("addr"),
+            start_after: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+    let mut staker_pools = from_binary::<Vec<StakerPositionResponse>>(&res).unwrap();
+    staker_pools.sort_by(|a, b| a.staking_token.cmp(&b.staking_token));
+    assert_eq!(
+        staker_pools,
+        vec![
+            position("staking", "addr", 100),
+            position("staking2", "addr", 30)
+        ]
+    );
+
+    // closed positions leave the indexes
+    let msg = ExecuteMsg::Unbond {
+        staking_token: Addr::unchecked("staking"),
+        amount: Uint128::from(50u128),
+        unbond_period: None,
+        zap_out: None,
+        staker_addr: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("addr2", &[]), msg).unwrap();
+    let res = pool_stakers(&deps);
+    assert_eq!(res.staker_count, 1);
+    assert_eq!(res.positions, vec![position("staking", "addr", 100)]);
+
+    // positions bonded before the indexes are added by the keeper
+    let asset_key = deps.api.addr_canonicalize("staking").unwrap();
+    let staker_addr = deps.api.addr_canonicalize("addr").unwrap();
+    staker_positions()
+        .remove(deps.as_mut().storage, (&asset_key, &staker_addr))
+        .unwrap();
+    POOL_STAKER_COUNTS.remove(deps.as_mut().storage, &asset_key);
+    assert_eq!(pool_stakers(&deps).staker_count, 0);
+
+    let msg = ExecuteMsg::IndexStakerPositions {
+        staking_token: Addr::unchecked("staking"),
+        start_after: None,
+        limit: None,
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(res, StdError::generic_err("unauthorized"));
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        msg.clone(),
+    )
+    .unwrap();
+    // indexing again does not count the stakers twice
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let res = pool_stakers(&deps);
+    assert_eq!(res.staker_count, 1);
+    assert_eq!(res.positions, vec![position("staking", "addr", 100)]);
+}
@@ -0,0 +1,286 @@
+use crate::contract::{execute, instantiate, query, reply};
+use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg, RewardInfoResponse};
+use crate::zap::ZAP_BOND_REPLY_ID;
+use cosmwasm_std::testing::{
+    mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage,
+};
+use cosmwasm_std::{
+    coin, from_binary, to_binary, Addr, Attribute, ContractResult, Decimal, Event, OwnedDeps,
+    Reply, StdError, SubMsg, SubMsgResponse, SubMsgResult, SystemError, SystemResult, Uint128,
+    WasmMsg, WasmQuery,
+};
+use cw20::Cw20ExecuteMsg;
+use oraiswap::asset::{Asset, AssetInfo, PairInfo, ORAI_DENOM};
+
+fn _pair_asset_infos() -> [AssetInfo; 2] {
+    [
+        AssetInfo::NativeToken {
+            denom: ORAI_DENOM.to_string(),
+        },
+        AssetInfo::Token {
+            contract_addr: Addr::unchecked("asset"),
+        },
+    ]
+}
+
+fn _pair_info(contract_addr: &str) -> PairInfo {
+    PairInfo {
+        oracle_addr: Addr::unchecked("oracle"),
+        asset_infos: _pair_asset_infos(),
+        contract_addr: Addr::unchecked(contract_addr),
+        liquidity_token: Addr::unchecked("lptoken"),
+        commission_rate: "0.003".to_string(),
+    }
+}
+
+// "fake_pair" reports the same assets as "pair" but the factory only lists "pair"
+fn _set_pair_querier(deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>) {
+    deps.querier.update_wasm(|query| match query {
+        WasmQuery::Smart { contract_addr, .. } => {
+            let pair_addr = match contract_addr.as_str() {
+                "factory" => "pair",
+                pair_addr => pair_addr,
+            };
+            SystemResult::Ok(ContractResult::Ok(
+                to_binary(&_pair_info(pair_addr)).unwrap(),
+            ))
+        }
+        _ => SystemResult::Err(SystemError::UnsupportedRequest {
+            kind: "wasm".to_string(),
+        }),
+    });
+}
+
+fn _setup_zap() -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+    let mut deps = mock_dependencies();
+
+    let msg = InstantiateMsg {
+        owner: Some(Addr::unchecked("owner")),
+        rewarder: Addr::unchecked("rewarder"),
+        withdraw_fee_receiver: Addr::unchecked("withdraw_fee_receiver"),
+        timelock_delay: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::RegisterAsset {
+        staking_token: Addr::unchecked("lptoken"),
+        unbonding_period: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::UpdateConfig {
+        rewarder: None,
+        owner: None,
+        withdraw_fee_receiver: None,
+        router: None,
+        empty_pool_policy: None,
+        fee_collector: None,
+        factory: Some(Addr::unchecked("factory")),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    _set_pair_querier(&mut deps);
+    deps
+}
+
+// the chain sets the reserved _contract_address key, which attr() refuses in debug builds
+fn _wasm_event(attributes: &[(&str, &str)]) -> Event {
+    let mut event = Event::new("wasm");
+    event.attributes = attributes
+        .iter()
+        .map(|(key, value)| Attribute {
+            key: key.to_string(),
+            value: value.to_string(),
+        })
+        .collect();
+    event
+}
+
+fn _provide_reply(pair_addr: &str, share: u128) -> Reply {
+    Reply {
+        id: ZAP_BOND_REPLY_ID,
+        result: SubMsgResult::Ok(SubMsgResponse {
+            events: vec![_wasm_event(&[
+                ("_contract_address", pair_addr),
+                ("action", "provide_liquidity"),
+                ("share", &share.to_string()),
+            ])],
+            data: None,
+        }),
+    }
+}
+
+fn _zap_assets() -> [Asset; 2] {
+    let [orai, asset] = _pair_asset_infos();
+    [
+        Asset {
+            info: orai,
+            amount: Uint128::from(100u128),
+        },
+        Asset {
+            info: asset,
+            amount: Uint128::from(200u128),
+        },
+    ]
+}
+
+#[test]
+fn test_zap_bond() {
+    let mut deps = _setup_zap();
+
+    // funds do not match the native asset
+    let msg = ExecuteMsg::ZapBond {
+        pair_addr: Addr::unchecked("pair"),
+        assets: _zap_assets(),
+        slippage_tolerance: None,
+        minimum_bond_amount: None,
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr", &[coin(50u128, ORAI_DENOM)]),
+        msg.clone(),
+    );
+    match res {
+        Err(StdError::GenericErr { msg, .. }) => assert_eq!(
+            msg,
+            "Native token balance mismatch between the argument and the transferred"
+        ),
+        _ => panic!("Must return native token mismatch error"),
+    }
+
+    // funds which are not provided would stay in the contract
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr", &[coin(100u128, ORAI_DENOM), coin(10u128, "uatom")]),
+        msg.clone(),
+    );
+    match res {
+        Err(StdError::GenericErr { msg, .. }) => {
+            assert_eq!(msg, "Sent funds must be one of the provided assets")
+        }
+        _ => panic!("Must return unexpected funds error"),
+    }
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr", &[coin(100u128, ORAI_DENOM)]),
+        msg,
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(WasmMsg::Execute {
+                contract_addr: "asset".to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::TransferFrom {
+                    owner: "addr".to_string(),
+                    recipient: mock_env().contract.address.to_string(),
+                    amount: Uint128::from(200u128),
+                })
+                .unwrap(),
+                funds: vec![],
+            }),
+            SubMsg::new(WasmMsg::Execute {
+                contract_addr: "asset".to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::IncreaseAllowance {
+                    spender: "pair".to_string(),
+                    amount: Uint128::from(200u128),
+                    expires: None,
+                })
+                .unwrap(),
+                funds: vec![],
+            }),
+            SubMsg::reply_on_success(
+                WasmMsg::Execute {
+                    contract_addr: "pair".to_string(),
+                    msg: to_binary(&oraiswap::pair::ExecuteMsg::ProvideLiquidity {
+                        assets: _zap_assets(),
+                        slippage_tolerance: None,
+                        receiver: None,
+                    })
+                    .unwrap(),
+                    funds: vec![coin(100u128, ORAI_DENOM)],
+                },
+                ZAP_BOND_REPLY_ID,
+            ),
+        ]
+    );
+
+    // the share minted by another contract is ignored
+    let mut provide_reply = _provide_reply("pair", 150);
+    if let SubMsgResult::Ok(response) = &mut provide_reply.result {
+        response.events.insert(
+            0,
+            _wasm_event(&[("_contract_address", "other"), ("share", "1000")]),
+        );
+    }
+    reply(deps.as_mut(), mock_env(), provide_reply).unwrap();
+
+    let res: RewardInfoResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::RewardInfo {
+                staker_addr: Addr::unchecked("addr"),
+                staking_token: Some(Addr::unchecked("lptoken")),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.reward_infos[0].bond_amount, Uint128::from(150u128));
+}
+
+#[test]
+fn test_zap_bond_slippage() {
+    let mut deps = _setup_zap();
+
+    let msg = ExecuteMsg::ZapBond {
+        pair_addr: Addr::unchecked("pair"),
+        assets: _zap_assets(),
+        slippage_tolerance: Some(Decimal::percent(1)),
+        minimum_bond_amount: Some(Uint128::from(200u128)),
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr", &[coin(100u128, ORAI_DENOM)]),
+        msg,
+    )
+    .unwrap();
+
+    let res = reply(deps.as_mut(), mock_env(), _provide_reply("pair", 150));
+    match res {
+        Err(StdError::GenericErr { msg, .. }) => {
+            assert_eq!(msg, "Bond amount 150 is less than minimum bond amount 200")
+        }
+        _ => panic!("Must return slippage error"),
+    }
+}
+
+#[test]
+fn test_zap_bond_unlisted_pair() {
+    let mut deps = _setup_zap();
+
+    let msg = ExecuteMsg::ZapBond {
+        pair_addr: Addr::unchecked("fake_pair"),
+        assets: _zap_assets(),
+        slippage_tolerance: None,
+        minimum_bond_amount: None,
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr", &[coin(100u128, ORAI_DENOM)]),
+        msg,
+    );
+    match res {
+        Err(StdError::GenericErr { msg, .. }) => {
+            assert_eq!(msg, "The pair is not listed by the factory")
+        }
+        _ => panic!("Must return unlisted pair error"),
+    }
+}
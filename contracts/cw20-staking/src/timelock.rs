@@ -0,0 +1,241 @@
+use crate::fee::{validate_performance_fee, validate_unbond_fee_split};
+use crate::msg::{AdminChange, PendingAdminChangeResponse, Role, UnbondFeeSplit};
+use crate::roles::assert_role;
+use crate::staking::{validate_max_unbond_fee, validate_unbond_option, validate_unbonding_period};
+use crate::state::{
+    read_config, store_config, store_unbonding_period, PendingAdminChange, DEFAULT_LIMIT,
+    DEFAULT_TIMELOCK_DELAY, MAX_LIMIT, MAX_UNBOND_FEE, PENDING_ADMIN_CHANGES,
+    PENDING_ADMIN_CHANGE_COUNT, PERFORMANCE_FEES, TIMELOCK_DELAY, UNBOND_FEE_SPLIT, UNBOND_OPTIONS,
+};
+use cosmwasm_std::{Deps, DepsMut, Env, MessageInfo, Order, Response, StdError, StdResult};
+use cw_storage_plus::Bound;
+
+// the caller checks the sender, the change is applied right away while no delay is set
+pub fn submit_admin_change(deps: DepsMut, env: &Env, change: AdminChange) -> StdResult<Response> {
+    let delay = TIMELOCK_DELAY
+        .may_load(deps.storage)?
+        .unwrap_or(DEFAULT_TIMELOCK_DELAY);
+    if delay == 0 {
+        return apply_admin_change(deps, change);
+    }
+
+    let id = PENDING_ADMIN_CHANGE_COUNT
+        .may_load(deps.storage)?
+        .unwrap_or_default()
+        + 1;
+    PENDING_ADMIN_CHANGE_COUNT.save(deps.storage, &id)?;
+    let eta = env.block.time.seconds() + delay;
+    PENDING_ADMIN_CHANGES.save(deps.storage, id, &PendingAdminChange { change, eta })?;
+
+    Ok(Response::new().add_attributes([
+        ("action", "queue_admin_change"),
+        ("change_id", &id.to_string()),
+        ("eta", &eta.to_string()),
+    ]))
+}
+
+fn apply_admin_change(deps: DepsMut, change: AdminChange) -> StdResult<Response> {
+    match change {
+        AdminChange::UnbondingPeriod {
+            staking_token,
+            unbonding_period,
+        } => {
+            // an option may have been added while the change was queued
+            validate_unbonding_period(deps.storage, &staking_token, unbonding_period)?;
+            let asset_key = deps.api.addr_canonicalize(staking_token.as_str())?;
+            store_unbonding_period(deps.storage, &asset_key, unbonding_period)?;
+
+            Ok(Response::new()
+                .add_attribute("action", "update_unbonding_period")
+                .add_attribute("unbonding_period", unbonding_period.to_string()))
+        }
+        AdminChange::UnbondOption {
+            staking_token,
+            period,
+            fee,
+        } => {
+            // the pool or the other options may have changed while the change was queued
+            validate_unbond_option(deps.storage, deps.api, &staking_token, period, fee)?;
+            UNBOND_OPTIONS.save(deps.storage, (&staking_token, period), &fee)?;
+
+            Ok(Response::new()
+                .add_attribute("action", "update_instant_withdraw_option")
+                .add_attribute("staking_token", staking_token.to_string())
+                .add_attribute("unbonding_period", period.to_string())
+                .add_attribute("fee", fee.to_string()))
+        }
+        AdminChange::RemoveUnbondOption {
+            staking_token,
+            period,
+        } => {
+            UNBOND_OPTIONS.remove(deps.storage, (&staking_token, period));
+
+            Ok(Response::new()
+                .add_attribute("action", "remove_instant_withdraw_option")
+                .add_attribute("staking_token", staking_token.to_string())
+                .add_attribute("unbonding_period", period.to_string()))
+        }
+        AdminChange::WithdrawFeeReceiver {
+            withdraw_fee_receiver,
+        } => {
+            let mut config = read_config(deps.storage)?;
+            config.withdraw_fee_receiver =
+                deps.api.addr_canonicalize(withdraw_fee_receiver.as_str())?;
+            store_config(deps.storage, &config)?;
+
+            Ok(Response::new()
+                .add_attribute("action", "update_withdraw_fee_receiver")
+                .add_attribute("withdraw_fee_receiver", withdraw_fee_receiver.to_string()))
+        }
+        AdminChange::PerformanceFee { staking_token, fee } => {
+            // the pool may have changed while the change was queued
+            validate_performance_fee(deps.storage, deps.api, &staking_token, fee)?;
+            let asset_key = deps.api.addr_canonicalize(staking_token.as_str())?;
+            PERFORMANCE_FEES.save(deps.storage, &asset_key, &fee)?;
+
+            Ok(Response::new().add_attributes([
+                ("action", "update_performance_fee"),
+                ("staking_token", staking_token.as_str()),
+                ("fee", &fee.to_string()),
+            ]))
+        }
+        AdminChange::FeeCollector { fee_collector } => {
+            let mut config = read_config(deps.storage)?;
+            config.fee_collector = Some(deps.api.addr_canonicalize(fee_collector.as_str())?);
+            store_config(deps.storage, &config)?;
+
+            Ok(Response::new()
+                .add_attribute("action", "update_fee_collector")
+                .add_attribute("fee_collector", fee_collector.to_string()))
+        }
+        AdminChange::TimelockDelay { delay } => {
+            TIMELOCK_DELAY.save(deps.storage, &delay)?;
+
+            Ok(Response::new()
+                .add_attribute("action", "update_timelock_delay")
+                .add_attribute("delay", delay.to_string()))
+        }
+        AdminChange::Owner { owner } => {
+            let mut config = read_config(deps.storage)?;
+            config.owner = deps.api.addr_canonicalize(owner.as_str())?;
+            store_config(deps.storage, &config)?;
+
+            Ok(Response::new()
+                .add_attribute("action", "update_owner")
+                .add_attribute("owner", owner.to_string()))
+        }
+        AdminChange::Rewarder { rewarder } => {
+            let mut config = read_config(deps.storage)?;
+            config.rewarder = deps.api.addr_canonicalize(rewarder.as_str())?;
+            store_config(deps.storage, &config)?;
+
+            Ok(Response::new()
+                .add_attribute("action", "update_rewarder")
+                .add_attribute("rewarder", rewarder.to_string()))
+        }
+        AdminChange::UnbondFeeSplit {
+            treasury,
+            stakers,
+            burn,
+        } => {
+            let split = UnbondFeeSplit {
+                treasury,
+                stakers,
+                burn,
+            };
+            validate_unbond_fee_split(&split)?;
+            UNBOND_FEE_SPLIT.save(deps.storage, &split)?;
+
+            Ok(Response::new().add_attributes([
+                ("action", "update_unbond_fee_split"),
+                ("treasury", &split.treasury.to_string()),
+                ("stakers", &split.stakers.to_string()),
+                ("burn", &split.burn.to_string()),
+            ]))
+        }
+        AdminChange::MaxUnbondFee { max_fee } => {
+            validate_max_unbond_fee(max_fee)?;
+            MAX_UNBOND_FEE.save(deps.storage, &max_fee)?;
+
+            Ok(Response::new()
+                .add_attribute("action", "update_max_unbond_fee")
+                .add_attribute("max_fee", max_fee.to_string()))
+        }
+    }
+}
+
+// a shorter delay must wait for the current one, otherwise it could skip the queue
+pub fn update_timelock_delay(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    delay: u64,
+) -> StdResult<Response> {
+    let config = read_config(deps.storage)?;
+    if config.owner != deps.api.addr_canonicalize(info.sender.as_str())? {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    let current = TIMELOCK_DELAY
+        .may_load(deps.storage)?
+        .unwrap_or(DEFAULT_TIMELOCK_DELAY);
+    if delay >= current {
+        return apply_admin_change(deps, AdminChange::TimelockDelay { delay });
+    }
+    submit_admin_change(deps, &env, AdminChange::TimelockDelay { delay })
+}
+
+pub fn execute_admin_change(deps: DepsMut, env: Env, id: u64) -> StdResult<Response> {
+    let pending = PENDING_ADMIN_CHANGES
+        .may_load(deps.storage, id)?
+        .ok_or_else(|| StdError::generic_err("Admin change not found"))?;
+    if env.block.time.seconds() < pending.eta {
+        return Err(StdError::generic_err("Admin change is still timelocked"));
+    }
+    PENDING_ADMIN_CHANGES.remove(deps.storage, id);
+
+    let response = apply_admin_change(deps, pending.change)?;
+    Ok(response.add_attribute("change_id", id.to_string()))
+}
+
+pub fn cancel_admin_change(deps: DepsMut, info: MessageInfo, id: u64) -> StdResult<Response> {
+    assert_role(deps.as_ref(), &info.sender, Role::Pauser)?;
+
+    if !PENDING_ADMIN_CHANGES.has(deps.storage, id) {
+        return Err(StdError::generic_err("Admin change not found"));
+    }
+    PENDING_ADMIN_CHANGES.remove(deps.storage, id);
+
+    Ok(Response::new().add_attributes([
+        ("action", "cancel_admin_change"),
+        ("change_id", &id.to_string()),
+    ]))
+}
+
+pub fn query_timelock_delay(deps: Deps) -> StdResult<u64> {
+    Ok(TIMELOCK_DELAY
+        .may_load(deps.storage)?
+        .unwrap_or(DEFAULT_TIMELOCK_DELAY))
+}
+
+pub fn query_pending_admin_changes(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<PendingAdminChangeResponse>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    PENDING_ADMIN_CHANGES
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (id, pending) = item?;
+            Ok(PendingAdminChangeResponse {
+                id,
+                change: pending.change,
+                eta: pending.eta,
+            })
+        })
+        .collect()
+}
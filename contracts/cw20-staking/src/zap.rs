@@ -0,0 +1,366 @@
+use crate::msg::ZapOutMsg;
+use crate::roles::assert_not_paused;
+use crate::staking::bond;
+use crate::state::{
+    read_config, read_pool_info, ZapBondCache, ZapOutCache, ZAP_BOND_CACHE, ZAP_OUT_CACHE,
+};
+use cosmwasm_std::{
+    to_binary, Addr, Api, Coin, CosmosMsg, Decimal, DepsMut, Env, MessageInfo, QuerierWrapper,
+    Reply, Response, StdError, StdResult, Storage, SubMsg, SubMsgResult, Uint128, WasmMsg,
+};
+use cw20::Cw20ExecuteMsg;
+use oraiswap::asset::{Asset, AssetInfo, PairInfo};
+use oraiswap::pair::{
+    Cw20HookMsg as PairCw20HookMsg, ExecuteMsg as PairExecuteMsg, QueryMsg as PairQueryMsg,
+};
+use oraiswap::querier::{query_pair_info, query_token_balance};
+
+pub const ZAP_BOND_REPLY_ID: u64 = 1;
+pub const ZAP_OUT_REPLY_ID: u64 = 2;
+
+// provide both assets to the oraiswap pair, then bond the minted lp for the sender in the reply
+pub fn zap_bond(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pair_addr: Addr,
+    assets: [Asset; 2],
+    slippage_tolerance: Option<Decimal>,
+    minimum_bond_amount: Option<Uint128>,
+) -> StdResult<Response> {
+    assert_not_paused(deps.storage)?;
+
+    let pair_info = query_factory_pair(deps.storage, deps.api, &deps.querier, &pair_addr)?;
+    let staking_token = pair_info.liquidity_token;
+
+    // the minted lp must belong to a registered pool
+    let asset_key = deps.api.addr_canonicalize(staking_token.as_str())?;
+    if read_pool_info(deps.storage, &asset_key).is_err() {
+        return Err(StdError::generic_err(
+            "The liquidity token of this pair is not registered",
+        ));
+    }
+
+    // the pair only takes the provided assets, anything else would be left in this contract
+    if info.funds.iter().any(|coin| {
+        !assets.iter().any(|asset| match &asset.info {
+            AssetInfo::NativeToken { denom } => denom.eq(&coin.denom),
+            AssetInfo::Token { .. } => false,
+        })
+    }) {
+        return Err(StdError::generic_err(
+            "Sent funds must be one of the provided assets",
+        ));
+    }
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+    let mut funds: Vec<Coin> = vec![];
+    for asset in assets.iter() {
+        if !pair_info.asset_infos.contains(&asset.info) {
+            return Err(StdError::generic_err("Asset does not belong to the pair"));
+        }
+        match &asset.info {
+            AssetInfo::NativeToken { denom } => {
+                let sent = info
+                    .funds
+                    .iter()
+                    .find(|c| c.denom.eq(denom))
+                    .map(|c| c.amount)
+                    .unwrap_or_default();
+                if sent != asset.amount {
+                    return Err(StdError::generic_err(
+                        "Native token balance mismatch between the argument and the transferred",
+                    ));
+                }
+                if !asset.amount.is_zero() {
+                    funds.push(Coin {
+                        denom: denom.clone(),
+                        amount: asset.amount,
+                    });
+                }
+            }
+            AssetInfo::Token { contract_addr } => {
+                // pull the token from the sender using its allowance, then let the pair spend it
+                messages.push(
+                    WasmMsg::Execute {
+                        contract_addr: contract_addr.to_string(),
+                        msg: to_binary(&Cw20ExecuteMsg::TransferFrom {
+                            owner: info.sender.to_string(),
+                            recipient: env.contract.address.to_string(),
+                            amount: asset.amount,
+                        })?,
+                        funds: vec![],
+                    }
+                    .into(),
+                );
+                messages.push(
+                    WasmMsg::Execute {
+                        contract_addr: contract_addr.to_string(),
+                        msg: to_binary(&Cw20ExecuteMsg::IncreaseAllowance {
+                            spender: pair_addr.to_string(),
+                            amount: asset.amount,
+                            expires: None,
+                        })?,
+                        funds: vec![],
+                    }
+                    .into(),
+                );
+            }
+        }
+    }
+    funds.sort_by(|a, b| a.denom.cmp(&b.denom));
+
+    ZAP_BOND_CACHE.save(
+        deps.storage,
+        &ZapBondCache {
+            staker_addr: info.sender.clone(),
+            staking_token: staking_token.clone(),
+            pair_addr: pair_addr.clone(),
+            minimum_bond_amount,
+        },
+    )?;
+
+    let provide_msg = SubMsg::reply_on_success(
+        WasmMsg::Execute {
+            contract_addr: pair_addr.to_string(),
+            msg: to_binary(&PairExecuteMsg::ProvideLiquidity {
+                assets,
+                slippage_tolerance,
+                receiver: None,
+            })?,
+            funds,
+        },
+        ZAP_BOND_REPLY_ID,
+    );
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_submessage(provide_msg)
+        .add_attributes([
+            ("action", "zap_bond"),
+            ("staker_addr", info.sender.as_str()),
+            ("pair_addr", pair_addr.as_str()),
+            ("staking_token", staking_token.as_str()),
+        ]))
+}
+
+// the minted amount is the share reported by the pair, the lp balance of this contract also
+// holds the other stakers' lp and could move within the provide call
+pub fn zap_bond_reply(deps: DepsMut, env: Env, msg: Reply) -> StdResult<Response> {
+    let cache = ZAP_BOND_CACHE.load(deps.storage)?;
+    ZAP_BOND_CACHE.remove(deps.storage);
+
+    let events = match msg.result {
+        SubMsgResult::Ok(response) => response.events,
+        SubMsgResult::Err(err) => return Err(StdError::generic_err(err)),
+    };
+    let bond_amount = events
+        .iter()
+        .filter(|event| {
+            event.ty == "wasm"
+                && event.attributes.iter().any(|attr| {
+                    attr.key == "_contract_address" && attr.value == cache.pair_addr.as_str()
+                })
+        })
+        .flat_map(|event| event.attributes.iter())
+        .find(|attr| attr.key == "share")
+        .map(|attr| attr.value.parse::<Uint128>())
+        .transpose()?
+        .unwrap_or_default();
+
+    if bond_amount.is_zero() {
+        return Err(StdError::generic_err("No liquidity token was minted"));
+    }
+
+    if let Some(minimum_bond_amount) = cache.minimum_bond_amount {
+        if bond_amount < minimum_bond_amount {
+            return Err(StdError::generic_err(format!(
+                "Bond amount {} is less than minimum bond amount {}",
+                bond_amount, minimum_bond_amount
+            )));
+        }
+    }
+
+    bond(
+        deps,
+        env,
+        cache.staker_addr,
+        cache.staking_token,
+        bond_amount,
+    )
+}
+
+// a pair answering Pair {} can be any contract, only the pairs listed by the oraiswap factory
+// for their own assets are trusted
+pub fn query_factory_pair(
+    storage: &dyn Storage,
+    api: &dyn Api,
+    querier: &QuerierWrapper,
+    pair_addr: &Addr,
+) -> StdResult<PairInfo> {
+    let factory = api.addr_humanize(
+        &read_config(storage)?
+            .factory
+            .ok_or_else(|| StdError::generic_err("Factory is not configured"))?,
+    )?;
+    let pair_info: PairInfo =
+        querier.query_wasm_smart(pair_addr.clone(), &PairQueryMsg::Pair {})?;
+    let factory_pair = query_pair_info(querier, factory, &pair_info.asset_infos)?;
+    if factory_pair.contract_addr.ne(pair_addr) {
+        return Err(StdError::generic_err(
+            "The pair is not listed by the factory",
+        ));
+    }
+    Ok(factory_pair)
+}
+
+pub fn validate_zap_out(
+    storage: &dyn Storage,
+    api: &dyn Api,
+    querier: &QuerierWrapper,
+    staking_token: &Addr,
+    zap_out: &ZapOutMsg,
+) -> StdResult<PairInfo> {
+    let pair_info = query_factory_pair(storage, api, querier, &zap_out.pair_addr)?;
+    if pair_info.liquidity_token.ne(staking_token) {
+        return Err(StdError::generic_err(
+            "The pair does not belong to the staking token",
+        ));
+    }
+    if zap_out
+        .minimum_receive
+        .iter()
+        .any(|asset| !pair_info.asset_infos.contains(&asset.info))
+    {
+        return Err(StdError::generic_err("Asset does not belong to the pair"));
+    }
+    Ok(pair_info)
+}
+
+// lp zapped out in the same transaction is withdrawn from the pair at once, so sum up the amounts
+pub fn merge_zap_out(
+    zap: Option<(Uint128, ZapOutMsg)>,
+    amount: Uint128,
+    other: ZapOutMsg,
+) -> (Uint128, ZapOutMsg) {
+    let (zap_amount, mut zap_out) = match zap {
+        None => return (amount, other),
+        Some(zap) => zap,
+    };
+    for asset in other.minimum_receive {
+        match zap_out
+            .minimum_receive
+            .iter_mut()
+            .find(|a| a.info.eq(&asset.info))
+        {
+            None => zap_out.minimum_receive.push(asset),
+            Some(minimum) => minimum.amount += asset.amount,
+        }
+    }
+    (zap_amount + amount, zap_out)
+}
+
+fn query_asset_balance(
+    querier: &QuerierWrapper,
+    asset_info: &AssetInfo,
+    account: &Addr,
+) -> StdResult<Uint128> {
+    match asset_info {
+        AssetInfo::NativeToken { denom } => Ok(querier.query_balance(account, denom)?.amount),
+        AssetInfo::Token { contract_addr } => {
+            query_token_balance(querier, contract_addr.clone(), account.clone())
+        }
+    }
+}
+
+// send the lp to the pair with WithdrawLiquidity, the reply forwards what actually arrived to the staker.
+// the balances are taken before the transaction sends anything, so this message must be the first one
+#[allow(clippy::too_many_arguments)]
+pub fn zap_out_lp(
+    storage: &mut dyn Storage,
+    api: &dyn Api,
+    querier: &QuerierWrapper,
+    env: &Env,
+    staker_addr: &Addr,
+    staking_token: &Addr,
+    amount: Uint128,
+    zap_out: &ZapOutMsg,
+) -> StdResult<Response> {
+    // the pair may have been delisted while the lp was locked
+    let pair_info = validate_zap_out(storage, api, querier, staking_token, zap_out)?;
+
+    let prev_balances = pair_info
+        .asset_infos
+        .iter()
+        .map(|info| {
+            Ok(Asset {
+                info: info.clone(),
+                amount: query_asset_balance(querier, info, &env.contract.address)?,
+            })
+        })
+        .collect::<StdResult<Vec<Asset>>>()?;
+    ZAP_OUT_CACHE.save(
+        storage,
+        &ZapOutCache {
+            staker_addr: staker_addr.clone(),
+            prev_balances,
+            minimum_receive: zap_out.minimum_receive.clone(),
+        },
+    )?;
+
+    let withdraw_msg = SubMsg::reply_on_success(
+        WasmMsg::Execute {
+            contract_addr: staking_token.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Send {
+                contract: zap_out.pair_addr.to_string(),
+                amount,
+                msg: to_binary(&PairCw20HookMsg::WithdrawLiquidity {})?,
+            })?,
+            funds: vec![],
+        },
+        ZAP_OUT_REPLY_ID,
+    );
+
+    Ok(Response::new()
+        .add_submessage(withdraw_msg)
+        .add_attributes([
+            ("action", "zap_out"),
+            ("staker_addr", staker_addr.as_str()),
+            ("amount", &amount.to_string()),
+            ("staking_token", staking_token.as_str()),
+            ("pair_addr", zap_out.pair_addr.as_str()),
+        ]))
+}
+
+pub fn zap_out_reply(deps: DepsMut, env: Env) -> StdResult<Response> {
+    let cache = ZAP_OUT_CACHE.load(deps.storage)?;
+    ZAP_OUT_CACHE.remove(deps.storage);
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+    for prev_balance in cache.prev_balances {
+        let balance =
+            query_asset_balance(&deps.querier, &prev_balance.info, &env.contract.address)?;
+        let refund_asset = Asset {
+            amount: balance.checked_sub(prev_balance.amount)?,
+            info: prev_balance.info,
+        };
+
+        if let Some(minimum) = cache
+            .minimum_receive
+            .iter()
+            .find(|a| a.info.eq(&refund_asset.info))
+        {
+            if refund_asset.amount < minimum.amount {
+                return Err(StdError::generic_err(format!(
+                    "Withdrawn amount {} is less than minimum receive amount {}",
+                    refund_asset.amount, minimum.amount
+                )));
+            }
+        }
+        if !refund_asset.amount.is_zero() {
+            messages.push(refund_asset.into_msg(None, &deps.querier, cache.staker_addr.clone())?);
+        }
+    }
+
+    Ok(Response::new().add_messages(messages))
+}
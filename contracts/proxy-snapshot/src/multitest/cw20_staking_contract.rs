@@ -34,6 +34,7 @@ impl Cw20Staking {
                 owner: Some(owner.clone()),
                 rewarder: Addr::unchecked("rewarder"),
                 withdraw_fee_receiver: Addr::unchecked("withdraw_fee_receiver"),
+                timelock_delay: None,
             },
             &[],
             "treasury contract",
@@ -77,6 +77,8 @@ fn test_query_snapshot_balance() {
             staking_token: asset_key.clone(),
             amount: Uint128::from(50u128),
             unbond_period: None,
+            zap_out: None,
+            staker_addr: None,
         },
         &[],
     )